@@ -1,4 +1,13 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use crate::container_task::{
+	backend::{Backend, Backends, CliBackend, ProcessBackend},
+	docker::DockerBackend,
+	logs::LogPolicy,
+	state::STATE_FILE,
+	supervisor::RestartPolicy,
+	ContainerConfig, DownloadConfig, HeadFollow,
+};
 
 /// Sub-commands supported by the collator.
 #[derive(Debug, clap::Subcommand)]
@@ -80,11 +89,162 @@ pub struct Cli {
 	#[arg(long)]
 	pub no_hardware_benchmarks: bool,
 
+	#[command(flatten)]
+	pub container: ContainerCli,
+
 	/// Relay chain arguments
 	#[arg(raw = true)]
 	pub relay_chain_args: Vec<String>,
 }
 
+/// Options of the task running the app of the sequencer's group.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ContainerCli {
+	/// Number of ranges of the app binary downloaded in parallel.
+	#[arg(long, default_value_t = 4)]
+	pub container_download_parallel: usize,
+
+	/// Cap on the app binary download rate in KiB/s, unlimited by default.
+	#[arg(long)]
+	pub container_download_rate: Option<u64>,
+
+	/// Runtime apps shipped as docker images run on.
+	#[arg(long, value_enum, default_value_t = ContainerEngine::Docker)]
+	pub container_engine: ContainerEngine,
+
+	/// Unix socket of the Docker Engine API.
+	#[arg(long, default_value = "/var/run/docker.sock")]
+	pub docker_socket: PathBuf,
+
+	/// Seconds before a crashed app instance is restarted, doubled on each further crash.
+	#[arg(long, default_value_t = 5)]
+	pub container_restart_backoff: u64,
+
+	/// Cap on the seconds between restarts of a crashed app instance.
+	#[arg(long, default_value_t = 300)]
+	pub container_max_restart_backoff: u64,
+
+	/// Crashes in a row after which an app instance is left down.
+	#[arg(long, default_value_t = 5)]
+	pub container_failure_budget: u32,
+
+	/// Seconds an app instance gets to exit after SIGTERM before it is killed.
+	#[arg(long, default_value_t = 30)]
+	pub container_stop_timeout: u64,
+
+	/// First port reserved for apps. Each app gets a range of 100 ports, the sequencer's app the
+	/// first one and the app of processed group `n` the range `n + 1`. The two instances of an app
+	/// get half of it each. Ranges never go past port 65535: the node does not start when the
+	/// sequencer's range does not fit, and does not run the apps of groups whose range does not.
+	#[arg(long, default_value_t = 30000)]
+	pub container_port_base: u16,
+
+	/// Relay chain blocks whose parachain heads drive app downloads and round switches. Best
+	/// blocks switch rounds at the tip, finalized ones lag behind but never see a reorg.
+	#[arg(long, value_enum, default_value_t = ContainerFollow::Best)]
+	pub container_follow: ContainerFollow,
+
+	/// With `--container-follow best`, blocks below the best relay block the heads are taken
+	/// from, so shorter reorgs never reach the apps.
+	#[arg(long, default_value_t = 0)]
+	pub container_safety_depth: u32,
+
+	/// Seconds an app instance gets to pass the readiness probe of its app, it takes over from the
+	/// previous instance anyway once they are over.
+	#[arg(long, default_value_t = 600)]
+	pub container_ready_timeout: u64,
+
+	/// MiB after which the log file of an app instance is rotated.
+	#[arg(long, default_value_t = 100)]
+	pub container_log_max_size: u64,
+
+	/// Hours after which the log file of an app instance is rotated.
+	#[arg(long, default_value_t = 24)]
+	pub container_log_max_age: u64,
+
+	/// Rotated log files kept per app instance.
+	#[arg(long, default_value_t = 5)]
+	pub container_log_generations: u32,
+}
+
+/// Runtime for apps shipped as docker images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ContainerEngine {
+	/// Docker.
+	Docker,
+	/// Podman.
+	Podman,
+}
+
+/// Relay chain blocks the container task follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ContainerFollow {
+	/// Best blocks, `--container-safety-depth` below the tip.
+	Best,
+	/// Finalized blocks.
+	Finalized,
+}
+
+impl ContainerCli {
+	/// Settings of the app binary downloader.
+	pub fn download_config(&self) -> DownloadConfig {
+		DownloadConfig {
+			parallel: self.container_download_parallel,
+			rate_limit: self.container_download_rate.map(|rate| rate.saturating_mul(1024)),
+		}
+	}
+
+	/// How crashed app instances are restarted.
+	pub fn restart_policy(&self) -> RestartPolicy {
+		RestartPolicy {
+			backoff: Duration::from_secs(self.container_restart_backoff),
+			max_backoff: Duration::from_secs(self.container_max_restart_backoff),
+			failure_budget: self.container_failure_budget,
+		}
+	}
+
+	/// How the log files of app instances are rotated.
+	pub fn log_policy(&self) -> LogPolicy {
+		LogPolicy {
+			max_size: self.container_log_max_size.saturating_mul(1024 * 1024),
+			max_age: Duration::from_secs(self.container_log_max_age.saturating_mul(3600)),
+			generations: self.container_log_generations,
+		}
+	}
+
+	/// Settings of the container task, its files are kept under `data_path`.
+	pub fn container_config(&self, data_path: PathBuf) -> ContainerConfig {
+		// Instances run in their data directory, paths handed to them must not be relative.
+		let data_path =
+			std::env::current_dir().map(|dir| dir.join(&data_path)).unwrap_or(data_path);
+		ContainerConfig {
+			backends: self.backends(data_path.clone()),
+			restart_policy: self.restart_policy(),
+			state_file: data_path.join(STATE_FILE),
+			apps_dir: data_path.join("apps"),
+			port_base: self.container_port_base,
+			follow: match self.container_follow {
+				ContainerFollow::Best => HeadFollow::Best { depth: self.container_safety_depth },
+				ContainerFollow::Finalized => HeadFollow::Finalized,
+			},
+			ready_timeout: Duration::from_secs(self.container_ready_timeout),
+			log_policy: self.log_policy(),
+		}
+	}
+
+	/// Backends apps are started on, binaries are kept under `data_path`.
+	pub fn backends(&self, data_path: PathBuf) -> Backends {
+		let stop_timeout = Duration::from_secs(self.container_stop_timeout);
+		let image: Arc<dyn Backend> = match self.container_engine {
+			ContainerEngine::Docker =>
+				Arc::new(DockerBackend::new(self.docker_socket.clone(), stop_timeout)),
+			ContainerEngine::Podman => Arc::new(CliBackend::podman(stop_timeout)),
+		};
+		let process = ProcessBackend::new(data_path, self.download_config(), stop_timeout);
+		Backends::new(Arc::new(process), image)
+	}
+}
+
 #[derive(Debug)]
 pub struct RelayChainCli {
 	/// The actual relay chain cli object.
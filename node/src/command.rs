@@ -259,6 +259,7 @@ pub fn run() -> Result<()> {
 					collator_options,
 					id,
 					hwbench,
+					cli.container.clone(),
 				)
 				.await
 				.map(|r| r.0)
@@ -1,20 +1,36 @@
+pub mod backend;
+pub mod docker;
+pub mod logs;
+pub mod readiness;
+pub mod state;
+pub mod supervisor;
+
+use backend::{Backends, Instance, InstanceHandle, InstanceState, Launch};
 use codec::Decode;
 use cumulus_primitives_core::{
-	relay_chain::BlockNumber as RelayBlockNumber, ParaId, PersistedValidationData,
+	relay_chain::{BlockId, BlockNumber as RelayBlockNumber},
+	ParaId, PersistedValidationData,
 };
 use cumulus_relay_chain_interface::{RelayChainInterface, RelayChainResult};
-use futures::{lock::Mutex, pin_mut, select, FutureExt, Stream, StreamExt};
+use futures::{
+	channel::{mpsc, oneshot},
+	future,
+	lock::{Mutex, MutexGuard},
+	pin_mut, select, stream, FutureExt, Stream, StreamExt,
+};
+use logs::LogPolicy;
 use polkadot_primitives::OccupiedCoreAssumption;
 use primitives_container::{ContainerRuntimeApi, DownloadInfo};
 use reqwest::{
 	self,
-	header::{HeaderValue, CONTENT_LENGTH, RANGE},
+	header::{CONTENT_LENGTH, RANGE},
 	StatusCode,
 };
 use ring::digest::{Context, Digest, SHA256};
 use sc_client_api::UsageProvider;
 use sc_service::TaskManager;
-use sp_api::ProvideRuntimeApi;
+use serde::{Deserialize, Serialize};
+use sp_api::{ApiExt, ProvideRuntimeApi};
 use sp_core::{offchain::OffchainStorage, H256};
 use sp_keystore::KeystorePtr;
 use sp_offchain::STORAGE_PREFIX;
@@ -23,19 +39,135 @@ use sp_runtime::{
 	AccountId32,
 };
 use std::{
+	collections::{btree_map::Entry, BTreeMap},
 	error::Error,
 	fs,
-	fs::{File, Permissions},
-	io::{BufReader, Read},
+	fs::{File, OpenOptions, Permissions},
+	io::{self, BufReader, Read, Write},
+	ops::RangeInclusive,
 	os::unix::fs::PermissionsExt,
 	path::{Path, PathBuf},
-	process::{Child, Command, Stdio},
 	str::FromStr,
-	sync::Arc,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
 };
+use supervisor::{Action, RestartPolicy, Supervision};
 pub const RUN_ARGS_KEY: &[u8] = b"run_args";
 pub const SYNC_ARGS_KEY: &[u8] = b"sync_args";
 pub const OPTION_ARGS_KEY: &[u8] = b"option_args";
+/// Longest accepted operator args value.
+pub const MAX_ARGS_LEN: usize = 4096;
+// How often the instances are checked for crashes.
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(2);
+// Ports reserved for each app, starting at its port base.
+const PORTS_PER_APP: u32 = 100;
+// Each of the two instances of an app gets half of its ports.
+const PORTS_PER_INSTANCE: u16 = 50;
+// Ports of an instance by template name, as offsets into its range.
+const INSTANCE_PORTS: [(&str, u16); 3] = [("p2p_port", 0), ("rpc_port", 1), ("prometheus_port", 2)];
+
+/// Operator args kept in offchain storage, globally or per app. Like the app args and envs they
+/// may use the `{{data_dir}}`, `{{port_base}}`, `{{p2p_port}}`, `{{rpc_port}}` and
+/// `{{prometheus_port}}` placeholders of the instance they are started with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArgsKind {
+	/// Appended to the app args of the running instance.
+	Run,
+	/// Appended to the app args of the syncing instance.
+	Sync,
+	/// Extra `docker run` options.
+	Option,
+}
+
+impl ArgsKind {
+	/// Offchain storage key, `<kind>` globally or `<kind>:<app_id>` for one app.
+	pub fn storage_key(self, app_id: Option<u32>) -> Vec<u8> {
+		let key = match self {
+			ArgsKind::Run => RUN_ARGS_KEY,
+			ArgsKind::Sync => SYNC_ARGS_KEY,
+			ArgsKind::Option => OPTION_ARGS_KEY,
+		};
+		match app_id {
+			Some(app_id) => [key, format!(":{}", app_id).as_bytes()].concat(),
+			None => key.to_vec(),
+		}
+	}
+}
+
+/// Args of an app after applying the global overrides.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveArgs {
+	/// App the args apply to.
+	pub app_id: u32,
+	/// Run args.
+	pub run: Option<Vec<String>>,
+	/// Sync args.
+	pub sync: Option<Vec<String>>,
+	/// Docker options.
+	pub option: Option<Vec<String>>,
+}
+
+/// Check an args value is a non-empty list of args without control characters, and that
+/// option args only use the `docker run` options the Docker Engine API backend maps.
+pub fn validate_args(kind: ArgsKind, args: &[String]) -> Result<(), String> {
+	if args.is_empty() {
+		return Err("args are empty, clear them instead".into());
+	}
+	if encode_args(args).len() > MAX_ARGS_LEN {
+		return Err(format!("args are longer than {} bytes", MAX_ARGS_LEN));
+	}
+	if args.iter().any(|arg| arg.chars().any(|c| c.is_control())) {
+		return Err("args contain control characters".into());
+	}
+	if kind == ArgsKind::Option {
+		docker::check_options(args).map_err(|err| err.to_string())?;
+	}
+	Ok(())
+}
+
+/// Offchain storage value of args, a JSON array with one entry per arg.
+pub fn encode_args(args: &[String]) -> Vec<u8> {
+	serde_json::to_vec(args).expect("a list of strings always serializes; qed")
+}
+
+/// Args of an offchain storage value. Values set by nodes from before the JSON arrays are one
+/// string of space separated args, and are split as those nodes did.
+pub fn decode_args(value: &[u8]) -> Vec<String> {
+	serde_json::from_slice(value).unwrap_or_else(|_| {
+		String::from_utf8_lossy(value)
+			.split(' ')
+			.filter(|arg| !arg.is_empty())
+			.map(str::to_string)
+			.collect()
+	})
+}
+
+/// Operator args of an app, the global value takes precedence over the per app one.
+pub fn effective_args<S: OffchainStorage>(
+	storage: &S,
+	kind: ArgsKind,
+	app_id: u32,
+) -> Option<Vec<String>> {
+	let prefix = &STORAGE_PREFIX;
+	storage
+		.get(prefix, &kind.storage_key(None))
+		.or_else(|| storage.get(prefix, &kind.storage_key(Some(app_id))))
+		.map(|value| decode_args(&value))
+}
+
+/// Settings of the app binary downloader.
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadConfig {
+	/// Number of ranges fetched in parallel.
+	pub parallel: usize,
+	/// Cap on the download rate in bytes per second.
+	pub rate_limit: Option<u64>,
+}
 
 struct PartialRangeIter {
 	start: u64,
@@ -44,7 +176,11 @@ struct PartialRangeIter {
 }
 
 impl PartialRangeIter {
-	pub fn new(start: u64, end: u64, buffer_size: u32) -> Result<Self, Box<dyn Error>> {
+	pub fn new(
+		start: u64,
+		end: u64,
+		buffer_size: u32,
+	) -> Result<Self, Box<dyn Error + Send + Sync>> {
 		if buffer_size == 0 {
 			Err("invalid buffer_size, give a value greater than zero.")?;
 		}
@@ -53,57 +189,115 @@ impl PartialRangeIter {
 }
 
 impl Iterator for PartialRangeIter {
-	type Item = HeaderValue;
+	type Item = (u64, u64);
 	fn next(&mut self) -> Option<Self::Item> {
 		if self.start > self.end {
 			None
 		} else {
 			let prev_start = self.start;
 			self.start += std::cmp::min(self.buffer_size as u64, self.end - self.start + 1);
-			Some(
-				HeaderValue::from_str(&format!("bytes={}-{}", prev_start, self.start - 1))
-					.expect("string provided by format!"),
-			)
+			Some((prev_start, self.start - 1))
 		}
 	}
 }
 
-async fn sha256_digest<R: Read>(mut reader: R) -> Result<Digest, Box<dyn Error + Send + Sync>> {
-	let mut context = Context::new(&SHA256);
-	let mut buffer = [0; 1024];
+// Shared between the range fetches so the cap applies to the whole download.
+struct Throttle {
+	rate_limit: Option<u64>,
+	started: Instant,
+	bytes: AtomicU64,
+}
 
-	loop {
-		let count = reader.read(&mut buffer)?;
-		if count == 0 {
-			break;
+impl Throttle {
+	fn new(rate_limit: Option<u64>) -> Self {
+		Throttle { rate_limit, started: Instant::now(), bytes: AtomicU64::new(0) }
+	}
+
+	async fn consume(&self, count: u64) {
+		let Some(rate_limit) = self.rate_limit.filter(|rate| *rate > 0) else { return };
+		let bytes = self.bytes.fetch_add(count, Ordering::Relaxed) + count;
+		let due = Duration::from_secs_f64(bytes as f64 / rate_limit as f64);
+		let elapsed = self.started.elapsed();
+		if due > elapsed {
+			tokio::time::sleep(due - elapsed).await;
 		}
-		context.update(&buffer[..count]);
 	}
+}
+
+// Hash the file on a blocking thread, binaries take a while to read.
+async fn sha256_digest(path: PathBuf) -> Result<Digest, Box<dyn Error + Send + Sync>> {
+	let digest = tokio::task::spawn_blocking(move || -> io::Result<Digest> {
+		let mut reader = BufReader::new(File::open(path)?);
+		let mut context = Context::new(&SHA256);
+		let mut buffer = [0; 1024];
+
+		loop {
+			let count = reader.read(&mut buffer)?;
+			if count == 0 {
+				break;
+			}
+			context.update(&buffer[..count]);
+		}
 
-	Ok(context.finish())
+		Ok(context.finish())
+	})
+	.await??;
+
+	Ok(digest)
+}
+
+async fn fetch_range(
+	client: &reqwest::Client,
+	web_path: &str,
+	(start, end): (u64, u64),
+	length: u64,
+	throttle: &Throttle,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+	let mut response = client
+		.get(web_path)
+		.header(RANGE, format!("bytes={}-{}", start, end))
+		.send()
+		.await?;
+
+	let status = response.status();
+	// A server ignoring the range answers with the whole file, only fine if that is what we asked.
+	let whole_file = start == 0 && end + 1 == length;
+	if !(status == StatusCode::PARTIAL_CONTENT || (status == StatusCode::OK && whole_file)) {
+		Err(format!("unexpected server response: {:?}", status))?;
+	}
+	let mut buffer = Vec::with_capacity((end - start + 1) as usize);
+	while let Some(chunk) = response.chunk().await? {
+		throttle.consume(chunk.len() as u64).await;
+		buffer.extend_from_slice(&chunk);
+	}
+	if buffer.len() as u64 != end - start + 1 {
+		Err(format!("range {}-{} returned {} bytes", start, end, buffer.len()))?;
+	}
+	Ok(buffer)
 }
 
+// Fetch into `<file>.part`, resuming from the bytes already there, and only move it to the
+// final path once the hash matches.
 async fn download_sdk(
 	data_path: PathBuf,
 	app_info: DownloadInfo,
 	url: &str,
+	progress: &DownloadProgress,
+	config: DownloadConfig,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
 	//firt create dir
 	let path_str = format!("{}/sdk", data_path.as_os_str().to_str().ok_or("path error!")?);
 
-	let download_dir = Path::new(&path_str);
-	if !download_dir.exists() {
-		fs::create_dir(&download_dir)?
-	}
+	fs::create_dir_all(&path_str)?;
 
 	const CHUNK_SIZE: u32 = 1024000; // 1 M
 
-	let client = reqwest::blocking::Client::new();
+	let client = reqwest::Client::new();
 
 	let web_path = format!("{}/{}", url, std::str::from_utf8(&app_info.file_name)?);
 	log::info!("=============download:{:?}", web_path);
 
-	let response = client.head(&web_path).send()?;
+	let response = client.head(&web_path).send().await?;
 
 	let length = response
 		.headers()
@@ -114,59 +308,70 @@ async fn download_sdk(
 	log::info!("==========total length:{:?}", length);
 
 	let download_path = format!("{}/{}", path_str, std::str::from_utf8(&app_info.file_name)?);
+	let part_path = format!("{}.part", download_path);
 	log::info!("=============download_path:{:?}", download_path);
 
-	let download_dir = Path::new(&download_path);
-
-	let mut output_file = File::create(download_dir)?;
-
-	println!("starting download...");
-	for range in PartialRangeIter::new(0, length - 1, CHUNK_SIZE).unwrap() {
-		println!("range {:?}", range);
-		let mut response = client.get(&web_path).header(RANGE, range).send()?;
-
-		let status = response.status();
-		if !(status == StatusCode::OK || status == StatusCode::PARTIAL_CONTENT) {
-			log::info!("Unexpected server response: {:?}", status);
-			break;
+	let mut output_file = OpenOptions::new().create(true).append(true).open(&part_path)?;
+	let mut existing = output_file.metadata()?.len();
+	if existing > length {
+		// Left over from a different release under the same name.
+		output_file.set_len(0)?;
+		existing = 0;
+	}
+	log::info!("resume download at {:?}", existing);
+	progress.start(length, existing);
+
+	if existing < length {
+		let throttle = Throttle::new(config.rate_limit);
+		// Ranges arrive in order, so the part file always holds a prefix of the binary.
+		let mut ranges = stream::iter(PartialRangeIter::new(existing, length - 1, CHUNK_SIZE)?)
+			.map(|range| fetch_range(&client, &web_path, range, length, &throttle))
+			.buffered(config.parallel.max(1));
+		while let Some(range) = ranges.next().await {
+			let range = range?;
+			output_file.write_all(&range)?;
+			progress.downloaded.fetch_add(range.len() as u64, Ordering::Relaxed);
 		}
-		std::io::copy(&mut response, &mut output_file)?;
+		output_file.sync_all()?;
 	}
+	drop(output_file);
 
-	let content = response.text()?;
-	std::io::copy(&mut content.as_bytes(), &mut output_file)?;
-
-	println!("Finished with success!");
+	log::info!("check file hash");
+	let digest = sha256_digest(PathBuf::from(&part_path)).await?;
 
-	output_file.set_permissions(Permissions::from_mode(0o777))?;
-	//check file hash
-	println!("check file hash");
-	let input = File::open(download_dir)?;
-	let reader = BufReader::new(input);
-	let digest = sha256_digest(reader).await?;
-
-	println!("SHA-256 digest is {:?}", digest);
-	if digest.as_ref() == app_info.app_hash.as_bytes() {
-		println!("check ok");
-	} else {
-		println!("check fail");
+	log::info!("SHA-256 digest is {:?}", digest);
+	if digest.as_ref() != app_info.app_hash.as_bytes() {
+		// A corrupt part file must not be resumed from.
+		fs::remove_file(&part_path)?;
 		Err("Hash check fail.")?;
 	}
 
+	fs::set_permissions(&part_path, Permissions::from_mode(0o777))?;
+	fs::rename(&part_path, &download_path)?;
+
 	Ok(())
 }
+
+// Mirrors in the order the runtime declared them, falling back to the single url.
+fn download_urls(app_info: &DownloadInfo) -> Vec<Vec<u8>> {
+	if app_info.mirrors.is_empty() {
+		vec![app_info.url.clone()]
+	} else {
+		app_info.mirrors.clone()
+	}
+}
+
 async fn need_download(
 	data_path: &str,
 	app_hash: H256,
 ) -> Result<bool, Box<dyn Error + Send + Sync>> {
-	let input =
-		if let Ok(open_file) = File::open(data_path) { open_file } else { return Ok(true) };
-
-	let reader = BufReader::new(input);
+	if !Path::new(data_path).is_file() {
+		return Ok(true);
+	}
 
-	let digest = sha256_digest(reader).await?;
+	let digest = sha256_digest(PathBuf::from(data_path)).await?;
 
-	println!("SHA-256 digest is {:?}", digest);
+	log::debug!("SHA-256 digest of {} is {:?}", data_path, digest);
 	if digest.as_ref() == app_hash.as_bytes() {
 		Ok(false)
 	} else {
@@ -174,365 +379,1068 @@ async fn need_download(
 	}
 }
 
-async fn check_docker_image_exist(
-	docker_image: &str,
-) -> Result<bool, Box<dyn Error + Send + Sync>> {
-	let ls_cmd = format!("image ls {}", docker_image);
-	let args: Vec<&str> = ls_cmd.split(' ').into_iter().map(|arg| arg).collect();
-	let mut instance = Command::new("docker").stdout(Stdio::piped()).args(args).spawn()?;
-	let mut result = false;
-	if let Some(ls_output) = instance.stdout.take() {
-		let grep_cmd = Command::new("grep")
-			.arg(docker_image)
-			.stdin(ls_output)
-			.stdout(Stdio::piped())
-			.spawn()?;
-
-		let grep_stdout = grep_cmd.wait_with_output()?;
-		instance.wait()?;
-		let grep_out = String::from_utf8(grep_stdout.stdout)?;
-		if grep_out.len() > 0 {
-			result = true;
-		}
-	}
-	Ok(result)
-}
-
-async fn download_docker_image(docker_image: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-	let pull_cmd = format!("pull {}", docker_image);
-	let args: Vec<&str> = pull_cmd.split(' ').into_iter().map(|arg| arg).collect();
-	let mut instance = Command::new("docker").args(args).spawn()?;
-	instance.wait()?;
-	Ok(())
-}
-
-async fn remove_docker_container(container_name: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-	let mut docker_cmd = format!("container stop {}", container_name);
-	let mut args: Vec<&str> = docker_cmd.split(' ').into_iter().map(|arg| arg).collect();
-	let mut instance = Command::new("docker").args(args).spawn()?;
-	instance.wait()?;
-
-	docker_cmd = format!("container remove {}", container_name);
-	args = docker_cmd.split(' ').into_iter().map(|arg| arg).collect();
-	instance = Command::new("docker").args(args).spawn()?;
-	instance.wait()?;
-	Ok(())
-}
-
-async fn start_docker_container(
-	container_name: &str,
-	docker_image: &str,
-	in_args: Vec<&str>,
-	option_args: Option<Vec<u8>>,
-	log_file: File,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-	let docker_cmd = if let Some(option_arg) = option_args {
-		let option = std::str::from_utf8(&option_arg)?;
-		format!("run -itd --name {} {} {}", container_name, option, docker_image)
-	} else {
-		format!("run -itd --name {} {}", container_name, docker_image)
-	};
-	let mut args: Vec<&str> = docker_cmd.split(' ').into_iter().map(|arg| arg).collect();
-	args.extend(in_args);
-	log::info!("=======================args:{:?}", args);
-	let mut instance = Command::new("docker").args(args).stdout(Stdio::from(log_file)).spawn()?;
-	instance.wait()?;
-	Ok(())
-}
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum StartType {
 	SYNC,
 	RUN,
+	// Replace the active instance in place, leaving a syncing instance alone.
+	RESTART,
 }
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum InstanceIndex {
 	Instance1,
 	Instance2,
 }
-#[derive(Debug, PartialEq, Eq)]
-enum RunStatus {
+
+impl InstanceIndex {
+	fn other(self) -> Self {
+		match self {
+			InstanceIndex::Instance1 => InstanceIndex::Instance2,
+			InstanceIndex::Instance2 => InstanceIndex::Instance1,
+		}
+	}
+
+	fn number(self) -> u8 {
+		match self {
+			InstanceIndex::Instance1 => 1,
+			InstanceIndex::Instance2 => 2,
+		}
+	}
+
+	fn from_number(number: u8) -> Option<Self> {
+		match number {
+			1 => Some(InstanceIndex::Instance1),
+			2 => Some(InstanceIndex::Instance2),
+			_ => None,
+		}
+	}
+}
+
+/// Lifecycle of the app run by the local sequencer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RunStatus {
+	/// Waiting for a group or release to fetch.
 	Pending,
+	/// Fetching the binary or docker image.
 	Downloading,
+	/// Fetched and syncing, waiting for the round switch.
 	Downloaded,
+	/// Running.
 	Running,
+	/// Stopped by the operator, only a restart or re-download starts it again.
+	Stopped,
+}
+
+/// What an app is run for, the node runs one app per role.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AppRole {
+	/// The app of the group the node sequences for.
+	#[default]
+	Sequencer,
+	/// The app of a group the node processes for.
+	Processor(u32),
+}
+
+impl AppRole {
+	// Tells apart the names and files of apps running side by side.
+	fn key(self) -> String {
+		match self {
+			AppRole::Sequencer => "sequencer".to_string(),
+			AppRole::Processor(group) => format!("group{}", group),
+		}
+	}
+
+	// First of the ports reserved for the app, an error when its range does not end below 65536.
+	fn port_base(self, base: u16) -> Result<u16, String> {
+		let rank = match self {
+			AppRole::Sequencer => Some(0),
+			AppRole::Processor(group) => group.checked_add(1),
+		};
+		rank.and_then(|rank| rank.checked_mul(PORTS_PER_APP))
+			.and_then(|offset| offset.checked_add(base.into()))
+			.filter(|port| port.checked_add(PORTS_PER_APP - 1).is_some_and(|last| last <= 65535))
+			.and_then(|port| u16::try_from(port).ok())
+			.ok_or_else(|| {
+				format!("ports of the app of {:?} do not fit above port base {}", self, base)
+			})
+	}
+}
+
+/// Bytes fetched of the binary currently being downloaded. Each download gets its own, so a
+/// cancelled download can tell it was replaced.
+#[derive(Debug, Default)]
+pub struct DownloadProgress {
+	downloaded: AtomicU64,
+	total: AtomicU64,
+	cancelled: AtomicBool,
 }
+
+impl DownloadProgress {
+	fn start(&self, total: u64, downloaded: u64) {
+		self.total.store(total, Ordering::Relaxed);
+		self.downloaded.store(downloaded, Ordering::Relaxed);
+	}
+
+	/// Whether the download is no longer wanted, backends blocking on a fetch give up when set.
+	pub fn cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::Relaxed)
+	}
+}
+
+/// One of the two instance slots of the app.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceStatus {
+	/// Slot number, 1 or 2.
+	pub slot: u8,
+	/// Whether this instance serves the current round, otherwise it is syncing.
+	pub active: bool,
+	/// Process id, `None` for docker containers.
+	pub pid: Option<u32>,
+	/// Docker container name.
+	pub docker_name: Option<String>,
+	/// Exit code once the process has exited.
+	pub exit_code: Option<i32>,
+	/// Crashes since it last ran healthy.
+	pub failures: u32,
+	/// Restarts after crashes.
+	pub restarts: u32,
+	/// Whether it crashed too often and is left down.
+	pub given_up: bool,
+	/// Whether it passed the readiness probe of the app.
+	pub ready: bool,
+}
+
+/// Snapshot of an app run by the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerStatus {
+	/// What the app is run for.
+	pub role: AppRole,
+	/// Lifecycle status.
+	pub status: RunStatus,
+	/// Group of the app, `None` until assigned.
+	pub group_id: Option<u32>,
+	/// App of the group, `None` until assigned.
+	pub app_id: Option<u32>,
+	/// Mirror the binary was downloaded from.
+	pub download_url: Option<String>,
+	/// Bytes fetched of the last download.
+	pub downloaded: u64,
+	/// Size of the last download.
+	pub download_size: u64,
+	/// Occupied instance slots.
+	pub instances: Vec<InstanceStatus>,
+	/// Round the active instance was switched into.
+	pub round: Option<u32>,
+	/// Whether the instance started for the round waits to be ready before it takes over.
+	pub handover: bool,
+}
+
+/// Operator commands handled by the container task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerCommand {
+	/// Restart the active instance.
+	Restart,
+	/// Drop the fetched binary and download it again.
+	Redownload,
+	/// Stop all instances.
+	Stop,
+}
+
+type CommandResult = Result<(), String>;
+
+// Apps run by the node, by role.
+type RunningApps = Arc<Mutex<BTreeMap<AppRole, Arc<Mutex<RunningApp>>>>>;
+
+type Command = (AppRole, ContainerCommand, oneshot::Sender<CommandResult>);
+
+/// Shared view of the container task, used by the node rpc.
+#[derive(Clone)]
+pub struct ContainerHandle {
+	apps: RunningApps,
+	commands: mpsc::UnboundedSender<Command>,
+}
+
+/// Receiving side of the operator commands, consumed by the container task.
+pub type ContainerCommands = mpsc::UnboundedReceiver<Command>;
+
+impl ContainerHandle {
+	/// Create a handle and the command stream to pass to `spawn_container_task`.
+	pub fn new() -> (Self, ContainerCommands) {
+		let (commands, receiver) = mpsc::unbounded();
+		(ContainerHandle { apps: Default::default(), commands }, receiver)
+	}
+
+	async fn app(&self, role: AppRole) -> Option<Arc<Mutex<RunningApp>>> {
+		self.apps.lock().await.get(&role).cloned()
+	}
+
+	/// Current status of every app.
+	pub async fn statuses(&self) -> Vec<ContainerStatus> {
+		let apps: Vec<_> = self.apps.lock().await.values().cloned().collect();
+		let mut statuses = Vec::new();
+		for app in apps {
+			statuses.push(app.lock().await.status());
+		}
+		statuses
+	}
+
+	/// Current status of an app, `None` if the node does not run it.
+	pub async fn status(&self, role: AppRole) -> Option<ContainerStatus> {
+		Some(self.app(role).await?.lock().await.status())
+	}
+
+	/// Last `tail` lines of the log file of the active instance of an app, or of the syncing one
+	/// if none is active.
+	pub async fn logs(&self, role: AppRole, tail: usize) -> Result<String, String> {
+		let app = self.app(role).await.ok_or("no such app")?;
+		let app = app.lock().await;
+		let instance = app
+			.instance(app.cur_ins.other())
+			.as_ref()
+			.or(app.instance(app.cur_ins).as_ref())
+			.ok_or("no instance is running")?;
+		instance.logs(tail).map_err(|err| err.to_string())
+	}
+
+	/// Send a command for an app to the container task and wait for its result.
+	pub async fn send(&self, role: AppRole, command: ContainerCommand) -> CommandResult {
+		let (tx, rx) = oneshot::channel();
+		self.commands
+			.unbounded_send((role, command, tx))
+			.map_err(|_| "container task is not running".to_string())?;
+		rx.await.map_err(|_| "container task is not running".to_string())?
+	}
+}
+
+/// Settings of the container task.
+#[derive(Debug, Clone)]
+pub struct ContainerConfig {
+	/// Backends apps are started on.
+	pub backends: Backends,
+	/// How crashed instances are restarted.
+	pub restart_policy: RestartPolicy,
+	/// File the state of the apps is persisted to.
+	pub state_file: PathBuf,
+	/// Directory each app gets its own data directory in.
+	pub apps_dir: PathBuf,
+	/// First port reserved for the apps, each gets a range of its own.
+	pub port_base: u16,
+	/// Parachain heads the task acts on.
+	pub follow: HeadFollow,
+	/// Time an instance gets to pass the readiness probe of its app before it counts as ready.
+	pub ready_timeout: Duration,
+	/// How the log files of instances are rotated.
+	pub log_policy: LogPolicy,
+}
+
+/// Parachain heads the container task acts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadFollow {
+	/// Best heads, as of the relay block `depth` blocks below the best one, so reorgs shorter
+	/// than that never reach the apps.
+	Best { depth: u32 },
+	/// Finalized heads.
+	Finalized,
+}
+
 #[derive(Debug)]
 struct RunningApp {
+	role: AppRole,
+	// Each instance gets its own data directory under here.
+	apps_dir: PathBuf,
+	// First of the ports reserved for the app.
+	port_base: u16,
+	log_policy: LogPolicy,
 	group_id: u32,
 	app_id: u32,
 	running: RunStatus,
 	app_info: Option<DownloadInfo>,
-	instance1: Option<Child>,
-	instance2: Option<Child>,
-	instance1_docker: bool,
-	instance2_docker: bool,
-	instance1_docker_name: Option<Vec<u8>>,
-	instance2_docker_name: Option<Vec<u8>>,
+	instance1: Option<Instance>,
+	instance2: Option<Instance>,
 	cur_ins: InstanceIndex,
+	// Mirror the current binary was downloaded from.
+	download_url: Option<Vec<u8>>,
+	// Release the active instance was started from.
+	run_app_info: Option<DownloadInfo>,
+	download_progress: Arc<DownloadProgress>,
+	// Download in flight, if any.
+	download: Option<Download>,
+	// Round the active instance was switched into.
+	round: Option<u32>,
+	// Round the fetched release is synced for, `None` on runtimes that do not tell rounds apart.
+	synced_for: Option<u32>,
+	// The instance in `cur_ins` was started for the round and takes over once ready, the other
+	// one serves the round until then.
+	handover: bool,
+	// Slots of crashed instances being stopped with the lock released, to start them again in.
+	restarting: Vec<InstanceIndex>,
 }
 
-async fn process_download_task(
-	data_path: PathBuf,
+#[derive(Debug)]
+struct Download {
+	// Release being fetched.
 	app_info: DownloadInfo,
-	running_app: Arc<Mutex<RunningApp>>,
-	new_group: u32,
-	sync_args: Option<Vec<u8>>,
-	option_args: Option<Vec<u8>>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-	let run_as_docker = app_info.is_docker_image;
-	let mut start_flag = false;
-
-	if run_as_docker {
-		log::info!("===========Download app from docker hub and run the application as a container=========");
-		if let Some(image) = app_info.clone().docker_image {
-			let docker_image = std::str::from_utf8(&image)?;
-			let mut exist_docker_image = check_docker_image_exist(docker_image).await?;
-			if !exist_docker_image {
-				download_docker_image(docker_image).await?;
-				exist_docker_image = check_docker_image_exist(docker_image).await?;
-			}
-			if exist_docker_image {
-				//Start docker container
-				start_flag = true;
-			}
+	task: tokio::task::JoinHandle<Result<(), Box<dyn Error + Send + Sync>>>,
+}
+
+impl RunningApp {
+	fn new(role: AppRole, config: &ContainerConfig) -> Result<Self, String> {
+		Ok(RunningApp {
+			role,
+			apps_dir: config.apps_dir.clone(),
+			port_base: role.port_base(config.port_base)?,
+			log_policy: config.log_policy,
+			group_id: 0xFFFFFFFF,
+			app_id: 0xFFFFFFFF,
+			running: RunStatus::Pending,
+			app_info: None,
+			instance1: None,
+			instance2: None,
+			cur_ins: InstanceIndex::Instance1,
+			download_url: None,
+			run_app_info: None,
+			download_progress: Default::default(),
+			download: None,
+			round: None,
+			synced_for: None,
+			handover: false,
+			restarting: Vec::new(),
+		})
+	}
+
+	// Working directory of the instance of `app_id` in `slot`, its relative paths and logs end up
+	// here. Instances never share one, so a new release syncs into a database of its own.
+	fn instance_dir(&self, app_id: u32, slot: InstanceIndex) -> PathBuf {
+		self.apps_dir.join(app_id.to_string()).join(format!(
+			"{}-{}",
+			self.role.key(),
+			slot.number()
+		))
+	}
+
+	// Ports reserved for the app, within the range checked in `new`.
+	fn ports(&self) -> RangeInclusive<u16> {
+		self.port_base..=self.port_base + (PORTS_PER_APP - 1) as u16
+	}
+
+	// First of the ports of the instance in `slot`, within the range checked in `new`.
+	fn instance_port_base(&self, slot: InstanceIndex) -> u16 {
+		self.port_base + u16::from(slot.number() - 1) * PORTS_PER_INSTANCE
+	}
+
+	fn instance(&self, index: InstanceIndex) -> &Option<Instance> {
+		match index {
+			InstanceIndex::Instance1 => &self.instance1,
+			InstanceIndex::Instance2 => &self.instance2,
 		}
-	} else {
-		log::info!(
-			"===========Download app from the web and run the application as a process========="
-		);
-		let url = std::str::from_utf8(&app_info.url)?;
+	}
 
-		let download_path = format!(
-			"{}/sdk/{}",
-			data_path.as_os_str().to_str().ok_or("invalid data_path")?,
-			std::str::from_utf8(&app_info.file_name)?
-		);
+	fn instance_mut(&mut self, index: InstanceIndex) -> &mut Option<Instance> {
+		match index {
+			InstanceIndex::Instance1 => &mut self.instance1,
+			InstanceIndex::Instance2 => &mut self.instance2,
+		}
+	}
+
+	fn status(&mut self) -> ContainerStatus {
+		let active = self.cur_ins.other();
+		let mut instances = Vec::new();
+		for index in [InstanceIndex::Instance1, InstanceIndex::Instance2] {
+			let Some(instance) = self.instance_mut(index) else { continue };
+			let exit_code = match instance.state() {
+				InstanceState::Exited(code) => code,
+				_ => None,
+			};
+			let (pid, docker_name) = match &instance.handle {
+				InstanceHandle::Process(child) => (Some(child.id()), None),
+				InstanceHandle::Adopted(pid) => (Some(*pid), None),
+				InstanceHandle::Container(name) => (None, Some(name.clone())),
+			};
+			instances.push(InstanceStatus {
+				slot: index.number(),
+				active: index == active,
+				pid,
+				docker_name,
+				exit_code,
+				failures: instance.supervision.failures,
+				restarts: instance.supervision.restarts,
+				given_up: instance.supervision.given_up,
+				ready: instance.readiness.ready,
+			});
+		}
+		let assigned = |id: u32| if id == 0xFFFFFFFF { None } else { Some(id) };
+		ContainerStatus {
+			role: self.role,
+			status: self.running,
+			group_id: assigned(self.group_id),
+			app_id: assigned(self.app_id),
+			download_url: self
+				.download_url
+				.as_ref()
+				.map(|url| String::from_utf8_lossy(url).into_owned()),
+			downloaded: self.download_progress.downloaded.load(Ordering::Relaxed),
+			download_size: self.download_progress.total.load(Ordering::Relaxed),
+			instances,
+			round: self.round,
+			handover: self.handover,
+		}
+	}
+}
 
-		let need_download = need_download(&download_path, app_info.app_hash).await;
-		log::info!("need_download:{:?}", need_download);
+fn stop_instance(instance: &mut Instance) {
+	let result = instance.stop();
+	log::info!("stop instance {:?}:{:?}", instance.handle, result);
+}
 
-		if let Ok(need_down) = need_download {
-			if need_down {
-				let result = download_sdk(data_path.clone(), app_info.clone(), url).await;
+// Stop instances taken out of their apps, each on a thread of its own. Their grace periods run
+// concurrently, and neither stall the async workers nor hold the lock of an app. Returns them
+// stopped and in order, `None` for one whose stop panicked.
+async fn stop_instances(instances: Vec<Instance>) -> Vec<Option<Instance>> {
+	let stopped: Vec<_> = instances
+		.into_iter()
+		.map(|mut instance| {
+			let (sender, receiver) = oneshot::channel();
+			std::thread::spawn(move || {
+				stop_instance(&mut instance);
+				let _ = sender.send(instance);
+			});
+			receiver
+		})
+		.collect();
+	future::join_all(stopped).await.into_iter().map(Result::ok).collect()
+}
 
-				if result.is_ok() {
-					start_flag = true;
-				} else {
-					log::info!("download sdk error:{:?}", result);
+// Lock the app with room made for an instance started with `start_type`, `None` once `wanted`
+// no longer holds. What is in the way is stopped with the lock released.
+async fn lock_with_room<'a>(
+	running_app: &'a Arc<Mutex<RunningApp>>,
+	start_type: StartType,
+	wanted: impl Fn(&RunningApp) -> bool,
+) -> Option<MutexGuard<'a, RunningApp>> {
+	loop {
+		let mut app = running_app.lock().await;
+		if !wanted(&app) {
+			return None;
+		}
+		let in_the_way = app.make_room(start_type);
+		if in_the_way.is_empty() {
+			return Some(app);
+		}
+		drop(app);
+		stop_instances(in_the_way).await;
+	}
+}
+
+// Whether both are the same release of the same app.
+fn same_release(a: &DownloadInfo, b: &DownloadInfo) -> bool {
+	a.app_id == b.app_id &&
+		a.app_hash == b.app_hash &&
+		a.file_name == b.file_name &&
+		a.docker_image == b.docker_image
+}
+
+impl RunningApp {
+	// Fetch `app_info` in the background and start it for sync once fetched.
+	fn start_download(
+		&mut self,
+		running_app: &Arc<Mutex<RunningApp>>,
+		backends: &Backends,
+		app_info: DownloadInfo,
+		sync_args: Option<Vec<String>>,
+		option_args: Option<Vec<String>>,
+		sync_round: Option<u32>,
+	) {
+		self.running = RunStatus::Downloading;
+		self.app_id = app_info.app_id;
+		self.synced_for = sync_round;
+		self.download_progress = Default::default();
+		let task = tokio::spawn(process_download_task(
+			backends.clone(),
+			app_info.clone(),
+			running_app.clone(),
+			self.download_progress.clone(),
+			sync_args,
+			option_args,
+		));
+		self.download = Some(Download { app_info, task });
+	}
+
+	// Give up the download in flight, if it is not for `wanted`, and drop what it fetched so
+	// far. A download left running would take over the app once done.
+	fn cancel_download(&mut self, backends: &Backends, wanted: Option<&DownloadInfo>) {
+		let Some(download) = &self.download else { return };
+		if wanted.is_some_and(|wanted| {
+			wanted.group == download.app_info.group && same_release(wanted, &download.app_info)
+		}) {
+			return;
+		}
+		let Some(Download { app_info, task }) = self.download.take() else { return };
+		log::info!("cancel download of app {} for group {}", app_info.app_id, app_info.group);
+		self.download_progress.cancelled.store(true, Ordering::Relaxed);
+		task.abort();
+		if let Err(err) = backends.for_app(&app_info).discard(&app_info) {
+			log::warn!("discard download of app {} error:{:?}", app_info.app_id, err);
+		}
+		if self.running == RunStatus::Downloading {
+			self.running = RunStatus::Pending;
+		}
+	}
+
+	// Whether the synced instance takes over for `round`, seen started at this head. Only once
+	// per round, so a start block seen again after a reorg does not switch a second time. With
+	// `synced_for` only at the first head seen of that round, which catches up with a missed
+	// start block, while a release not synced by then waits for the next round.
+	fn switch_due(&mut self, round: u32) -> bool {
+		if self.round == Some(round) {
+			return false;
+		}
+		let ready = self.running == RunStatus::Downloaded && self.synced();
+		match self.synced_for {
+			None => ready,
+			Some(synced_for) if synced_for > round => false,
+			Some(synced_for) => {
+				if synced_for == round && ready {
+					return true;
 				}
-			} else {
-				start_flag = true;
+				self.synced_for = Some(round + 1);
+				false
+			},
+		}
+	}
+
+	// Whether the syncing instance caught up, as told by the readiness probe of the app.
+	fn synced(&self) -> bool {
+		if self.restarting.contains(&self.cur_ins) {
+			return false;
+		}
+		match self.instance(self.cur_ins) {
+			Some(instance) => instance.readiness.ready,
+			None => true,
+		}
+	}
+
+	// Let the instance started for the round take over. Returns the previous one, to be stopped
+	// once the lock of the app is released.
+	fn finish_handover(&mut self) -> Option<Instance> {
+		let previous = self.take_slot(self.cur_ins.other());
+		self.cur_ins = self.cur_ins.other();
+		self.handover = false;
+		previous
+	}
+
+	// Take out both instances, to be stopped once the lock of the app is released.
+	fn take_instances(&mut self) -> Vec<Instance> {
+		self.handover = false;
+		[InstanceIndex::Instance1, InstanceIndex::Instance2]
+			.into_iter()
+			.filter_map(|slot| self.take_slot(slot))
+			.collect()
+	}
+
+	// Empty the slot for good, a crashed instance being stopped is not started in it again.
+	fn take_slot(&mut self, slot: InstanceIndex) -> Option<Instance> {
+		self.restarting.retain(|restarting| *restarting != slot);
+		self.instance_mut(slot).take()
+	}
+
+	// Slot an instance started with `start_type` goes into.
+	fn slot(&self, start_type: StartType) -> InstanceIndex {
+		if start_type == StartType::RESTART {
+			self.cur_ins.other()
+		} else {
+			self.cur_ins
+		}
+	}
+
+	// Take out what is in the way of an instance started with `start_type`, to be stopped before
+	// it starts.
+	fn make_room(&mut self, start_type: StartType) -> Vec<Instance> {
+		let mut in_the_way = Vec::new();
+		if self.handover {
+			// Its slot is about to be reused, the pending handover can not wait any longer.
+			log::warn!(
+				"instance {:?} {} taking over before it is ready",
+				self.role,
+				self.cur_ins.number()
+			);
+			in_the_way.extend(self.finish_handover());
+		}
+		let slot = self.slot(start_type);
+		in_the_way.extend(self.take_slot(slot));
+		in_the_way
+	}
+}
+
+// Stops the instances when the container task is dropped, which is how the task manager ends it
+// on node shutdown.
+struct StopOnExit(RunningApps);
+
+impl Drop for StopOnExit {
+	fn drop(&mut self) {
+		log::info!("container task exiting, stopping app instances");
+		// Never waits for a lock, its holder may need this thread to go on. The instances of an
+		// app that is busy are left running, the next run of the node takes them over.
+		let Some(apps) = self.0.try_lock() else {
+			log::warn!("apps are busy, leaving their instances running");
+			return;
+		};
+		let mut instances = Vec::new();
+		for app in apps.values() {
+			match app.try_lock() {
+				Some(mut app) => instances.extend(app.take_instances()),
+				None => log::warn!("app is busy, leaving its instances running"),
+			}
+		}
+		std::thread::scope(|scope| {
+			for mut instance in instances {
+				scope.spawn(move || stop_instance(&mut instance));
+			}
+		});
+	}
+}
+
+// Restart the instances that exited on their own and hand over to the ones that became ready.
+async fn supervise(apps: &RunningApps, policy: &RestartPolicy, ready_timeout: Duration) {
+	let apps: Vec<_> = apps.lock().await.values().cloned().collect();
+	for app in apps {
+		let Supervised { previous, crashed } =
+			supervise_app(&mut *app.lock().await, policy, ready_timeout);
+		stop_instances(previous.into_iter().collect()).await;
+		if crashed.is_empty() {
+			continue;
+		}
+		let (slots, crashed): (Vec<_>, Vec<_>) = crashed.into_iter().unzip();
+		let stopped = stop_instances(crashed).await;
+		let mut app = app.lock().await;
+		for (slot, instance) in slots.into_iter().zip(stopped) {
+			app.restart_instance(slot, instance, policy);
+		}
+	}
+}
+
+// What supervising an app took out of it, to be dealt with once its lock is released.
+#[derive(Default)]
+struct Supervised {
+	// Instance taken over from, to be stopped.
+	previous: Option<Instance>,
+	// Crashed instances due for a restart and their slots, to be stopped and started again.
+	crashed: Vec<(InstanceIndex, Instance)>,
+}
+
+fn supervise_app(
+	app: &mut RunningApp,
+	policy: &RestartPolicy,
+	ready_timeout: Duration,
+) -> Supervised {
+	let mut supervised = Supervised::default();
+	let now = Instant::now();
+	let role = app.role;
+	let ports = app.ports();
+	for index in [InstanceIndex::Instance1, InstanceIndex::Instance2] {
+		let Some(instance) = app.instance_mut(index) else { continue };
+		let state = instance.state();
+		let action = instance.supervision.observe(state, now, policy);
+		if action == Action::Restart {
+			if let Some(instance) = app.instance_mut(index).take() {
+				app.restarting.push(index);
+				supervised.crashed.push((index, instance));
 			}
+			continue;
 		}
+		log_action(role, index, &instance.supervision, action, policy);
+		instance.collect();
+		readiness::check(instance, &ports, ready_timeout, now);
 	}
-	if start_flag {
+	if app.handover && app.synced() {
+		log::info!("instance {:?} {} is ready, taking over", role, app.cur_ins.number());
+		supervised.previous = app.finish_handover();
+	}
+	supervised
+}
+
+fn log_action(
+	role: AppRole,
+	index: InstanceIndex,
+	supervision: &Supervision,
+	action: Action,
+	policy: &RestartPolicy,
+) {
+	match action {
+		Action::Crashed(backoff) => log::warn!(
+			"instance {:?} {} exited with {:?}, failure {}/{}, restarting in {:?}",
+			role,
+			index.number(),
+			supervision.last_exit.flatten(),
+			supervision.failures,
+			policy.failure_budget,
+			backoff
+		),
+		Action::GiveUp => log::error!(
+			"instance {:?} {} exited with {:?} after {} restarts, failure budget used up, leaving it down",
+			role,
+			index.number(),
+			supervision.last_exit.flatten(),
+			supervision.restarts
+		),
+		Action::Wait | Action::Restart => {},
+	}
+}
+
+impl RunningApp {
+	// Start a crashed instance, stopped with the lock released, again in its slot. Left stopped
+	// when the slot was emptied or taken for another instance meanwhile.
+	fn restart_instance(
+		&mut self,
+		slot: InstanceIndex,
+		instance: Option<Instance>,
+		policy: &RestartPolicy,
+	) {
+		let restarting = self.restarting.contains(&slot);
+		self.restarting.retain(|restarting| *restarting != slot);
+		let Some(mut instance) = instance else { return };
+		if !restarting || self.instance(slot).is_some() {
+			log::info!(
+				"instance {:?} {} was replaced, not restarting it",
+				self.role,
+				slot.number()
+			);
+			return;
+		}
+		match instance.restart() {
+			Ok(()) => {
+				instance.supervision.restarted();
+				log::info!(
+					"restarted instance {:?} {}:{:?}",
+					self.role,
+					slot.number(),
+					instance.handle
+				);
+			},
+			Err(err) => {
+				log::warn!("restart instance {:?} {} error:{:?}", self.role, slot.number(), err);
+				let action = instance.supervision.crashed(None, Instant::now(), policy);
+				log_action(self.role, slot, &instance.supervision, action, policy);
+			},
+		}
+		*self.instance_mut(slot) = Some(instance);
+	}
+}
+
+async fn process_download_task(
+	backends: Backends,
+	app_info: DownloadInfo,
+	running_app: Arc<Mutex<RunningApp>>,
+	progress: Arc<DownloadProgress>,
+	sync_args: Option<Vec<String>>,
+	option_args: Option<Vec<String>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+	log::info!("===========Download app {:?}=========", app_info.app_id);
+	let prepared = backends.for_app(&app_info).prepare(&app_info, &progress).await;
+	log::info!("prepare result:{:?}", prepared);
+
+	// Checked and acted on under one lock, so a cancel can not slip in between.
+	let wanted =
+		|app: &RunningApp| !progress.cancelled() && Arc::ptr_eq(&app.download_progress, &progress);
+	let app = match prepared {
+		Ok(_) => lock_with_room(&running_app, StartType::SYNC, wanted).await,
+		Err(_) => Some(running_app.lock().await).filter(|app| wanted(app)),
+	};
+	let Some(mut app) = app else {
+		log::info!("download of app {} was cancelled, not starting it", app_info.app_id);
+		return Ok(());
+	};
+	app.download = None;
+	if let Ok(download_url) = prepared {
 		log::info!("===============start app for sync=================");
-		let result = process_run_task(
-			data_path,
+		let result = run_instance(
+			&mut app,
+			backends,
 			app_info.clone(),
 			sync_args,
 			option_args,
-			running_app.clone(),
 			StartType::SYNC,
-		)
-		.await;
+		);
 		log::info!("start result:{:?}", result);
-		let mut app = running_app.lock().await;
-		app.app_info = Some(app_info);
-		app.group_id = new_group;
+		app.app_info = Some(app_info.clone());
+		app.group_id = app_info.group;
 		app.running = RunStatus::Downloaded;
+		if download_url.is_some() {
+			app.download_url = download_url;
+		}
 	} else {
-		let mut app = running_app.lock().await;
 		app.running = RunStatus::Pending;
 	}
 	Ok(())
 }
 
-async fn process_run_task(
-	data_path: PathBuf,
-	app_info: DownloadInfo,
-	run_args: Option<Vec<u8>>,
-	option_args: Option<Vec<u8>>,
-	running_app: Arc<Mutex<RunningApp>>,
-	start_type: StartType,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-	let run_as_docker = app_info.is_docker_image;
+// Values of the `{{name}}` placeholders of an instance, also passed as `POPSICLE_<NAME>` envs.
+fn template_vars(data_dir: &Path, port_base: u16) -> Vec<(&'static str, String)> {
+	let mut vars = vec![
+		("data_dir", data_dir.to_string_lossy().into_owned()),
+		("port_base", port_base.to_string()),
+	];
+	vars.extend(
+		INSTANCE_PORTS
+			.iter()
+			.map(|(name, offset)| (*name, (port_base + *offset).to_string())),
+	);
+	vars
+}
 
-	let extension_args_clone;
+// Fill in the placeholders of `vars`, unknown ones are left as they are.
+fn expand(value: &str, vars: &[(&str, String)]) -> String {
+	vars.iter().fold(value.to_string(), |value, (name, var)| {
+		value.replace(&format!("{{{{{}}}}}", name), var)
+	})
+}
 
-	let extension_args: Option<Vec<&str>> = if let Some(run_args) = run_args {
-		extension_args_clone = run_args.clone();
+// Args, envs and log file of an instance of `app` started into `slot`.
+fn launch(
+	app_info: &DownloadInfo,
+	run_args: Option<Vec<String>>,
+	option_args: Option<Vec<String>>,
+	slot: InstanceIndex,
+	app: &RunningApp,
+) -> Result<Launch, Box<dyn Error + Send + Sync>> {
+	// App args are kept as registered, one argument per entry.
+	let mut args = app_info
+		.args
+		.iter()
+		.map(|arg| Ok(std::str::from_utf8(arg)?.to_string()))
+		.collect::<Result<Vec<String>, std::str::Utf8Error>>()?;
+
+	// Operator args from offchain storage, one argument per entry as well.
+	args.extend(run_args.unwrap_or_default());
+
+	let mut envs = app_info
+		.envs
+		.iter()
+		.map(|(name, value)| {
+			Ok((std::str::from_utf8(name)?.to_string(), std::str::from_utf8(value)?.to_string()))
+		})
+		.collect::<Result<Vec<(String, String)>, std::str::Utf8Error>>()?;
+
+	let options = option_args.unwrap_or_default();
+
+	// Instances running side by side keep apart by these.
+	let data_dir = app.instance_dir(app_info.app_id, slot);
+	let port_base = app.instance_port_base(slot);
+	let vars = template_vars(&data_dir, port_base);
+	let args = args.iter().map(|arg| expand(arg, &vars)).collect();
+	let options = options.iter().map(|option| expand(option, &vars)).collect();
+	envs = envs.into_iter().map(|(name, value)| (name, expand(&value, &vars))).collect();
+	envs.extend(
+		vars.iter()
+			.map(|(name, var)| (format!("POPSICLE_{}", name.to_uppercase()), var.clone())),
+	);
 
-		Some(
-			std::str::from_utf8(&extension_args_clone)?
-				.split(' ')
-				.into_iter()
-				.map(|arg| std::str::from_utf8(arg.as_bytes()).unwrap())
-				.collect(),
-		)
-	} else {
-		None
+	let file_name = std::str::from_utf8(&app_info.file_name)?;
+	let log_file = match &app_info.log {
+		Some(log) => std::str::from_utf8(log)?,
+		None => file_name,
 	};
+	// Comes from the chain, it must not name a file outside of the data directory.
+	if Path::new(log_file).file_name() != Some(log_file.as_ref()) {
+		return Err(format!("log file {:?} is not a plain file name", log_file).into());
+	}
 
-	let app_args_clone;
-
-	let mut args: Vec<&str> = if let Some(app_args) = app_info.args {
-		app_args_clone = app_args.clone();
+	Ok(Launch {
+		// Both slots of every app may run at once, so their containers need distinct names.
+		name: format!("{}-{}-{}", file_name, app.role.key(), slot.number()),
+		args,
+		envs,
+		options,
+		log_file: data_dir.join(log_file),
+		data_dir,
+		port_base,
+	})
+}
 
-		std::str::from_utf8(&app_args_clone)?
-			.split(' ')
-			.into_iter()
-			.map(|arg| std::str::from_utf8(arg.as_bytes()).unwrap())
-			.collect()
-	} else {
-		Vec::new()
+async fn process_run_task(
+	backends: Backends,
+	app_info: DownloadInfo,
+	run_args: Option<Vec<String>>,
+	option_args: Option<Vec<String>>,
+	running_app: Arc<Mutex<RunningApp>>,
+	start_type: StartType,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+	let Some(mut app) = lock_with_room(&running_app, start_type, |_| true).await else {
+		return Ok(());
 	};
+	let previous = run_instance(&mut app, backends, app_info, run_args, option_args, start_type)?;
+	drop(app);
+	stop_instances(previous.into_iter().collect()).await;
+	Ok(())
+}
 
-	if let Some(extension_args) = extension_args {
-		args.extend(extension_args);
-	}
-
-	let log_file_name;
+// Start an instance in the slot `make_room` cleared. Returns the instance it took over from
+// right away, if any, to be stopped once the lock of the app is released.
+fn run_instance(
+	app: &mut RunningApp,
+	backends: Backends,
+	app_info: DownloadInfo,
+	run_args: Option<Vec<String>>,
+	option_args: Option<Vec<String>>,
+	start_type: StartType,
+) -> Result<Option<Instance>, Box<dyn Error + Send + Sync>> {
+	let backend = backends.for_app(&app_info);
 
-	let log_file_buf;
+	if start_type == StartType::RUN {
+		app.run_app_info = Some(app_info.clone());
+	}
+	let slot = app.slot(start_type);
+	let launch = launch(&app_info, run_args, option_args, slot, app)?;
+	log::info!("launch:{:?}", launch);
 
-	if app_info.log.is_none() {
-		log_file_name = std::str::from_utf8(&app_info.file_name)?;
-	} else {
-		log_file_buf = app_info.log.unwrap();
+	// start new instance
+	let mut instance = Instance::start(backend, app_info, launch, app.log_policy)?;
+	// Its lines are tagged with the round it serves, a syncing instance serves none yet.
+	instance.log.round = if start_type == StartType::SYNC { None } else { app.round };
+	*app.instance_mut(slot) = Some(instance);
 
-		log_file_name = std::str::from_utf8(&log_file_buf)?;
+	let mut previous = None;
+	if start_type == StartType::RUN {
+		// The other slot serves the previous round until the new instance is ready.
+		app.handover = true;
+		if app.synced() {
+			previous = app.finish_handover();
+		}
 	}
+	log::info!("app:{:?}", app);
+	Ok(previous)
+}
 
-	log::info!("log_file_name:{:?}", log_file_name);
-	log::info!("args:{:?}", args);
-	let outputs = File::create(log_file_name)?;
-
-	let errors = outputs.try_clone()?;
+// Keep an app for every role in `loads` and stop the apps of groups the node no longer processes
+// for. Returns the apps along with the release each should load.
+async fn assign_apps(
+	apps: &RunningApps,
+	loads: Vec<(AppRole, Option<DownloadInfo>)>,
+	config: &ContainerConfig,
+) -> Vec<(Arc<Mutex<RunningApp>>, Option<DownloadInfo>)> {
+	let (assigned, removed) = {
+		let mut apps = apps.lock().await;
+		let removed: Vec<AppRole> = apps
+			.keys()
+			.filter(|role| {
+				**role != AppRole::Sequencer && !loads.iter().any(|(load, _)| load == *role)
+			})
+			.copied()
+			.collect();
+		let removed: Vec<_> = removed.iter().filter_map(|role| apps.remove(role)).collect();
+		let mut assigned = Vec::new();
+		for (role, app_info) in loads {
+			let app = match apps.entry(role) {
+				Entry::Occupied(entry) => entry.get().clone(),
+				Entry::Vacant(entry) => match RunningApp::new(role, config) {
+					Ok(app) => entry.insert(Arc::new(Mutex::new(app))).clone(),
+					Err(err) => {
+						log::error!("not running the app of {:?}: {}", role, err);
+						continue;
+					},
+				},
+			};
+			assigned.push((app, app_info));
+		}
+		(assigned, removed)
+	};
+	for app in removed {
+		let instances = {
+			let mut app = app.lock().await;
+			log::info!("{:?} is no longer assigned, stopping its app", app.role);
+			app.cancel_download(&config.backends, None);
+			app.take_instances()
+		};
+		stop_instances(instances).await;
+	}
+	assigned
+}
 
+// Fetch and sync the release of `app_info` for `sync_round` when the group or release of the app
+// changed.
+async fn load_app<Block, TBackend>(
+	app_info: Option<DownloadInfo>,
+	sync_round: Option<u32>,
+	running_app: &Arc<Mutex<RunningApp>>,
+	backends: &Backends,
+	backend: &TBackend,
+) where
+	Block: BlockT,
+	TBackend: sc_client_api::backend::Backend<Block>,
+{
 	let mut app = running_app.lock().await;
 
-	let (old_instance, instance_docker, op_docker_name) = if app.cur_ins == InstanceIndex::Instance1
-	{
-		let is_docker_instance = app.instance1_docker;
-		let top_docker_name = app.instance1_docker_name.clone();
-		(&mut app.instance1, is_docker_instance, top_docker_name)
-	} else {
-		let is_docker_instance = app.instance2_docker;
-		let top_docker_name = app.instance2_docker_name.clone();
-		(&mut app.instance2, is_docker_instance, top_docker_name)
-	};
-	// stop old instance
-	if instance_docker {
-		//reomve docker container
-		if let Some(docker_name) = op_docker_name {
-			let kill_result = remove_docker_container(std::str::from_utf8(&docker_name)?).await;
-			log::info!("kill old docker instance:{:?}", kill_result);
-		}
-	} else {
-		if let Some(ref mut old_instance) = old_instance {
-			old_instance.kill()?;
-			let kill_result = old_instance.wait()?;
-			log::info!("kill old instance:{:?}", kill_result);
-		}
-	}
-	// start new instance
-	let mut instance: Option<Child> = None;
-	if run_as_docker {
-		let image_name = app_info.docker_image.ok_or("docker image not exist")?;
-		let docker_image = std::str::from_utf8(&image_name)?;
-		let start_result = start_docker_container(
-			std::str::from_utf8(&app_info.file_name)?,
-			docker_image,
-			args,
-			option_args,
-			outputs.try_clone()?,
-		)
-		.await;
-		log::info!("start docker container :{:?}", start_result);
-	} else {
-		let download_path = format!(
-			"{}/sdk/{}",
-			data_path.as_os_str().to_str().unwrap(),
-			std::str::from_utf8(&app_info.file_name)?
-		);
-		instance = Some(
-			Command::new(download_path)
-				.stdin(Stdio::piped())
-				.stderr(Stdio::from(outputs))
-				.stdout(Stdio::from(errors))
-				.args(args)
-				.spawn()
-				.expect("failed to execute process"),
-		);
-	}
-	if start_type == StartType::RUN {
-		if app.cur_ins == InstanceIndex::Instance1 {
-			if app.instance2_docker {
-				let docker_name_op = app.instance2_docker_name.clone();
-				if let Some(docker_name) = docker_name_op {
-					let kill_result =
-						remove_docker_container(std::str::from_utf8(&docker_name)?).await;
-					log::info!("kill docker instance2:{:?}", kill_result);
-				}
-				app.instance2_docker_name = None;
-				app.instance2_docker = false;
-			} else {
-				let other_instance = &mut app.instance2;
-				if let Some(ref mut other_instance) = other_instance {
-					other_instance.kill()?;
-					let kill_result = other_instance.wait()?;
-					log::info!("kill instance2:{:?}", kill_result);
-				}
-				app.instance1 = instance;
-				app.instance2 = None;
-			}
-			app.cur_ins = InstanceIndex::Instance2;
-		} else {
-			if app.instance1_docker {
-				let docker_name_op = app.instance1_docker_name.clone();
-				if let Some(docker_name) = docker_name_op {
-					let kill_result =
-						remove_docker_container(std::str::from_utf8(&docker_name)?).await;
-					log::info!("kill docker instance1:{:?}", kill_result);
-				}
-				app.instance1_docker_name = None;
-				app.instance1_docker = false;
-			} else {
-				let other_instance = &mut app.instance1;
-				if let Some(ref mut other_instance) = other_instance {
-					other_instance.kill()?;
-					let kill_result = other_instance.wait()?;
-					log::info!("kill instance1:{:?}", kill_result);
-				}
-				app.instance2 = instance;
-				app.instance1 = None;
-			}
-			app.cur_ins = InstanceIndex::Instance1;
-		}
-	} else {
-		if app.cur_ins == InstanceIndex::Instance1 {
-			app.instance1 = instance;
-			if run_as_docker {
-				app.instance1_docker = true;
-				app.instance1_docker_name = Some(app_info.file_name);
-			} else {
-				app.instance1_docker = false;
-				app.instance1_docker_name = None;
-			}
-		} else {
-			app.instance2 = instance;
-			if run_as_docker {
-				app.instance2_docker = true;
-				app.instance2_docker_name = Some(app_info.file_name);
-			} else {
-				app.instance2_docker = false;
-				app.instance2_docker_name = None;
+	// The group or its app changed while downloading, the download is for nothing now.
+	app.cancel_download(backends, app_info.as_ref());
+
+	let old_group_id = app.group_id;
+
+	match app_info {
+		Some(app_info) => {
+			let new_group = app_info.group;
+			let app_id = app_info.app_id;
+			let run_status = &app.running;
+			// A release scheduled for the next round (or a rollback) keeps the group but
+			// changes the binary, fetch it and sync it before the round switch.
+			let release_changed = old_group_id == new_group &&
+				app.app_info.as_ref().is_some_and(|info| !same_release(info, &app_info));
+			if (old_group_id != new_group && *run_status == RunStatus::Pending) ||
+				(release_changed &&
+					*run_status != RunStatus::Downloading &&
+					*run_status != RunStatus::Stopped)
+			{
+				let sync_args = offchain_args::<Block, _>(backend, ArgsKind::Sync, app_id);
+				log::info!("offchain_storage of sync_args:{:?}", sync_args);
+				let option_args = offchain_args::<Block, _>(backend, ArgsKind::Option, app_id);
+				log::info!("offchain_storage of option_args:{:?}", option_args);
+				app.start_download(
+					running_app,
+					backends,
+					app_info,
+					sync_args,
+					option_args,
+					sync_round,
+				);
 			}
+		},
+		None => log::info!("None"),
+	}
+}
+
+// Hand `round` over to the synced release of the app.
+async fn run_app<Block, TBackend>(
+	running_app: &Arc<Mutex<RunningApp>>,
+	round: u32,
+	backends: &Backends,
+	backend: &TBackend,
+) where
+	Block: BlockT,
+	TBackend: sc_client_api::backend::Backend<Block>,
+{
+	let mut app = running_app.lock().await;
+	let app_id = app.app_id;
+	if let Some(app_info) = app.app_info.clone() {
+		if app.switch_due(round) {
+			log::info!("run round {}:{:?}", round, app);
+			let run_args = offchain_args::<Block, _>(backend, ArgsKind::Run, app_id);
+			let option_args = offchain_args::<Block, _>(backend, ArgsKind::Option, app_id);
+			log::info!("offchain_storage of option_args:{:?}", option_args);
+			tokio::spawn(process_run_task(
+				backends.clone(),
+				app_info,
+				run_args,
+				option_args,
+				running_app.clone(),
+				StartType::RUN,
+			));
+
+			app.running = RunStatus::Pending;
+			app.round = Some(round);
 		}
 	}
-	log::info!("app:{:?}", app);
-	Ok(())
 }
 
 async fn handle_new_best_parachain_head<P, Block, TBackend>(
@@ -543,8 +1451,8 @@ async fn handle_new_best_parachain_head<P, Block, TBackend>(
 	relay_chain: impl RelayChainInterface + Clone,
 	p_hash: H256,
 	para_id: ParaId,
-	data_path: PathBuf,
-	running_app: Arc<Mutex<RunningApp>>,
+	config: &ContainerConfig,
+	apps: &RunningApps,
 	backend: Arc<TBackend>,
 ) -> Result<(), Box<dyn Error>>
 where
@@ -554,19 +1462,6 @@ where
 	<Block::Header as HeaderT>::Number: Into<u32>,
 	TBackend: 'static + sc_client_api::backend::Backend<Block> + Send,
 {
-	let offchain_storage = backend.offchain_storage();
-
-	let (mut run_args, mut sync_args, mut option_args) =
-		if let Some(storage) = offchain_storage.clone() {
-			let prefix = &STORAGE_PREFIX;
-			(
-				storage.get(prefix, RUN_ARGS_KEY),
-				storage.get(prefix, SYNC_ARGS_KEY),
-				storage.get(prefix, OPTION_ARGS_KEY),
-			)
-		} else {
-			(None, None, None)
-		};
 	// Check if there is a download task
 	let head = validation_data.clone().parent_head.0;
 
@@ -579,132 +1474,186 @@ where
 
 	let xx = keystore.sr25519_public_keys(sp_application_crypto::key_types::AURA)[0];
 
-	let should_load: Option<DownloadInfo> = parachain.runtime_api().shuld_load(hash, xx.into())?;
+	let api_version = parachain
+		.runtime_api()
+		.api_version::<dyn ContainerRuntimeApi<Block, AccountId32>>(hash)?;
+
+	// Runtimes from before v5 return the download info in an older layout.
+	let should_load: Option<DownloadInfo> = match api_version {
+		Some(version) if version >= 5 => parachain.runtime_api().shuld_load(hash, xx.into())?,
+		Some(version) if version >= 3 => {
+			#[allow(deprecated)]
+			let v4 = parachain.runtime_api().shuld_load_before_version_5(hash, xx.into())?;
+			v4.map(DownloadInfo::from)
+		},
+		_ => {
+			#[allow(deprecated)]
+			let legacy = parachain.runtime_api().shuld_load_before_version_3(hash, xx.into())?;
+			legacy.map(DownloadInfo::from)
+		},
+	};
 	log::info!("app download info of sequencer's group:{:?}", should_load);
 
-	let number = (*parachain_head.number()).into();
-	{
-		let mut app = running_app.lock().await;
+	// Runtimes from before v4 assign no apps to processors.
+	let processor_apps = match api_version {
+		Some(version) if version >= 5 => parachain.runtime_api().processor_apps(hash, xx.into())?,
+		Some(4) => {
+			#[allow(deprecated)]
+			let v4 = parachain.runtime_api().processor_apps_before_version_5(hash, xx.into())?;
+			v4.into_iter().map(DownloadInfo::from).collect()
+		},
+		_ => Vec::new(),
+	};
+	log::info!("app download info of processor's groups:{:?}", processor_apps);
 
-		let old_group_id = app.group_id;
-
-		match should_load {
-			Some(app_info) => {
-				let new_group = app_info.group;
-				let app_id = app_info.app_id;
-				let run_status = &app.running;
-				if old_group_id != new_group && *run_status == RunStatus::Pending {
-					if sync_args == None {
-						sync_args = if let Some(storage) = offchain_storage.clone() {
-							let prefix = &STORAGE_PREFIX;
-							let sync_args = format!(
-								"{}:{}",
-								std::str::from_utf8(SYNC_ARGS_KEY).unwrap(),
-								app_id
-							);
-							storage.get(prefix, sync_args.as_bytes())
-						} else {
-							None
-						};
-					}
-					log::info!("offchain_storage of sync_args:{:?}", sync_args);
-					if option_args == None {
-						option_args = if let Some(storage) = offchain_storage.clone() {
-							let prefix = &STORAGE_PREFIX;
-							let option_args = format!(
-								"{}:{}",
-								std::str::from_utf8(OPTION_ARGS_KEY).unwrap(),
-								app_id
-							);
-
-							storage.get(prefix, option_args.as_bytes())
-						} else {
-							None
-						};
-					}
-					log::info!("offchain_storage of option_args:{:?}", option_args);
-					app.running = RunStatus::Downloading;
-					app.app_id = app_id;
-					tokio::spawn(process_download_task(
-						data_path.clone(),
-						app_info,
-						running_app.clone(),
-						new_group,
-						sync_args,
-						option_args.clone(),
-					));
-				}
-			},
-			None => log::info!("None"),
+	let mut loads = vec![(AppRole::Sequencer, should_load)];
+	loads.extend(
+		processor_apps
+			.into_iter()
+			.map(|app_info| (AppRole::Processor(app_info.group), Some(app_info))),
+	);
+	let apps = assign_apps(apps, loads, config).await;
+
+	// The round started at this head or before, and the round a release fetched now is synced
+	// for: the next one to start. An app switches at the first head it sees of the round it
+	// synced for, so a start block skipped by a reorg or a missed head is caught up with.
+	let number: u32 = (*parachain_head.number()).into();
+	let (round, sync_round) = if api_version.is_some_and(|version| version >= 3) {
+		let schedule = parachain.runtime_api().round_schedule(hash)?;
+		if number >= schedule.starting_block.into() {
+			(Some(schedule.round_index), Some(schedule.round_index + 1))
+		} else {
+			(None, Some(schedule.round_index))
 		}
+	} else {
+		// Older runtimes only tell whether the round starts at this head, which then stands in
+		// for the round.
+		(parachain.runtime_api().should_run(hash)?.then_some(number), None)
+	};
+
+	for (running_app, app_info) in &apps {
+		load_app::<Block, _>(
+			app_info.clone(),
+			sync_round,
+			running_app,
+			&config.backends,
+			&*backend,
+		)
+		.await;
 	}
-	let should_run = parachain.runtime_api().should_run(hash)?;
 
-	if should_run {
-		let mut app = running_app.lock().await;
-		let run_status = &app.running;
-		let app_id = app.app_id;
-		if let Some(app_info) = app.app_info.clone() {
-			if *run_status == RunStatus::Downloaded {
-				log::info!("run:{:?}", app);
-				if run_args == None {
-					run_args = if let Some(storage) = offchain_storage.clone() {
-						let prefix = &STORAGE_PREFIX;
-						let run_args =
-							format!("{}:{}", std::str::from_utf8(RUN_ARGS_KEY).unwrap(), app_id);
-
-						storage.get(prefix, run_args.as_bytes())
-					} else {
-						None
-					};
-				}
-				if option_args == None {
-					option_args = if let Some(storage) = offchain_storage {
-						let prefix = &STORAGE_PREFIX;
-						let option_args =
-							format!("{}:{}", std::str::from_utf8(OPTION_ARGS_KEY).unwrap(), app_id);
-
-						storage.get(prefix, option_args.as_bytes())
-					} else {
-						None
-					};
-				}
-				log::info!("offchain_storage of option_args:{:?}", option_args);
-				tokio::spawn(process_run_task(
-					data_path,
-					app_info,
-					run_args,
-					option_args,
-					running_app.clone(),
-					StartType::RUN,
-				));
+	if let Some(round) = round {
+		for (running_app, _) in &apps {
+			run_app::<Block, _>(running_app, round, &config.backends, &*backend).await;
+		}
+	}
+	Ok(())
+}
 
+fn offchain_args<Block, TBackend>(
+	backend: &TBackend,
+	kind: ArgsKind,
+	app_id: u32,
+) -> Option<Vec<String>>
+where
+	Block: BlockT,
+	TBackend: sc_client_api::backend::Backend<Block>,
+{
+	effective_args(&backend.offchain_storage()?, kind, app_id)
+}
+
+async fn handle_container_command<Block, TBackend>(
+	role: AppRole,
+	command: ContainerCommand,
+	backends: Backends,
+	apps: &RunningApps,
+	backend: Arc<TBackend>,
+) -> CommandResult
+where
+	Block: BlockT,
+	TBackend: 'static + sc_client_api::backend::Backend<Block> + Send,
+{
+	log::info!("container command for {:?}:{:?}", role, command);
+	let running_app = apps.lock().await.get(&role).cloned().ok_or("no such app")?;
+	match command {
+		ContainerCommand::Restart => {
+			let (app_info, app_id) = {
+				let app = running_app.lock().await;
+				(app.run_app_info.clone().ok_or("no active instance")?, app.app_id)
+			};
+			let run_args = offchain_args::<Block, _>(&*backend, ArgsKind::Run, app_id);
+			let option_args = offchain_args::<Block, _>(&*backend, ArgsKind::Option, app_id);
+			process_run_task(
+				backends,
+				app_info,
+				run_args,
+				option_args,
+				running_app.clone(),
+				StartType::RESTART,
+			)
+			.await
+			.map_err(|err| err.to_string())?;
+			let mut app = running_app.lock().await;
+			if app.running == RunStatus::Stopped {
 				app.running = RunStatus::Pending;
 			}
-		}
+		},
+		ContainerCommand::Redownload => {
+			let mut app = running_app.lock().await;
+			if app.running == RunStatus::Downloading {
+				return Err("download in progress".into());
+			}
+			if let Some(app_info) = &app.app_info {
+				log::info!("purge app:{:?}", backends.for_app(app_info).purge(app_info));
+			}
+			// Forget the group so the next head fetches the release again.
+			app.group_id = 0xFFFFFFFF;
+			app.running = RunStatus::Pending;
+		},
+		ContainerCommand::Stop => {
+			let instances = {
+				let mut app = running_app.lock().await;
+				app.cancel_download(&backends, None);
+				app.running = RunStatus::Stopped;
+				app.take_instances()
+			};
+			stop_instances(instances).await;
+		},
 	}
 	Ok(())
 }
 
-async fn new_best_heads(
+async fn parachain_heads(
 	relay_chain: impl RelayChainInterface + Clone,
 	para_id: ParaId,
+	follow: HeadFollow,
 ) -> RelayChainResult<impl Stream<Item = (u32, PersistedValidationData, H256)>> {
-	let new_best_notification_stream =
-		relay_chain.new_best_notification_stream().await?.filter_map(move |n| {
-			let relay_chain = relay_chain.clone();
-			async move {
-				let relay_head: PersistedValidationData = relay_chain
-					.persisted_validation_data(n.hash(), para_id, OccupiedCoreAssumption::TimedOut)
+	let (relay_heads, depth) = match follow {
+		HeadFollow::Best { depth } => (relay_chain.new_best_notification_stream().await?, depth),
+		HeadFollow::Finalized => (relay_chain.finality_notification_stream().await?, 0),
+	};
+	let parachain_heads = relay_heads.filter_map(move |n| {
+		let relay_chain = relay_chain.clone();
+		async move {
+			let n = match depth {
+				0 => n,
+				depth => relay_chain
+					.header(BlockId::Number(n.number.checked_sub(depth)?))
 					.await
-					.map(|s| s.map(|s| s))
 					.ok()
-					.flatten()?;
-				Some((n.number, relay_head, n.hash()))
-			}
-		});
+					.flatten()?,
+			};
+			let relay_head: PersistedValidationData = relay_chain
+				.persisted_validation_data(n.hash(), para_id, OccupiedCoreAssumption::TimedOut)
+				.await
+				.map(|s| s.map(|s| s))
+				.ok()
+				.flatten()?;
+			Some((n.number, relay_head, n.hash()))
+		}
+	});
 
-	Ok(new_best_notification_stream)
+	Ok(parachain_heads)
 }
 
 async fn relay_chain_notification<P, R, Block, TBackend>(
@@ -712,8 +1661,10 @@ async fn relay_chain_notification<P, R, Block, TBackend>(
 	parachain: Arc<P>,
 	relay_chain: R,
 	keystore: KeystorePtr,
-	data_path: PathBuf,
 	backend: Arc<TBackend>,
+	container: ContainerHandle,
+	commands: ContainerCommands,
+	config: ContainerConfig,
 ) where
 	R: RelayChainInterface + Clone,
 	Block: BlockT,
@@ -722,38 +1673,56 @@ async fn relay_chain_notification<P, R, Block, TBackend>(
 	<Block::Header as HeaderT>::Number: Into<u32>,
 	TBackend: 'static + sc_client_api::backend::Backend<Block> + Send,
 {
-	let new_best_heads = match new_best_heads(relay_chain.clone(), para_id).await {
+	let heads = match parachain_heads(relay_chain.clone(), para_id, config.follow).await {
 		Ok(best_heads_stream) => best_heads_stream.fuse(),
 		Err(_err) => {
 			return;
 		},
 	};
-	pin_mut!(new_best_heads);
-	let runing_app = Arc::new(Mutex::new(RunningApp {
-		group_id: 0xFFFFFFFF,
-		app_id: 0xFFFFFFFF,
-		running: RunStatus::Pending,
-		app_info: None,
-		instance1: None,
-		instance2: None,
-		instance1_docker: false,
-		instance2_docker: false,
-		instance1_docker_name: None,
-		instance2_docker_name: None,
-		cur_ins: InstanceIndex::Instance1,
-	}));
+	pin_mut!(heads);
+	let apps = container.apps;
+	*apps.lock().await = state::restore(&config.state_file, &config);
+	let _stop_on_exit = StopOnExit(apps.clone());
+	let mut saved = None;
+	let mut commands = commands;
+	let mut supervise_ticks =
+		stream::unfold(tokio::time::interval(SUPERVISE_INTERVAL), |mut interval| async move {
+			interval.tick().await;
+			Some(((), interval))
+		})
+		.boxed()
+		.fuse();
 	loop {
 		select! {
-			h = new_best_heads.next() => {
+			h = heads.next() => {
 				match h {
 					Some((height, head, hash)) => {
-						let _ = handle_new_best_parachain_head(head,height, &*parachain,keystore.clone(), relay_chain.clone(), hash, para_id, data_path.clone(), runing_app.clone(), backend.clone()).await;
+						let _ = handle_new_best_parachain_head(head,height, &*parachain,keystore.clone(), relay_chain.clone(), hash, para_id, &config, &apps, backend.clone()).await;
 					},
 					None => {
 						return;
 					}
 				}
 			},
+			c = commands.next() => {
+				if let Some((role, command, result)) = c {
+					let handled = handle_container_command::<Block, _>(
+						role,
+						command,
+						config.backends.clone(),
+						&apps,
+						backend.clone(),
+					)
+					.await;
+					let _ = result.send(handled);
+				}
+			},
+			_ = supervise_ticks.next() => {
+				supervise(&apps, &config.restart_policy, config.ready_timeout).await
+			},
+		}
+		if let Err(err) = state::save(&apps, &config.state_file, &mut saved).await {
+			log::warn!("save container state {:?} error:{:?}", config.state_file, err);
 		}
 	}
 }
@@ -763,8 +1732,10 @@ pub async fn run_container_task<P, R, Block, TBackend>(
 	parachain: Arc<P>,
 	relay_chain: R,
 	keystore: KeystorePtr,
-	data_path: PathBuf,
 	backend: Arc<TBackend>,
+	container: ContainerHandle,
+	commands: ContainerCommands,
+	config: ContainerConfig,
 ) where
 	R: RelayChainInterface + Clone,
 	Block: BlockT,
@@ -778,22 +1749,28 @@ pub async fn run_container_task<P, R, Block, TBackend>(
 		parachain.clone(),
 		relay_chain,
 		keystore,
-		data_path,
 		backend,
+		container,
+		commands,
+		config,
 	);
 	select! {
 		_ = relay_chain_notification.fuse() => {},
 	}
 }
 
+/// Spawn the container task as an essential task of `task_manager`. The task manager drops it
+/// on shutdown, which stops every app instance gracefully.
 pub fn spawn_container_task<T, R, Block, TBackend>(
 	parachain: Arc<T>,
 	para_id: ParaId,
 	relay_chain: R,
 	task_manager: &TaskManager,
 	keystore: KeystorePtr,
-	data_path: PathBuf,
 	backend: Arc<TBackend>,
+	container: ContainerHandle,
+	commands: ContainerCommands,
+	config: ContainerConfig,
 ) -> sc_service::error::Result<()>
 where
 	Block: BlockT,
@@ -803,16 +1780,640 @@ where
 	<Block::Header as HeaderT>::Number: Into<u32>,
 	TBackend: 'static + sc_client_api::backend::Backend<Block> + Send,
 {
+	AppRole::Sequencer
+		.port_base(config.port_base)
+		.map_err(sc_service::Error::Other)?;
 	let container_task = run_container_task(
 		para_id,
 		parachain.clone(),
 		relay_chain.clone(),
 		keystore,
-		data_path,
 		backend,
+		container,
+		commands,
+		config,
 	);
 	task_manager
 		.spawn_essential_handle()
 		.spawn_blocking("container_task", None, container_task);
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{backend::FakeBackend, *};
+	use futures::executor::block_on;
+	use primitives_container::ReadinessProbe;
+
+	const READY_TIMEOUT: Duration = Duration::from_secs(600);
+
+	// Logs rotated at a MiB, crashed instances restarted at once and left down after a failed
+	// restart.
+	pub(super) fn policies() -> (LogPolicy, RestartPolicy) {
+		(
+			LogPolicy { max_size: 1 << 20, max_age: Duration::from_secs(3600), generations: 1 },
+			RestartPolicy {
+				backoff: Duration::ZERO,
+				max_backoff: Duration::ZERO,
+				failure_budget: 1,
+			},
+		)
+	}
+
+	// Launch of an instance with its data and log in `dir`.
+	pub(super) fn launch_in(dir: &Path) -> Launch {
+		Launch {
+			name: "app-1".into(),
+			args: Vec::new(),
+			envs: Vec::new(),
+			options: Vec::new(),
+			log_file: dir.join("app"),
+			data_dir: dir.to_path_buf(),
+			port_base: 30000,
+		}
+	}
+
+	pub(super) fn app_info() -> DownloadInfo {
+		DownloadInfo {
+			app_id: 1,
+			app_hash: H256::zero(),
+			file_name: b"app".to_vec(),
+			size: 0,
+			group: 0,
+			url: Vec::new(),
+			args: vec![b"--chain".to_vec(), b"dev".to_vec()],
+			envs: vec![(b"RUST_LOG".to_vec(), b"info".to_vec())],
+			log: None,
+			is_docker_image: false,
+			docker_image: None,
+			mirrors: Vec::new(),
+			readiness: None,
+		}
+	}
+
+	// Ready once it logged an imported block.
+	fn probed_app_info() -> DownloadInfo {
+		let pattern = br"Imported #\d+".to_vec();
+		DownloadInfo { readiness: Some(ReadinessProbe::Log { pattern }), ..app_info() }
+	}
+
+	fn backends() -> (Arc<FakeBackend>, Backends) {
+		let fake = Arc::new(FakeBackend::default());
+		(fake.clone(), Backends::new(fake.clone(), fake))
+	}
+
+	fn start(
+		backends: &Backends,
+		running_app: &Arc<Mutex<RunningApp>>,
+		args: Option<&[&str]>,
+		start_type: StartType,
+	) {
+		block_on(process_run_task(
+			backends.clone(),
+			app_info(),
+			args.map(strings),
+			None,
+			running_app.clone(),
+			start_type,
+		))
+		.unwrap();
+	}
+
+	fn config(backends: &Backends, apps_dir: &Path) -> ContainerConfig {
+		ContainerConfig {
+			backends: backends.clone(),
+			restart_policy: policies().1,
+			state_file: PathBuf::from("state"),
+			apps_dir: apps_dir.to_path_buf(),
+			port_base: 30000,
+			follow: HeadFollow::Finalized,
+			ready_timeout: READY_TIMEOUT,
+			log_policy: policies().0,
+		}
+	}
+
+	fn sequencer() -> Arc<Mutex<RunningApp>> {
+		sequencer_in(Path::new("apps"))
+	}
+
+	// Sequencer whose instances keep their logs under `apps_dir`.
+	fn sequencer_in(apps_dir: &Path) -> Arc<Mutex<RunningApp>> {
+		let config = config(&backends().1, apps_dir);
+		Arc::new(Mutex::new(RunningApp::new(AppRole::Sequencer, &config).unwrap()))
+	}
+
+	fn apps(apps: Vec<Arc<Mutex<RunningApp>>>) -> RunningApps {
+		let apps = apps
+			.into_iter()
+			.map(|app| {
+				let role = block_on(app.lock()).role;
+				(role, app)
+			})
+			.collect();
+		Arc::new(Mutex::new(apps))
+	}
+
+	fn names(launches: &[Launch]) -> Vec<&str> {
+		launches.iter().map(|launch| launch.name.as_str()).collect()
+	}
+
+	fn strings(args: &[&str]) -> Vec<String> {
+		args.iter().map(|arg| arg.to_string()).collect()
+	}
+
+	#[test]
+	fn option_args_are_checked_against_mapped_options() {
+		assert!(validate_args(ArgsKind::Run, &strings(&["--pid", "host"])).is_ok());
+		assert!(
+			validate_args(ArgsKind::Option, &strings(&["--network", "host", "-e", "A=1"])).is_ok()
+		);
+		assert!(validate_args(ArgsKind::Option, &strings(&["--pid", "host"])).is_err());
+		assert!(validate_args(ArgsKind::Run, &[]).is_err());
+		assert!(validate_args(ArgsKind::Run, &strings(&["--name", "a\nb"])).is_err());
+	}
+
+	#[test]
+	fn args_keep_spaces_and_quotes() {
+		let args = strings(&["--base-path", "/data/my chain", "--name=\"a b\""]);
+		assert_eq!(decode_args(&encode_args(&args)), args);
+		// As set by nodes from before the JSON arrays.
+		assert_eq!(decode_args(b"--rpc-port  9944"), strings(&["--rpc-port", "9944"]));
+	}
+
+	#[test]
+	fn launch_keeps_operator_args() {
+		let launch = launch(
+			&app_info(),
+			Some(strings(&["--base-path", "/data/my chain"])),
+			Some(strings(&["--network", "host"])),
+			InstanceIndex::Instance2,
+			&block_on(sequencer().lock()),
+		)
+		.unwrap();
+
+		assert_eq!(launch.name, "app-sequencer-2");
+		assert_eq!(launch.args, vec!["--chain", "dev", "--base-path", "/data/my chain"]);
+		assert_eq!(launch.options, vec!["--network", "host"]);
+		assert_eq!(launch.log_file, PathBuf::from("apps/1/sequencer-2/app"));
+	}
+
+	#[test]
+	fn launch_keeps_log_file_in_data_dir() {
+		let running_app = sequencer();
+		let app = block_on(running_app.lock());
+		let launch_logging = |log: &str| {
+			let app_info = DownloadInfo { log: Some(log.as_bytes().to_vec()), ..app_info() };
+			launch(&app_info, None, None, InstanceIndex::Instance1, &app)
+		};
+
+		assert_eq!(
+			launch_logging("app.log").unwrap().log_file,
+			PathBuf::from("apps/1/sequencer-1/app.log")
+		);
+		for log in ["/tmp/app.log", "../app.log", "logs/app.log", "..", ".", ""] {
+			assert!(launch_logging(log).is_err(), "{:?} accepted", log);
+		}
+	}
+
+	#[test]
+	fn launch_fills_in_instance_dirs_and_ports() {
+		let app_info = DownloadInfo {
+			args: vec![
+				b"--base-path={{data_dir}}".to_vec(),
+				b"--port".to_vec(),
+				b"{{p2p_port}}".to_vec(),
+			],
+			envs: vec![(b"RPC".to_vec(), b"127.0.0.1:{{rpc_port}}".to_vec())],
+			..app_info()
+		};
+		let launch = launch(
+			&app_info,
+			Some(strings(&["--prometheus-port", "{{prometheus_port}}", "{{unknown}}"])),
+			Some(strings(&["-v", "{{data_dir}}:/data"])),
+			InstanceIndex::Instance2,
+			&block_on(sequencer().lock()),
+		)
+		.unwrap();
+
+		assert_eq!(
+			launch.args,
+			vec![
+				"--base-path=apps/1/sequencer-2",
+				"--port",
+				"30050",
+				"--prometheus-port",
+				"30052",
+				"{{unknown}}"
+			]
+		);
+		assert_eq!(launch.options, vec!["-v", "apps/1/sequencer-2:/data"]);
+		assert_eq!(
+			launch.envs,
+			vec![
+				("RPC".to_string(), "127.0.0.1:30051".to_string()),
+				("POPSICLE_DATA_DIR".to_string(), "apps/1/sequencer-2".to_string()),
+				("POPSICLE_PORT_BASE".to_string(), "30050".to_string()),
+				("POPSICLE_P2P_PORT".to_string(), "30050".to_string()),
+				("POPSICLE_RPC_PORT".to_string(), "30051".to_string()),
+				("POPSICLE_PROMETHEUS_PORT".to_string(), "30052".to_string()),
+			]
+		);
+		assert_eq!(launch.data_dir, PathBuf::from("apps/1/sequencer-2"));
+		assert_eq!(launch.port_base, 30050);
+	}
+
+	#[test]
+	fn apps_of_groups_keep_apart() {
+		let processor =
+			RunningApp::new(AppRole::Processor(2), &config(&backends().1, Path::new("apps")))
+				.unwrap();
+		let launch = launch(&app_info(), None, None, InstanceIndex::Instance1, &processor).unwrap();
+
+		assert_eq!(launch.name, "app-group2-1");
+		assert_eq!(launch.data_dir, PathBuf::from("apps/1/group2-1"));
+		assert_eq!(launch.port_base, 30300);
+		assert_eq!(AppRole::Processor(353).port_base(30000), Ok(65400));
+		// The ranges end at the last port, they never overlap.
+		assert!(AppRole::Processor(354).port_base(30000).is_err());
+		assert!(AppRole::Processor(u32::MAX).port_base(30000).is_err());
+		assert!(AppRole::Sequencer.port_base(65500).is_err());
+	}
+
+	#[test]
+	fn assign_apps_stops_apps_of_dropped_groups() {
+		let (fake, backends) = backends();
+		let config = config(&backends, Path::new("apps"));
+		let group1 = Arc::new(Mutex::new(RunningApp::new(AppRole::Processor(1), &config).unwrap()));
+		start(&backends, &group1, None, StartType::SYNC);
+		let apps = apps(vec![sequencer(), group1]);
+
+		let loads = vec![(AppRole::Sequencer, None), (AppRole::Processor(2), Some(app_info()))];
+		let assigned = block_on(assign_apps(&apps, loads, &config));
+
+		let roles: Vec<_> = assigned.iter().map(|(app, _)| block_on(app.lock()).role).collect();
+		assert_eq!(roles, vec![AppRole::Sequencer, AppRole::Processor(2)]);
+		assert!(assigned[1].1.is_some());
+		assert_eq!(
+			block_on(apps.lock()).keys().copied().collect::<Vec<_>>(),
+			vec![AppRole::Sequencer, AppRole::Processor(2)]
+		);
+		assert_eq!(*fake.stopped.lock().unwrap(), vec!["app-group1-1"]);
+	}
+
+	#[test]
+	fn run_takes_over_from_sync_instance() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+
+		start(&backends, &running_app, Some(&["--sync"]), StartType::SYNC);
+		start(&backends, &running_app, None, StartType::RUN);
+
+		let started = fake.started.lock().unwrap();
+		assert_eq!(names(&started), vec!["app-sequencer-1", "app-sequencer-1"]);
+		assert_eq!(started[0].args, vec!["--chain", "dev", "--sync"]);
+		assert_eq!(started[1].args, vec!["--chain", "dev"]);
+		assert_eq!(*fake.stopped.lock().unwrap(), vec!["app-sequencer-1"]);
+
+		let mut app = block_on(running_app.lock());
+		assert_eq!(app.cur_ins, InstanceIndex::Instance2);
+		let status = app.status();
+		assert_eq!(status.instances.len(), 1);
+		assert_eq!(status.instances[0].slot, 1);
+		assert!(status.instances[0].active);
+		assert_eq!(status.instances[0].docker_name, Some("app-sequencer-1".to_string()));
+	}
+
+	#[test]
+	fn restart_keeps_syncing_instance() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+
+		start(&backends, &running_app, None, StartType::SYNC);
+		start(&backends, &running_app, None, StartType::RUN);
+		// The next release syncs in the other slot.
+		start(&backends, &running_app, None, StartType::SYNC);
+		start(&backends, &running_app, None, StartType::RESTART);
+
+		assert_eq!(
+			names(&fake.started.lock().unwrap()),
+			vec!["app-sequencer-1", "app-sequencer-1", "app-sequencer-2", "app-sequencer-1"]
+		);
+		assert_eq!(*fake.stopped.lock().unwrap(), vec!["app-sequencer-1", "app-sequencer-1"]);
+
+		let app = block_on(running_app.lock());
+		assert_eq!(app.cur_ins, InstanceIndex::Instance2);
+		assert!(app.instance1.is_some());
+		assert!(app.instance2.is_some());
+	}
+
+	#[test]
+	fn app_is_not_locked_while_stopping() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+		start(&backends, &running_app, None, StartType::SYNC);
+		*fake.stop_delay.lock().unwrap() = Duration::from_millis(500);
+
+		let run = process_run_task(
+			backends.clone(),
+			app_info(),
+			None,
+			None,
+			running_app.clone(),
+			StartType::RUN,
+		);
+		let (result, stopping) = block_on(future::join(run, async {
+			// Taken out of its slot and still stopping.
+			let app = running_app.lock().await;
+			(app.instance1.is_none(), fake.stopped.lock().unwrap().len())
+		}));
+		result.unwrap();
+
+		assert_eq!(stopping, (true, 0));
+		assert_eq!(*fake.stopped.lock().unwrap(), vec!["app-sequencer-1"]);
+		assert!(block_on(running_app.lock()).instance1.is_some());
+	}
+
+	#[test]
+	fn exit_stops_all_instances() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+
+		start(&backends, &running_app, None, StartType::SYNC);
+		start(&backends, &running_app, None, StartType::RUN);
+		start(&backends, &running_app, None, StartType::SYNC);
+		drop(StopOnExit(apps(vec![running_app.clone()])));
+
+		let mut stopped = fake.stopped.lock().unwrap().clone();
+		stopped.sort();
+		assert_eq!(stopped, vec!["app-sequencer-1", "app-sequencer-1", "app-sequencer-2"]);
+		let app = block_on(running_app.lock());
+		assert!(app.instance1.is_none());
+		assert!(app.instance2.is_none());
+	}
+
+	#[test]
+	fn switches_once_per_round() {
+		let running_app = sequencer();
+		let mut app = block_on(running_app.lock());
+		// Nothing synced to switch to.
+		assert!(!app.switch_due(1));
+
+		app.running = RunStatus::Downloaded;
+		assert!(app.switch_due(1));
+		app.round = Some(1);
+		// The start block of the round seen again, after a reorg.
+		assert!(!app.switch_due(1));
+		// Caught up with as soon as the next round is seen started.
+		assert!(app.switch_due(2));
+	}
+
+	#[test]
+	fn switches_only_at_the_round_synced_for() {
+		let running_app = sequencer();
+		let mut app = block_on(running_app.lock());
+		app.synced_for = Some(2);
+		app.running = RunStatus::Downloaded;
+		// Synced before the round it is for started.
+		assert!(!app.switch_due(1));
+		assert!(app.switch_due(2));
+
+		// Still fetching at the start of its round, it finishes mid round and waits for the
+		// next one.
+		app.synced_for = Some(3);
+		app.running = RunStatus::Downloading;
+		assert!(!app.switch_due(3));
+		app.running = RunStatus::Downloaded;
+		assert!(!app.switch_due(3));
+		assert_eq!(app.synced_for, Some(4));
+		assert!(app.switch_due(4));
+	}
+
+	#[test]
+	fn download_for_another_group_is_cancelled() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+		let runtime = tokio::runtime::Runtime::new().unwrap();
+		let _guard = runtime.enter();
+		*fake.stalled.lock().unwrap() = true;
+
+		let group1 = DownloadInfo { group: 1, ..app_info() };
+		block_on(running_app.lock()).start_download(
+			&running_app,
+			&backends,
+			group1,
+			None,
+			None,
+			None,
+		);
+		let group2 = DownloadInfo { group: 2, app_id: 2, ..app_info() };
+		let mut app = block_on(running_app.lock());
+		// Still wanted, kept.
+		app.cancel_download(&backends, Some(&DownloadInfo { group: 1, ..app_info() }));
+		assert!(app.download.is_some());
+
+		app.cancel_download(&backends, Some(&group2));
+		assert!(app.download.is_none());
+		assert_eq!(app.running, RunStatus::Pending);
+		assert_eq!(*fake.discarded.lock().unwrap(), vec![1]);
+	}
+
+	#[test]
+	fn cancelled_download_does_not_take_over() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+		let progress = block_on(running_app.lock()).download_progress.clone();
+		// Replaced by another download while it ran.
+		block_on(running_app.lock()).download_progress = Default::default();
+
+		block_on(process_download_task(
+			backends,
+			DownloadInfo { group: 1, ..app_info() },
+			running_app.clone(),
+			progress,
+			None,
+			None,
+		))
+		.unwrap();
+
+		assert!(fake.started.lock().unwrap().is_empty());
+		let app = block_on(running_app.lock());
+		assert_eq!(app.group_id, 0xFFFFFFFF);
+		assert!(app.app_info.is_none());
+	}
+
+	#[test]
+	fn supervisor_restarts_crashed_instance_within_budget() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+		let apps = apps(vec![running_app.clone()]);
+		let (_, policy) = policies();
+
+		start(&backends, &running_app, None, StartType::SYNC);
+		*fake.exit_code.lock().unwrap() = Some(1);
+
+		// Noticed, then restarted once the backoff is over.
+		block_on(supervise(&apps, &policy, READY_TIMEOUT));
+		block_on(supervise(&apps, &policy, READY_TIMEOUT));
+		assert_eq!(
+			names(&fake.started.lock().unwrap()),
+			vec!["app-sequencer-1", "app-sequencer-1"]
+		);
+		assert_eq!(*fake.stopped.lock().unwrap(), vec!["app-sequencer-1"]);
+
+		// The second crash is over budget.
+		block_on(supervise(&apps, &policy, READY_TIMEOUT));
+		block_on(supervise(&apps, &policy, READY_TIMEOUT));
+		assert_eq!(fake.started.lock().unwrap().len(), 2);
+
+		let status = block_on(running_app.lock()).status();
+		assert_eq!(status.instances[0].exit_code, Some(1));
+		assert_eq!(status.instances[0].failures, 2);
+		assert_eq!(status.instances[0].restarts, 1);
+		assert!(status.instances[0].given_up);
+	}
+
+	#[test]
+	fn app_is_not_locked_while_restarting() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+		let apps = apps(vec![running_app.clone()]);
+		let (_, policy) = policies();
+		start(&backends, &running_app, None, StartType::SYNC);
+		*fake.exit_code.lock().unwrap() = Some(1);
+		block_on(supervise(&apps, &policy, READY_TIMEOUT));
+		*fake.stop_delay.lock().unwrap() = Duration::from_millis(500);
+
+		let (_, stopping) =
+			block_on(future::join(supervise(&apps, &policy, READY_TIMEOUT), async {
+				// Taken out of its slot and still stopping.
+				let app = running_app.lock().await;
+				(app.instance1.is_none(), app.synced(), fake.stopped.lock().unwrap().len())
+			}));
+
+		assert_eq!(stopping, (true, false, 0));
+		assert_eq!(*fake.stopped.lock().unwrap(), vec!["app-sequencer-1"]);
+		assert_eq!(fake.started.lock().unwrap().len(), 2);
+		let app = block_on(running_app.lock());
+		assert!(app.instance1.is_some());
+		assert!(app.restarting.is_empty());
+	}
+
+	#[test]
+	fn crashed_instance_is_not_restarted_in_a_taken_slot() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+		let (_, policy) = policies();
+		start(&backends, &running_app, None, StartType::SYNC);
+		*fake.exit_code.lock().unwrap() = Some(1);
+		let mut app = block_on(running_app.lock());
+		supervise_app(&mut app, &policy, READY_TIMEOUT);
+		let crashed = supervise_app(&mut app, &policy, READY_TIMEOUT).crashed;
+		assert_eq!(crashed.len(), 1);
+
+		// Stopped by the operator meanwhile.
+		assert!(app.take_instances().is_empty());
+		for (slot, instance) in crashed {
+			app.restart_instance(slot, Some(instance), &policy);
+		}
+		assert!(app.instance1.is_none());
+		assert_eq!(fake.started.lock().unwrap().len(), 1);
+	}
+
+	#[test]
+	fn run_takes_over_once_ready() {
+		let dir = tempfile::tempdir().unwrap();
+		let (fake, backends) = backends();
+		let running_app = sequencer_in(dir.path());
+		let apps = apps(vec![running_app.clone()]);
+		let (_, policy) = policies();
+		let run = |start_type| {
+			block_on(process_run_task(
+				backends.clone(),
+				probed_app_info(),
+				None,
+				None,
+				running_app.clone(),
+				start_type,
+			))
+			.unwrap()
+		};
+		let tick = |line: &str| {
+			fake.output.lock().unwrap().push_str(&format!("{}\n", line));
+			block_on(supervise(&apps, &policy, READY_TIMEOUT));
+		};
+
+		run(StartType::SYNC);
+		block_on(running_app.lock()).running = RunStatus::Downloaded;
+		// Still catching up.
+		tick("Syncing #3");
+		assert!(!block_on(running_app.lock()).switch_due(1));
+		tick("Imported #3");
+		assert!(block_on(running_app.lock()).switch_due(1));
+
+		block_on(running_app.lock()).round = Some(1);
+		run(StartType::RUN);
+		tick("Imported #4");
+		{
+			let app = block_on(running_app.lock());
+			let logs = app.instance1.as_ref().unwrap().logs(3).unwrap();
+			assert_eq!(
+				logs,
+				"[app-sequencer-1 sync] Syncing #3\n[app-sequencer-1 sync] Imported #3\n\
+				[app-sequencer-1 round 1] Imported #4"
+			);
+		}
+		// The next release syncs in the other slot, then runs there.
+		run(StartType::SYNC);
+		run(StartType::RUN);
+		tick("Syncing #5");
+		{
+			let mut app = block_on(running_app.lock());
+			assert!(app.handover);
+			// The instance of the previous round still serves it.
+			let status = app.status();
+			assert_eq!(status.instances.len(), 2);
+			assert!(status.instances[0].active);
+			assert!(!status.instances[1].ready);
+		}
+		assert_eq!(*fake.stopped.lock().unwrap(), vec!["app-sequencer-1", "app-sequencer-2"]);
+
+		tick("Imported #5");
+		let app = block_on(running_app.lock());
+		assert!(!app.handover);
+		assert_eq!(app.cur_ins, InstanceIndex::Instance1);
+		assert!(app.instance1.is_none());
+		assert_eq!(
+			*fake.stopped.lock().unwrap(),
+			vec!["app-sequencer-1", "app-sequencer-2", "app-sequencer-1"]
+		);
+	}
+
+	#[test]
+	fn unready_instance_takes_over_after_timeout() {
+		let (fake, backends) = backends();
+		let running_app = sequencer();
+		let apps = apps(vec![running_app.clone()]);
+		let (_, policy) = policies();
+		for start_type in [StartType::SYNC, StartType::RUN, StartType::SYNC, StartType::RUN] {
+			block_on(process_run_task(
+				backends.clone(),
+				probed_app_info(),
+				None,
+				None,
+				running_app.clone(),
+				start_type,
+			))
+			.unwrap();
+		}
+		block_on(supervise(&apps, &policy, READY_TIMEOUT));
+		assert!(block_on(running_app.lock()).handover);
+
+		block_on(supervise(&apps, &policy, Duration::ZERO));
+		let mut app = block_on(running_app.lock());
+		assert!(!app.handover);
+		let status = app.status();
+		assert_eq!(status.instances.len(), 1);
+		assert!(status.instances[0].ready);
+		assert_eq!(fake.stopped.lock().unwrap().len(), 3);
+	}
+}
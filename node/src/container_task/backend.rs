@@ -0,0 +1,921 @@
+//! Runtimes the container task starts app instances on.
+//!
+//! The state machine in `container_task` only deals with [`Instance`]s, which backend runs
+//! them is picked from the app info by [`Backends::for_app`].
+
+use super::{
+	download_sdk, download_urls,
+	logs::{self, LogCollector, LogFile, LogPolicy},
+	need_download,
+	readiness::Readiness,
+	supervisor::Supervision,
+	DownloadConfig, DownloadProgress,
+};
+use futures::{future::BoxFuture, FutureExt};
+use primitives_container::DownloadInfo;
+use serde::{Deserialize, Serialize};
+use std::{
+	error::Error,
+	fmt::Debug,
+	fs::{self, File, OpenOptions},
+	io::{self, ErrorKind, Read, Seek, SeekFrom},
+	os::unix::ffi::OsStrExt,
+	path::PathBuf,
+	process::{Child, Command, ExitStatus, Stdio},
+	sync::{mpsc, Arc},
+	thread,
+	time::{Duration, Instant},
+};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+pub type BackendResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// How an instance is started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Launch {
+	/// Name of the instance, unique per slot.
+	pub name: String,
+	/// Command line args.
+	pub args: Vec<String>,
+	/// Environment variables.
+	pub envs: Vec<(String, String)>,
+	/// Extra options of the runtime, e.g. `docker run` options.
+	pub options: Vec<String>,
+	/// Log file the output of the instance is collected into, rotated ones are kept next to it.
+	pub log_file: PathBuf,
+	/// Data directory of the instance, process instances run in it.
+	pub data_dir: PathBuf,
+	/// First of the ports of the instance.
+	#[serde(default)]
+	pub port_base: u16,
+}
+
+/// Backend specific handle of a started instance.
+#[derive(Debug)]
+pub enum InstanceHandle {
+	/// Native process.
+	Process(Child),
+	/// Native process started by a previous run of the node, by pid.
+	Adopted(u32),
+	/// Container, by name.
+	Container(String),
+}
+
+impl InstanceHandle {
+	pub fn id(&self) -> InstanceId {
+		match self {
+			InstanceHandle::Process(child) => InstanceId::Process(child.id()),
+			InstanceHandle::Adopted(pid) => InstanceId::Process(*pid),
+			InstanceHandle::Container(name) => InstanceId::Container(name.clone()),
+		}
+	}
+}
+
+/// Identifies an instance across restarts of the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InstanceId {
+	/// Process id.
+	Process(u32),
+	/// Container name.
+	Container(String),
+}
+
+/// State of an instance as reported by its backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceState {
+	/// Still running.
+	Running,
+	/// Exited, with its exit code if it has one.
+	Exited(Option<i32>),
+	/// The backend could not tell.
+	Unknown,
+}
+
+/// Started instance, tied to the backend it runs on.
+#[derive(Debug)]
+pub struct Instance {
+	pub backend: Arc<dyn Backend>,
+	pub handle: InstanceHandle,
+	/// App and launch it was started from, to start it again after a crash.
+	pub app: DownloadInfo,
+	pub launch: Launch,
+	pub supervision: Supervision,
+	pub readiness: Readiness,
+	pub log: LogCollector,
+}
+
+impl Instance {
+	/// Start an instance of `app` on `backend`, its output goes to the log file of `launch`.
+	pub fn start(
+		backend: Arc<dyn Backend>,
+		app: DownloadInfo,
+		launch: Launch,
+		policy: LogPolicy,
+	) -> BackendResult<Self> {
+		let mut handle = backend.start(&app, &launch)?;
+		let output = match backend.output(&handle, &launch, true) {
+			Ok(output) => output,
+			Err(err) => {
+				// Not left running unwatched.
+				let _ = backend.stop(&mut handle);
+				return Err(err);
+			},
+		};
+		let log = LogCollector::new(
+			output,
+			LogFile::new(launch.log_file.clone(), policy),
+			launch.name.clone(),
+		);
+		Ok(Instance {
+			backend,
+			handle,
+			readiness: Readiness::new(&app),
+			app,
+			launch,
+			supervision: Default::default(),
+			log,
+		})
+	}
+
+	/// Take over an instance a previous run of the node started, `None` when it is gone.
+	pub fn reattach(
+		backend: Arc<dyn Backend>,
+		app: DownloadInfo,
+		launch: Launch,
+		id: &InstanceId,
+		policy: LogPolicy,
+	) -> BackendResult<Option<Self>> {
+		let Some(handle) = backend.reattach(&app, &launch, id)? else { return Ok(None) };
+		let output = backend.output(&handle, &launch, false)?;
+		let log = LogCollector::new(
+			output,
+			LogFile::new(launch.log_file.clone(), policy),
+			launch.name.clone(),
+		);
+		Ok(Some(Instance {
+			backend,
+			handle,
+			readiness: Readiness::new(&app),
+			app,
+			launch,
+			supervision: Default::default(),
+			log,
+		}))
+	}
+
+	pub fn stop(&mut self) -> BackendResult<()> {
+		let result = self.backend.clone().stop(&mut self.handle);
+		// Whatever it output while shutting down.
+		self.collect();
+		result
+	}
+
+	/// Start the instance again with the same launch, once stopped so it released what the
+	/// exited instance still held, e.g. its container name.
+	pub fn restart(&mut self) -> BackendResult<()> {
+		self.handle = self.backend.start(&self.app, &self.launch)?;
+		self.readiness = Readiness::new(&self.app);
+		match self.backend.output(&self.handle, &self.launch, true) {
+			Ok(output) => self.log.restarted(output),
+			Err(err) => log::warn!("output of instance {:?} error:{:?}", self.handle, err),
+		}
+		Ok(())
+	}
+
+	pub fn state(&mut self) -> InstanceState {
+		self.backend.clone().status(&mut self.handle)
+	}
+
+	/// Move the new output of the instance into its log file.
+	pub fn collect(&mut self) {
+		if let Err(err) = self.log.collect() {
+			log::warn!("collect output of instance {:?} error:{:?}", self.handle, err);
+		}
+	}
+
+	/// Last `tail` lines of its log file, tagged and across starts.
+	pub fn logs(&self, tail: usize) -> BackendResult<String> {
+		Ok(logs::tail(&self.launch.log_file, tail)?)
+	}
+
+	/// Last `tail` lines of output since the instance was last started, as collected.
+	pub fn output(&self, tail: usize) -> impl Iterator<Item = &str> {
+		self.log.recent(tail)
+	}
+}
+
+/// Output of a started instance, read on every supervisor tick.
+pub trait Output: Debug + Send {
+	/// What the instance output since the last read.
+	fn read(&mut self) -> BackendResult<Vec<u8>>;
+}
+
+/// Runtime app instances are started on.
+pub trait Backend: Debug + Send + Sync {
+	/// Fetch what the app needs to start. Returns the source it was fetched from, `None` when
+	/// nothing had to be fetched.
+	fn prepare<'a>(
+		&'a self,
+		app: &'a DownloadInfo,
+		progress: &'a DownloadProgress,
+	) -> BoxFuture<'a, BackendResult<Option<Vec<u8>>>>;
+
+	/// Drop what `prepare` fetched, so the next `prepare` fetches it again.
+	fn purge(&self, app: &DownloadInfo) -> BackendResult<()>;
+
+	/// Drop what a cancelled `prepare` left behind.
+	fn discard(&self, app: &DownloadInfo) -> BackendResult<()>;
+
+	/// Start an instance of a prepared app.
+	fn start(&self, app: &DownloadInfo, launch: &Launch) -> BackendResult<InstanceHandle>;
+
+	/// Take over an instance started by a previous run of the node if it still runs, clean up
+	/// what is left of it otherwise.
+	fn reattach(
+		&self,
+		app: &DownloadInfo,
+		launch: &Launch,
+		id: &InstanceId,
+	) -> BackendResult<Option<InstanceHandle>>;
+
+	/// Stop an instance gracefully and release what it holds.
+	fn stop(&self, handle: &mut InstanceHandle) -> BackendResult<()>;
+
+	/// Current state of an instance.
+	fn status(&self, handle: &mut InstanceHandle) -> InstanceState;
+
+	/// Output of an instance, from its start when `from_start` and from now on otherwise.
+	fn output(
+		&self,
+		handle: &InstanceHandle,
+		launch: &Launch,
+		from_start: bool,
+	) -> BackendResult<Box<dyn Output>>;
+}
+
+/// Backends the container task picks from.
+#[derive(Debug, Clone)]
+pub struct Backends {
+	process: Arc<dyn Backend>,
+	image: Arc<dyn Backend>,
+}
+
+impl Backends {
+	pub fn new(process: Arc<dyn Backend>, image: Arc<dyn Backend>) -> Self {
+		Backends { process, image }
+	}
+
+	/// Apps shipped as a docker image run on the image backend, others as native processes.
+	pub fn for_app(&self, app: &DownloadInfo) -> Arc<dyn Backend> {
+		if app.is_docker_image {
+			self.image.clone()
+		} else {
+			self.process.clone()
+		}
+	}
+}
+
+/// Size after which the spool file of a process instance is emptied.
+const SPOOL_SIZE: u64 = 1 << 20;
+
+// File a process instance writes its output to, until it is collected into its log file.
+fn spool_path(launch: &Launch) -> PathBuf {
+	let mut path = launch.log_file.clone().into_os_string();
+	path.push(".out");
+	path.into()
+}
+
+/// Output of a process instance, read from its spool file.
+#[derive(Debug)]
+struct SpoolOutput {
+	file: File,
+	offset: u64,
+}
+
+impl Output for SpoolOutput {
+	fn read(&mut self) -> BackendResult<Vec<u8>> {
+		if self.file.metadata()?.len() < self.offset {
+			// Emptied by someone else.
+			self.offset = 0;
+		}
+		self.file.seek(SeekFrom::Start(self.offset))?;
+		let mut output = Vec::new();
+		self.file.read_to_end(&mut output)?;
+		self.offset += output.len() as u64;
+		if self.offset >= SPOOL_SIZE {
+			// The process appends, so it goes on at the start. Like with copytruncate, what it
+			// writes between the read and the truncate is lost.
+			self.file.set_len(0)?;
+			self.offset = 0;
+		}
+		Ok(output)
+	}
+}
+
+/// Output of a container, sent over by the threads following it.
+#[derive(Debug)]
+pub(super) struct ChannelOutput {
+	receiver: mpsc::Receiver<Vec<u8>>,
+	// Process following the output, killed once it is no longer read.
+	follower: Option<Child>,
+}
+
+impl ChannelOutput {
+	pub(super) fn new(receiver: mpsc::Receiver<Vec<u8>>, follower: Option<Child>) -> Self {
+		ChannelOutput { receiver, follower }
+	}
+}
+
+impl Output for ChannelOutput {
+	fn read(&mut self) -> BackendResult<Vec<u8>> {
+		Ok(self.receiver.try_iter().flatten().collect())
+	}
+}
+
+impl Drop for ChannelOutput {
+	fn drop(&mut self) {
+		if let Some(mut follower) = self.follower.take() {
+			let _ = follower.kill();
+			let _ = follower.wait();
+		}
+	}
+}
+
+// Send what `reader` reads over `sender` until either end is closed.
+fn forward(mut reader: impl Read + Send + 'static, sender: mpsc::Sender<Vec<u8>>) {
+	thread::spawn(move || {
+		let mut buf = [0u8; 8192];
+		loop {
+			match reader.read(&mut buf) {
+				Ok(0) | Err(_) => return,
+				Ok(read) =>
+					if sender.send(buf[..read].to_vec()).is_err() {
+						return;
+					},
+			}
+		}
+	});
+}
+
+// Ask a child to exit with SIGTERM, SIGKILL it if it is still running after `grace`.
+fn terminate(child: &mut Child, grace: Duration) -> BackendResult<ExitStatus> {
+	if let Some(status) = child.try_wait()? {
+		return Ok(status);
+	}
+	// SAFETY: `kill` has no memory effects, and the child is not reaped yet so its pid can not
+	// have been reused.
+	if unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) } != 0 {
+		return Err(io::Error::last_os_error().into());
+	}
+	let deadline = Instant::now() + grace;
+	while Instant::now() < deadline {
+		if let Some(status) = child.try_wait()? {
+			return Ok(status);
+		}
+		thread::sleep(Duration::from_millis(100));
+	}
+	log::warn!("instance {} still running {:?} after SIGTERM, killing it", child.id(), grace);
+	child.kill()?;
+	Ok(child.wait()?)
+}
+
+fn alive(pid: u32) -> bool {
+	// SAFETY: signal 0 only checks whether the process exists.
+	unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+// `terminate` for a process that is not a child of the node, so it can not be waited for.
+fn terminate_adopted(pid: u32, grace: Duration) -> BackendResult<()> {
+	// SAFETY: `kill` has no memory effects.
+	if !alive(pid) || unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+		return Ok(());
+	}
+	let deadline = Instant::now() + grace;
+	while Instant::now() < deadline {
+		if !alive(pid) {
+			return Ok(());
+		}
+		thread::sleep(Duration::from_millis(100));
+	}
+	log::warn!("instance {} still running {:?} after SIGTERM, killing it", pid, grace);
+	// SAFETY: as above.
+	unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) };
+	Ok(())
+}
+
+/// Runs the downloaded binary as a child process of the node.
+#[derive(Debug)]
+pub struct ProcessBackend {
+	data_path: PathBuf,
+	download: DownloadConfig,
+	stop_timeout: Duration,
+}
+
+impl ProcessBackend {
+	pub fn new(data_path: PathBuf, download: DownloadConfig, stop_timeout: Duration) -> Self {
+		ProcessBackend { data_path, download, stop_timeout }
+	}
+
+	fn binary_path(&self, app: &DownloadInfo) -> BackendResult<PathBuf> {
+		Ok(self.data_path.join("sdk").join(std::str::from_utf8(&app.file_name)?))
+	}
+
+	// The binary as started, made absolute since instances run in their data directory.
+	fn program(&self, app: &DownloadInfo) -> BackendResult<PathBuf> {
+		Ok(std::env::current_dir()?.join(self.binary_path(app)?))
+	}
+}
+
+impl Backend for ProcessBackend {
+	fn prepare<'a>(
+		&'a self,
+		app: &'a DownloadInfo,
+		progress: &'a DownloadProgress,
+	) -> BoxFuture<'a, BackendResult<Option<Vec<u8>>>> {
+		async move {
+			let download_path = self.binary_path(app)?;
+			let download_path = download_path.to_str().ok_or("invalid data_path")?;
+			if !need_download(download_path, app.app_hash).await? {
+				return Ok(None);
+			}
+			// Fail over to the next mirror until one serves a valid file.
+			for url in download_urls(app) {
+				let url = std::str::from_utf8(&url)?;
+				let result =
+					download_sdk(self.data_path.clone(), app.clone(), url, progress, self.download)
+						.await;
+				if result.is_ok() {
+					log::info!("download sdk from:{:?}", url);
+					return Ok(Some(url.as_bytes().to_vec()));
+				}
+				log::info!("download sdk from {:?} error:{:?}", url, result);
+			}
+			Err("download failed from all mirrors".into())
+		}
+		.boxed()
+	}
+
+	fn purge(&self, app: &DownloadInfo) -> BackendResult<()> {
+		match fs::remove_file(self.binary_path(app)?) {
+			Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+			_ => Ok(()),
+		}
+	}
+
+	fn discard(&self, app: &DownloadInfo) -> BackendResult<()> {
+		let mut part_path = self.binary_path(app)?.into_os_string();
+		part_path.push(".part");
+		match fs::remove_file(part_path) {
+			Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+			_ => Ok(()),
+		}
+	}
+
+	fn start(&self, app: &DownloadInfo, launch: &Launch) -> BackendResult<InstanceHandle> {
+		fs::create_dir_all(&launch.data_dir)?;
+		// Appended to, so the spool can be emptied while the process writes to it.
+		let outputs = OpenOptions::new().create(true).append(true).open(spool_path(launch))?;
+		outputs.set_len(0)?;
+		let errors = outputs.try_clone()?;
+		let child = Command::new(self.program(app)?)
+			.current_dir(&launch.data_dir)
+			.stdin(Stdio::piped())
+			.stderr(Stdio::from(outputs))
+			.stdout(Stdio::from(errors))
+			.args(&launch.args)
+			.envs(launch.envs.iter().cloned())
+			.spawn()?;
+		Ok(InstanceHandle::Process(child))
+	}
+
+	fn reattach(
+		&self,
+		app: &DownloadInfo,
+		_launch: &Launch,
+		id: &InstanceId,
+	) -> BackendResult<Option<InstanceHandle>> {
+		let InstanceId::Process(pid) = id else { return Err("not a process instance".into()) };
+		// The pid may have been reused since, only adopt it if it still runs the app binary.
+		let Ok(cmdline) = fs::read(format!("/proc/{}/cmdline", pid)) else { return Ok(None) };
+		let argv0 = cmdline.split(|byte| *byte == 0).next().unwrap_or_default();
+		// Instances started before they ran in their data directory have a relative argv0.
+		if argv0 != self.program(app)?.as_os_str().as_bytes() &&
+			argv0 != self.binary_path(app)?.as_os_str().as_bytes()
+		{
+			return Ok(None);
+		}
+		Ok(Some(InstanceHandle::Adopted(*pid)))
+	}
+
+	fn stop(&self, handle: &mut InstanceHandle) -> BackendResult<()> {
+		match handle {
+			InstanceHandle::Process(child) =>
+				log::info!("stop instance:{:?}", terminate(child, self.stop_timeout)?),
+			InstanceHandle::Adopted(pid) => terminate_adopted(*pid, self.stop_timeout)?,
+			InstanceHandle::Container(_) => return Err("not a process instance".into()),
+		}
+		Ok(())
+	}
+
+	fn status(&self, handle: &mut InstanceHandle) -> InstanceState {
+		match handle {
+			InstanceHandle::Process(child) => match child.try_wait() {
+				Ok(Some(status)) => InstanceState::Exited(status.code()),
+				Ok(None) => InstanceState::Running,
+				Err(_) => InstanceState::Unknown,
+			},
+			InstanceHandle::Adopted(pid) =>
+				if alive(*pid) {
+					InstanceState::Running
+				} else {
+					InstanceState::Exited(None)
+				},
+			InstanceHandle::Container(_) => InstanceState::Unknown,
+		}
+	}
+
+	fn output(
+		&self,
+		_handle: &InstanceHandle,
+		launch: &Launch,
+		from_start: bool,
+	) -> BackendResult<Box<dyn Output>> {
+		let file = OpenOptions::new()
+			.create(true)
+			.truncate(false)
+			.read(true)
+			.write(true)
+			.open(spool_path(launch))?;
+		// On reattach the spool may hold output collected before the node went down, only new
+		// output is read.
+		let offset = if from_start { 0 } else { file.metadata()?.len() };
+		Ok(Box::new(SpoolOutput { file, offset }))
+	}
+}
+
+/// Runs docker images through a docker compatible cli, `podman`.
+#[derive(Debug)]
+pub struct CliBackend {
+	program: &'static str,
+	stop_timeout: Duration,
+}
+
+impl CliBackend {
+	pub fn podman(stop_timeout: Duration) -> Self {
+		CliBackend { program: "podman", stop_timeout }
+	}
+
+	// The backend is called from the async supervise tick, `podman stop` may take its whole stop
+	// timeout.
+	fn run(&self, args: &[&str]) -> BackendResult<String> {
+		let output = blocking(|| Command::new(self.program).args(args).output())?;
+		if !output.status.success() {
+			return Err(format!(
+				"{} {:?} failed: {}",
+				self.program,
+				args,
+				String::from_utf8_lossy(&output.stderr).trim()
+			)
+			.into());
+		}
+		Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+	}
+
+	async fn image_exists(&self, image: &str) -> bool {
+		tokio::process::Command::new(self.program)
+			.args(["image", "inspect", image])
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.status()
+			.await
+			.is_ok_and(|status| status.success())
+	}
+}
+
+pub(super) fn docker_image(app: &DownloadInfo) -> BackendResult<&str> {
+	Ok(std::str::from_utf8(app.docker_image.as_ref().ok_or("docker image not exist")?)?)
+}
+
+/// Run a blocking call of a backend without stalling the async workers of the node.
+pub(super) fn blocking<T>(call: impl FnOnce() -> T) -> T {
+	match Handle::try_current() {
+		Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread =>
+			tokio::task::block_in_place(call),
+		_ => call(),
+	}
+}
+
+impl Backend for CliBackend {
+	fn prepare<'a>(
+		&'a self,
+		app: &'a DownloadInfo,
+		_progress: &'a DownloadProgress,
+	) -> BoxFuture<'a, BackendResult<Option<Vec<u8>>>> {
+		async move {
+			let image = docker_image(app)?;
+			if self.image_exists(image).await {
+				return Ok(None);
+			}
+			// Killed when the download is cancelled and this future dropped.
+			let output = tokio::process::Command::new(self.program)
+				.args(["pull", image])
+				.kill_on_drop(true)
+				.output()
+				.await?;
+			if !output.status.success() {
+				return Err(format!(
+					"{} pull {} failed: {}",
+					self.program,
+					image,
+					String::from_utf8_lossy(&output.stderr).trim()
+				)
+				.into());
+			}
+			Ok(Some(image.as_bytes().to_vec()))
+		}
+		.boxed()
+	}
+
+	fn purge(&self, app: &DownloadInfo) -> BackendResult<()> {
+		self.run(&["image", "rm", docker_image(app)?])?;
+		Ok(())
+	}
+
+	fn discard(&self, _app: &DownloadInfo) -> BackendResult<()> {
+		// A killed pull leaves no image, only layers the next pull reuses.
+		Ok(())
+	}
+
+	fn start(&self, app: &DownloadInfo, launch: &Launch) -> BackendResult<InstanceHandle> {
+		let mut args = vec!["run", "-itd", "--name", &launch.name];
+		args.extend(launch.options.iter().map(|option| option.as_str()));
+		// Each env is passed as its own argument so values may contain spaces.
+		let envs: Vec<String> =
+			launch.envs.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+		for env in &envs {
+			args.push("-e");
+			args.push(env);
+		}
+		args.push(docker_image(app)?);
+		args.extend(launch.args.iter().map(|arg| arg.as_str()));
+		log::info!("=======================args:{:?}", args);
+		self.run(&args)?;
+		Ok(InstanceHandle::Container(launch.name.clone()))
+	}
+
+	fn reattach(
+		&self,
+		_app: &DownloadInfo,
+		_launch: &Launch,
+		id: &InstanceId,
+	) -> BackendResult<Option<InstanceHandle>> {
+		let InstanceId::Container(name) = id else {
+			return Err("not a container instance".into());
+		};
+		match self.run(&["inspect", "-f", "{{.State.Running}}", name]) {
+			Ok(running) if running.trim() == "true" =>
+				Ok(Some(InstanceHandle::Container(name.clone()))),
+			Ok(_) => {
+				self.run(&["container", "rm", "-f", name])?;
+				Ok(None)
+			},
+			// No such container.
+			Err(_) => Ok(None),
+		}
+	}
+
+	fn stop(&self, handle: &mut InstanceHandle) -> BackendResult<()> {
+		let InstanceHandle::Container(name) = handle else {
+			return Err("not a container instance".into());
+		};
+		self.run(&["container", "stop", "-t", &self.stop_timeout.as_secs().to_string(), name])?;
+		self.run(&["container", "rm", name])?;
+		Ok(())
+	}
+
+	fn status(&self, handle: &mut InstanceHandle) -> InstanceState {
+		let InstanceHandle::Container(name) = handle else { return InstanceState::Unknown };
+		let Ok(state) =
+			self.run(&["inspect", "-f", "{{.State.Running}} {{.State.ExitCode}}", name])
+		else {
+			return InstanceState::Unknown;
+		};
+		match state.trim().split_once(' ') {
+			Some(("true", _)) => InstanceState::Running,
+			Some(("false", code)) => InstanceState::Exited(code.parse().ok()),
+			_ => InstanceState::Unknown,
+		}
+	}
+
+	fn output(
+		&self,
+		handle: &InstanceHandle,
+		_launch: &Launch,
+		from_start: bool,
+	) -> BackendResult<Box<dyn Output>> {
+		let InstanceHandle::Container(name) = handle else {
+			return Err("not a container instance".into());
+		};
+		let mut args = vec!["logs", "--follow"];
+		if !from_start {
+			args.extend(["--tail", "0"]);
+		}
+		args.push(name);
+		let mut follower = Command::new(self.program)
+			.args(args)
+			.stdin(Stdio::null())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()?;
+		// Started with a tty, so all output comes on stdout.
+		let (sender, receiver) = mpsc::channel();
+		if let Some(stdout) = follower.stdout.take() {
+			forward(stdout, sender.clone());
+		}
+		if let Some(stderr) = follower.stderr.take() {
+			forward(stderr, sender);
+		}
+		Ok(Box::new(ChannelOutput::new(receiver, Some(follower))))
+	}
+}
+
+/// Records what the container task asks for, without running anything.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct FakeBackend {
+	pub started: std::sync::Mutex<Vec<Launch>>,
+	pub stopped: std::sync::Mutex<Vec<String>>,
+	/// Exit code every instance reports once set.
+	pub exit_code: std::sync::Mutex<Option<i32>>,
+	/// Apps whose cancelled download was discarded.
+	pub discarded: std::sync::Mutex<Vec<u32>>,
+	/// Whether `prepare` hangs, like a download that takes long.
+	pub stalled: std::sync::Mutex<bool>,
+	/// How long a stop takes, like an instance using up its grace period.
+	pub stop_delay: std::sync::Mutex<Duration>,
+	/// Output of every instance, each one reads what is added after it started.
+	pub output: Arc<std::sync::Mutex<String>>,
+}
+
+/// Output of a fake instance.
+#[cfg(test)]
+#[derive(Debug)]
+struct FakeOutput {
+	output: Arc<std::sync::Mutex<String>>,
+	offset: usize,
+}
+
+#[cfg(test)]
+impl Output for FakeOutput {
+	fn read(&mut self) -> BackendResult<Vec<u8>> {
+		let output = self.output.lock().unwrap();
+		let new = output.as_bytes()[self.offset..].to_vec();
+		self.offset = output.len();
+		Ok(new)
+	}
+}
+
+#[cfg(test)]
+impl Backend for FakeBackend {
+	fn prepare<'a>(
+		&'a self,
+		_app: &'a DownloadInfo,
+		_progress: &'a DownloadProgress,
+	) -> BoxFuture<'a, BackendResult<Option<Vec<u8>>>> {
+		if *self.stalled.lock().unwrap() {
+			return futures::future::pending().boxed();
+		}
+		async { Ok(None) }.boxed()
+	}
+
+	fn purge(&self, _app: &DownloadInfo) -> BackendResult<()> {
+		Ok(())
+	}
+
+	fn discard(&self, app: &DownloadInfo) -> BackendResult<()> {
+		self.discarded.lock().unwrap().push(app.app_id);
+		Ok(())
+	}
+
+	fn start(&self, _app: &DownloadInfo, launch: &Launch) -> BackendResult<InstanceHandle> {
+		self.started.lock().unwrap().push(launch.clone());
+		Ok(InstanceHandle::Container(launch.name.clone()))
+	}
+
+	fn reattach(
+		&self,
+		_app: &DownloadInfo,
+		_launch: &Launch,
+		id: &InstanceId,
+	) -> BackendResult<Option<InstanceHandle>> {
+		Ok(match (id, *self.exit_code.lock().unwrap()) {
+			(InstanceId::Container(name), None) => Some(InstanceHandle::Container(name.clone())),
+			_ => None,
+		})
+	}
+
+	fn stop(&self, handle: &mut InstanceHandle) -> BackendResult<()> {
+		let delay = *self.stop_delay.lock().unwrap();
+		thread::sleep(delay);
+		if let InstanceHandle::Container(name) = handle {
+			self.stopped.lock().unwrap().push(name.clone());
+		}
+		Ok(())
+	}
+
+	fn status(&self, _handle: &mut InstanceHandle) -> InstanceState {
+		match *self.exit_code.lock().unwrap() {
+			Some(code) => InstanceState::Exited(Some(code)),
+			None => InstanceState::Running,
+		}
+	}
+
+	fn output(
+		&self,
+		_handle: &InstanceHandle,
+		_launch: &Launch,
+		_from_start: bool,
+	) -> BackendResult<Box<dyn Output>> {
+		let offset = self.output.lock().unwrap().len();
+		Ok(Box::new(FakeOutput { output: self.output.clone(), offset }))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{super::tests::launch_in, *};
+	use std::io::Write;
+
+	#[test]
+	fn terminate_sends_sigterm_first() {
+		let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+		let started = Instant::now();
+
+		let status = terminate(&mut child, Duration::from_secs(10)).unwrap();
+		assert!(started.elapsed() < Duration::from_secs(5));
+		assert_eq!(std::os::unix::process::ExitStatusExt::signal(&status), Some(libc::SIGTERM));
+	}
+
+	#[test]
+	fn terminate_kills_after_grace_period() {
+		let mut child = Command::new("sh")
+			.args(["-c", "trap '' TERM; while true; do sleep 1; done"])
+			.spawn()
+			.unwrap();
+		// Give the shell time to install the trap.
+		thread::sleep(Duration::from_millis(200));
+
+		let status = terminate(&mut child, Duration::from_millis(300)).unwrap();
+		assert_eq!(std::os::unix::process::ExitStatusExt::signal(&status), Some(libc::SIGKILL));
+	}
+
+	#[test]
+	fn spool_is_read_and_emptied() {
+		let dir = tempfile::tempdir().unwrap();
+		let launch = launch_in(dir.path());
+		let mut process =
+			OpenOptions::new().create(true).append(true).open(spool_path(&launch)).unwrap();
+		let backend = ProcessBackend::new(
+			dir.path().into(),
+			DownloadConfig { parallel: 1, rate_limit: None },
+			Duration::from_secs(1),
+		);
+		let mut output = backend.output(&InstanceHandle::Adopted(0), &launch, true).unwrap();
+
+		process.write_all(b"first\n").unwrap();
+		assert_eq!(output.read().unwrap(), b"first\n");
+		process.write_all(b"second\n").unwrap();
+		assert_eq!(output.read().unwrap(), b"second\n");
+
+		let large = vec![b'x'; SPOOL_SIZE as usize];
+		process.write_all(&large).unwrap();
+		assert_eq!(output.read().unwrap().len(), large.len());
+		assert_eq!(fs::metadata(spool_path(&launch)).unwrap().len(), 0);
+		// The process goes on at the start.
+		process.write_all(b"third\n").unwrap();
+		assert_eq!(output.read().unwrap(), b"third\n");
+	}
+
+	#[test]
+	fn discard_removes_partial_download_only() {
+		let dir = tempfile::tempdir().unwrap();
+		let backend = ProcessBackend::new(
+			dir.path().into(),
+			DownloadConfig { parallel: 1, rate_limit: None },
+			Duration::from_secs(1),
+		);
+		let sdk = dir.path().join("sdk");
+		fs::create_dir(&sdk).unwrap();
+		fs::write(sdk.join("app"), b"binary").unwrap();
+		fs::write(sdk.join("app.part"), b"bin").unwrap();
+
+		let app = super::super::tests::app_info();
+		backend.discard(&app).unwrap();
+		assert!(sdk.join("app").exists());
+		assert!(!sdk.join("app.part").exists());
+		// Nothing left to discard is fine.
+		backend.discard(&app).unwrap();
+	}
+}
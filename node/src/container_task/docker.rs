@@ -0,0 +1,1040 @@
+//! Minimal Docker Engine API client speaking HTTP over the daemon's Unix socket, and the
+//! backend running docker images through it.
+
+use super::{
+	backend::{
+		blocking, docker_image, Backend, BackendResult, ChannelOutput, InstanceHandle, InstanceId,
+		InstanceState, Launch, Output,
+	},
+	DownloadProgress,
+};
+use futures::{future::BoxFuture, FutureExt};
+use primitives_container::DownloadInfo;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::{
+	fmt,
+	io::{self, BufRead, BufReader, Read, Write},
+	ops::ControlFlow,
+	os::unix::net::UnixStream,
+	path::PathBuf,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc, Arc,
+	},
+	thread,
+	time::Duration,
+};
+
+const API_VERSION: &str = "v1.41";
+
+// Longest wait on the daemon for a request, or for the next line of a pull.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const PULL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Errors of the Docker Engine API.
+#[derive(Debug)]
+pub enum DockerError {
+	/// The socket could not be reached or the connection broke.
+	Io(io::Error),
+	/// The daemon answered with something that is not valid HTTP or JSON.
+	Protocol(String),
+	/// The image or container does not exist.
+	NotFound(String),
+	/// A container with the same name already exists.
+	Conflict(String),
+	/// Any other error status of the daemon.
+	Api { status: u16, message: String },
+	/// Pulling the image failed part way.
+	Pull(String),
+	/// The pull was given up before it finished.
+	Cancelled,
+	/// A `docker run` option the client does not support.
+	InvalidOption(String),
+}
+
+impl fmt::Display for DockerError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			DockerError::Io(err) => write!(f, "docker socket error: {}", err),
+			DockerError::Protocol(err) => write!(f, "invalid docker response: {}", err),
+			DockerError::NotFound(message) => write!(f, "not found: {}", message),
+			DockerError::Conflict(message) => write!(f, "conflict: {}", message),
+			DockerError::Api { status, message } =>
+				write!(f, "docker error {}: {}", status, message),
+			DockerError::Pull(message) => write!(f, "pull failed: {}", message),
+			DockerError::Cancelled => write!(f, "pull cancelled"),
+			DockerError::InvalidOption(option) =>
+				write!(f, "unsupported docker option: {}", option),
+		}
+	}
+}
+
+impl std::error::Error for DockerError {}
+
+impl From<io::Error> for DockerError {
+	fn from(err: io::Error) -> Self {
+		DockerError::Io(err)
+	}
+}
+
+pub type DockerResult<T> = Result<T, DockerError>;
+
+/// Progress line of an image pull.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct PullProgress {
+	pub id: Option<String>,
+	pub status: Option<String>,
+	pub progress: Option<String>,
+	pub error: Option<String>,
+}
+
+/// State of a container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerState {
+	pub running: bool,
+	pub exit_code: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerInspect {
+	state: ContainerState,
+}
+
+#[derive(Deserialize)]
+struct ErrorMessage {
+	message: String,
+}
+
+// Decodes a `Transfer-Encoding: chunked` body.
+struct ChunkedReader<R> {
+	inner: R,
+	remaining: usize,
+	done: bool,
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.done || buf.is_empty() {
+			return Ok(0);
+		}
+		if self.remaining == 0 {
+			let mut line = String::new();
+			self.inner.read_line(&mut line)?;
+			let size = line.trim().split(';').next().unwrap_or_default();
+			self.remaining = usize::from_str_radix(size, 16)
+				.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+			if self.remaining == 0 {
+				self.done = true;
+				return Ok(0);
+			}
+		}
+		let len = buf.len().min(self.remaining);
+		let count = self.inner.read(&mut buf[..len])?;
+		if count == 0 {
+			return Err(io::ErrorKind::UnexpectedEof.into());
+		}
+		self.remaining -= count;
+		if self.remaining == 0 {
+			let mut crlf = [0u8; 2];
+			self.inner.read_exact(&mut crlf)?;
+		}
+		Ok(count)
+	}
+}
+
+struct Response {
+	status: u16,
+	body: Box<dyn BufRead + Send>,
+}
+
+impl Response {
+	fn bytes(mut self) -> DockerResult<Vec<u8>> {
+		let mut body = Vec::new();
+		self.body.read_to_end(&mut body)?;
+		Ok(body)
+	}
+
+	// Map error statuses to errors, carrying the daemon's message.
+	fn check(self) -> DockerResult<Self> {
+		if self.status < 400 {
+			return Ok(self);
+		}
+		let status = self.status;
+		let body = self.bytes()?;
+		let message = serde_json::from_slice::<ErrorMessage>(&body)
+			.map(|error| error.message)
+			.unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned());
+		Err(match status {
+			404 => DockerError::NotFound(message),
+			409 => DockerError::Conflict(message),
+			status => DockerError::Api { status, message },
+		})
+	}
+
+	fn json<T: for<'de> Deserialize<'de>>(self) -> DockerResult<T> {
+		let body = self.check()?.bytes()?;
+		serde_json::from_slice(&body).map_err(|err| DockerError::Protocol(err.to_string()))
+	}
+}
+
+// Percent-encode a query parameter or path segment, image references carry `/`, `:` and `@`.
+fn encode(value: &str) -> String {
+	value
+		.bytes()
+		.map(|byte| match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' =>
+				(byte as char).to_string(),
+			byte => format!("%{:02X}", byte),
+		})
+		.collect()
+}
+
+/// Client of the Docker Engine API. Its calls block on the socket.
+#[derive(Debug, Clone)]
+pub struct DockerClient {
+	socket: PathBuf,
+	// Read and write timeout of a request.
+	timeout: Duration,
+}
+
+impl DockerClient {
+	pub fn new(socket: impl Into<PathBuf>) -> Self {
+		DockerClient { socket: socket.into(), timeout: REQUEST_TIMEOUT }
+	}
+
+	// Reads time out after `read_timeout`, or never with `None`.
+	fn send(
+		&self,
+		method: &str,
+		path: &str,
+		body: Option<&Value>,
+		read_timeout: Option<Duration>,
+	) -> DockerResult<Response> {
+		let mut stream = UnixStream::connect(&self.socket)?;
+		stream.set_write_timeout(Some(self.timeout))?;
+		stream.set_read_timeout(read_timeout)?;
+		let body = body.map(|body| body.to_string()).unwrap_or_default();
+		write!(
+			stream,
+			"{} /{}{} HTTP/1.1\r\nHost: docker\r\nConnection: close\r\n\
+			Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+			method,
+			API_VERSION,
+			path,
+			body.len(),
+			body
+		)?;
+
+		let mut reader = BufReader::new(stream);
+		let mut line = String::new();
+		reader.read_line(&mut line)?;
+		let status = line
+			.split(' ')
+			.nth(1)
+			.and_then(|status| status.parse().ok())
+			.ok_or_else(|| DockerError::Protocol(format!("invalid status line {:?}", line)))?;
+
+		let mut chunked = false;
+		let mut length = None;
+		loop {
+			line.clear();
+			if reader.read_line(&mut line)? == 0 {
+				return Err(DockerError::Protocol("unexpected end of headers".into()));
+			}
+			let header = line.trim_end();
+			if header.is_empty() {
+				break;
+			}
+			if let Some((name, value)) = header.split_once(':') {
+				let value = value.trim();
+				match name.to_ascii_lowercase().as_str() {
+					"transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+					"content-length" => length = value.parse::<u64>().ok(),
+					_ => {},
+				}
+			}
+		}
+
+		let body: Box<dyn BufRead + Send> = if chunked {
+			Box::new(BufReader::new(ChunkedReader { inner: reader, remaining: 0, done: false }))
+		} else if let Some(length) = length {
+			Box::new(reader.take(length))
+		} else {
+			Box::new(reader)
+		};
+		Ok(Response { status, body })
+	}
+
+	/// Whether the exact image exists locally.
+	pub fn image_exists(&self, image: &str) -> DockerResult<bool> {
+		match self
+			.send("GET", &format!("/images/{}/json", encode(image)), None, Some(self.timeout))?
+			.check()
+		{
+			Ok(_) => Ok(true),
+			Err(DockerError::NotFound(_)) => Ok(false),
+			Err(err) => Err(err),
+		}
+	}
+
+	/// Pull an image, reporting each progress line. Breaking out of `on_progress` gives up the
+	/// pull, the daemon stops it once the connection is closed.
+	pub fn pull_image(
+		&self,
+		image: &str,
+		mut on_progress: impl FnMut(&PullProgress) -> ControlFlow<()>,
+	) -> DockerResult<()> {
+		let path = format!("/images/create?fromImage={}", encode(image));
+		let response = self.send("POST", &path, None, Some(PULL_TIMEOUT.max(self.timeout)))?;
+		// A failing pull still answers 200, the error comes as a progress line.
+		for line in response.check()?.body.lines() {
+			let line = line?;
+			if line.trim().is_empty() {
+				continue;
+			}
+			let progress: PullProgress = serde_json::from_str(&line)
+				.map_err(|err| DockerError::Protocol(err.to_string()))?;
+			if let Some(error) = progress.error {
+				return Err(DockerError::Pull(error));
+			}
+			if on_progress(&progress).is_break() {
+				return Err(DockerError::Cancelled);
+			}
+		}
+		Ok(())
+	}
+
+	pub fn remove_image(&self, image: &str) -> DockerResult<()> {
+		self.send("DELETE", &format!("/images/{}", encode(image)), None, Some(self.timeout))?
+			.check()?;
+		Ok(())
+	}
+
+	/// Create a container, returns its id.
+	pub fn create_container(&self, name: &str, config: &Value) -> DockerResult<String> {
+		#[derive(Deserialize)]
+		struct Created {
+			#[serde(rename = "Id")]
+			id: String,
+		}
+		let path = format!("/containers/create?name={}", encode(name));
+		Ok(self
+			.send("POST", &path, Some(config), Some(self.timeout))?
+			.json::<Created>()?
+			.id)
+	}
+
+	pub fn start_container(&self, name: &str) -> DockerResult<()> {
+		let path = format!("/containers/{}/start", encode(name));
+		self.send("POST", &path, None, Some(self.timeout))?.check()?;
+		Ok(())
+	}
+
+	/// Stop a container, killing it after `timeout` seconds.
+	pub fn stop_container(&self, name: &str, timeout: u64) -> DockerResult<()> {
+		let path = format!("/containers/{}/stop?t={}", encode(name), timeout);
+		// The daemon answers once the container is gone.
+		let read_timeout = self.timeout + Duration::from_secs(timeout);
+		self.send("POST", &path, None, Some(read_timeout))?.check()?;
+		Ok(())
+	}
+
+	pub fn remove_container(&self, name: &str, force: bool) -> DockerResult<()> {
+		let path = format!("/containers/{}?force={}", encode(name), force);
+		self.send("DELETE", &path, None, Some(self.timeout))?.check()?;
+		Ok(())
+	}
+
+	pub fn inspect_container(&self, name: &str) -> DockerResult<ContainerState> {
+		let path = format!("/containers/{}/json", encode(name));
+		Ok(self
+			.send("GET", &path, None, Some(self.timeout))?
+			.json::<ContainerInspect>()?
+			.state)
+	}
+
+	/// Stream the output of a container frame by frame until `on_frame` returns `false` or the
+	/// stream ends, starting `tail` lines from the end or with all of it. With `follow` it lasts
+	/// as long as the container runs.
+	pub fn stream_logs(
+		&self,
+		name: &str,
+		tail: Option<usize>,
+		follow: bool,
+		mut on_frame: impl FnMut(&[u8]) -> bool,
+	) -> DockerResult<()> {
+		let tail = tail.map_or_else(|| "all".to_string(), |tail| tail.to_string());
+		let path = format!(
+			"/containers/{}/logs?stdout=true&stderr=true&tail={}&follow={}",
+			encode(name),
+			tail,
+			follow
+		);
+		// Followed output may stay quiet for as long as the container runs.
+		let read_timeout = if follow { None } else { Some(self.timeout) };
+		let mut body = self.send("GET", &path, None, read_timeout)?.check()?.body;
+		// Without a tty stdout and stderr are multiplexed, each frame has an 8 byte header
+		// ending with the big endian frame size.
+		let mut header = [0u8; 8];
+		loop {
+			match body.read_exact(&mut header) {
+				Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+				result => result?,
+			}
+			let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+			let mut frame = vec![0u8; size as usize];
+			body.read_exact(&mut frame)?;
+			if !on_frame(&frame) {
+				return Ok(());
+			}
+		}
+	}
+}
+
+// Whether a `docker run` option the client maps takes a value, `None` for any other option.
+fn takes_value(flag: &str) -> Option<bool> {
+	match flag {
+		"--network" | "--net" | "-v" | "--volume" | "-p" | "--publish" | "-e" | "--env" |
+		"--cap-add" | "--cap-drop" | "--ulimit" | "-m" | "--memory" | "--cpus" | "-w" |
+		"--workdir" | "--entrypoint" | "-u" | "--user" | "--add-host" | "--shm-size" => Some(true),
+		"--privileged" | "--init" | "--read-only" | "--rm" | "-it" | "-itd" | "-d" | "-i" |
+		"-t" => Some(false),
+		_ => None,
+	}
+}
+
+// Flag and inline value of an option, only long options take one after `=`.
+fn split_option(option: &str) -> (&str, Option<&str>) {
+	match option.split_once('=') {
+		Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+		_ => (option, None),
+	}
+}
+
+/// Check `options` only use the `docker run` options the client maps to the Engine API, each
+/// with its value. The values themselves are checked once the container is created.
+pub fn check_options(options: &[String]) -> DockerResult<()> {
+	let mut options = options.iter();
+	while let Some(option) = options.next() {
+		let (flag, inline) = split_option(option);
+		match takes_value(flag) {
+			Some(true) if inline.is_none() && options.next().is_none() =>
+				return Err(DockerError::InvalidOption(format!("{} needs a value", flag))),
+			Some(_) => {},
+			None => return Err(DockerError::InvalidOption(flag.to_string())),
+		}
+	}
+	Ok(())
+}
+
+// Bytes of a size like `512m`, `2g` or `1024`.
+fn parse_bytes(value: &str) -> Option<i64> {
+	let lower = value.to_ascii_lowercase();
+	let (number, unit) = match lower.trim_end_matches('b') {
+		number if number.ends_with('k') => (&number[..number.len() - 1], 1 << 10),
+		number if number.ends_with('m') => (&number[..number.len() - 1], 1 << 20),
+		number if number.ends_with('g') => (&number[..number.len() - 1], 1 << 30),
+		number => (number, 1),
+	};
+	number.parse::<i64>().ok()?.checked_mul(unit)
+}
+
+// A ulimit like `nofile=1024:2048`, the hard limit defaults to the soft one.
+fn parse_ulimit(value: &str) -> Option<Value> {
+	let (name, limits) = value.split_once('=')?;
+	let (soft, hard) = limits.split_once(':').unwrap_or((limits, limits));
+	Some(json!({
+		"Name": name,
+		"Soft": soft.parse::<i64>().ok()?,
+		"Hard": hard.parse::<i64>().ok()?,
+	}))
+}
+
+// Translate the `docker run` options operators keep in `option_args` into the create body.
+fn container_config(image: &str, launch: &Launch) -> DockerResult<Value> {
+	let mut config = Map::new();
+	let mut host_config = Map::new();
+	let mut exposed_ports = Map::new();
+	let mut port_bindings = Map::new();
+	let mut binds = Vec::new();
+	let mut cap_add = Vec::new();
+	let mut cap_drop = Vec::new();
+	let mut ulimits = Vec::new();
+	let mut extra_hosts = Vec::new();
+	let mut envs: Vec<String> =
+		launch.envs.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+
+	let mut options = launch.options.iter();
+	while let Some(option) = options.next() {
+		let (flag, inline) = split_option(option);
+		let mut value = || {
+			inline
+				.map(str::to_string)
+				.or_else(|| options.next().cloned())
+				.ok_or_else(|| DockerError::InvalidOption(format!("{} needs a value", flag)))
+		};
+		let invalid = |value: &str| DockerError::InvalidOption(format!("{} {}", flag, value));
+		match flag {
+			"--network" | "--net" => {
+				host_config.insert("NetworkMode".into(), json!(value()?));
+			},
+			"-v" | "--volume" => binds.push(value()?),
+			"-p" | "--publish" => {
+				let publish = value()?;
+				let (host, container) = publish.rsplit_once(':').ok_or_else(|| {
+					DockerError::InvalidOption(format!(
+						"--publish {} needs host:container",
+						publish
+					))
+				})?;
+				let port = if container.contains('/') {
+					container.to_string()
+				} else {
+					format!("{}/tcp", container)
+				};
+				exposed_ports.insert(port.clone(), json!({}));
+				port_bindings.insert(port, json!([{ "HostPort": host }]));
+			},
+			"-e" | "--env" => {
+				let env = value()?;
+				// Like `docker run`, a bare name passes on the variable of the node.
+				if env.contains('=') {
+					envs.push(env);
+				} else if let Ok(var) = std::env::var(&env) {
+					envs.push(format!("{}={}", env, var));
+				}
+			},
+			"--cap-add" => cap_add.push(value()?),
+			"--cap-drop" => cap_drop.push(value()?),
+			"--ulimit" => {
+				let ulimit = value()?;
+				ulimits.push(parse_ulimit(&ulimit).ok_or_else(|| invalid(&ulimit))?);
+			},
+			"-m" | "--memory" => {
+				let memory = value()?;
+				let bytes = parse_bytes(&memory).ok_or_else(|| invalid(&memory))?;
+				host_config.insert("Memory".into(), json!(bytes));
+			},
+			"--shm-size" => {
+				let size = value()?;
+				let bytes = parse_bytes(&size).ok_or_else(|| invalid(&size))?;
+				host_config.insert("ShmSize".into(), json!(bytes));
+			},
+			"--cpus" => {
+				let cpus = value()?;
+				let nano_cpus = cpus
+					.parse::<f64>()
+					.ok()
+					.filter(|cpus| cpus.is_finite() && *cpus > 0.0)
+					.ok_or_else(|| invalid(&cpus))?;
+				host_config.insert("NanoCpus".into(), json!((nano_cpus * 1e9) as i64));
+			},
+			"--add-host" => extra_hosts.push(value()?),
+			"-w" | "--workdir" => {
+				config.insert("WorkingDir".into(), json!(value()?));
+			},
+			"--entrypoint" => {
+				config.insert("Entrypoint".into(), json!([value()?]));
+			},
+			"-u" | "--user" => {
+				config.insert("User".into(), json!(value()?));
+			},
+			"--privileged" => {
+				host_config.insert("Privileged".into(), json!(true));
+			},
+			"--init" => {
+				host_config.insert("Init".into(), json!(true));
+			},
+			"--read-only" => {
+				host_config.insert("ReadonlyRootfs".into(), json!(true));
+			},
+			// The node removes the containers it stops itself.
+			"--rm" | "-it" | "-itd" | "-d" | "-i" | "-t" => {},
+			option => return Err(DockerError::InvalidOption(option.to_string())),
+		}
+	}
+	for (key, values) in
+		[("Binds", binds), ("CapAdd", cap_add), ("CapDrop", cap_drop), ("ExtraHosts", extra_hosts)]
+	{
+		if !values.is_empty() {
+			host_config.insert(key.into(), json!(values));
+		}
+	}
+	if !ulimits.is_empty() {
+		host_config.insert("Ulimits".into(), Value::Array(ulimits));
+	}
+	if !port_bindings.is_empty() {
+		host_config.insert("PortBindings".into(), Value::Object(port_bindings));
+	}
+
+	config.insert("Image".into(), json!(image));
+	config.insert("Cmd".into(), json!(launch.args));
+	config.insert("Env".into(), json!(envs));
+	config.insert("Tty".into(), json!(false));
+	config.insert("ExposedPorts".into(), Value::Object(exposed_ports));
+	config.insert("HostConfig".into(), Value::Object(host_config));
+	Ok(Value::Object(config))
+}
+
+// Tells a pull running on a blocking thread to give up once the download future is dropped.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+	fn drop(&mut self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+}
+
+/// Runs docker images through the Docker Engine API.
+#[derive(Debug)]
+pub struct DockerBackend {
+	client: DockerClient,
+	stop_timeout: Duration,
+}
+
+impl DockerBackend {
+	pub fn new(socket: impl Into<PathBuf>, stop_timeout: Duration) -> Self {
+		DockerBackend { client: DockerClient::new(socket), stop_timeout }
+	}
+}
+
+impl Backend for DockerBackend {
+	fn prepare<'a>(
+		&'a self,
+		app: &'a DownloadInfo,
+		download: &'a DownloadProgress,
+	) -> BoxFuture<'a, BackendResult<Option<Vec<u8>>>> {
+		async move {
+			let image = docker_image(app)?.to_string();
+			let client = self.client.clone();
+			let dropped = Arc::new(AtomicBool::new(false));
+			let _cancel = CancelOnDrop(dropped.clone());
+			let pulled = tokio::task::spawn_blocking({
+				let image = image.clone();
+				move || -> DockerResult<bool> {
+					if client.image_exists(&image)? {
+						return Ok(false);
+					}
+					// The pull blocks, so it watches for a cancel itself.
+					client.pull_image(&image, |progress| {
+						log::info!(
+							"pull {}: {} {}",
+							progress.id.as_deref().unwrap_or(&image),
+							progress.status.as_deref().unwrap_or_default(),
+							progress.progress.as_deref().unwrap_or_default()
+						);
+						if dropped.load(Ordering::Relaxed) {
+							ControlFlow::Break(())
+						} else {
+							ControlFlow::Continue(())
+						}
+					})?;
+					Ok(true)
+				}
+			})
+			.await??;
+			if download.cancelled() {
+				return Err(DockerError::Cancelled.into());
+			}
+			Ok(pulled.then(|| image.into_bytes()))
+		}
+		.boxed()
+	}
+
+	fn purge(&self, app: &DownloadInfo) -> BackendResult<()> {
+		let image = docker_image(app)?;
+		Ok(blocking(|| self.client.remove_image(image))?)
+	}
+
+	fn discard(&self, _app: &DownloadInfo) -> BackendResult<()> {
+		// A given up pull leaves no image, only layers the next pull reuses.
+		Ok(())
+	}
+
+	fn start(&self, app: &DownloadInfo, launch: &Launch) -> BackendResult<InstanceHandle> {
+		let config = container_config(docker_image(app)?, launch)?;
+		log::info!("create container {}:{}", launch.name, config);
+		blocking(|| {
+			match self.client.create_container(&launch.name, &config) {
+				// Left over from a node that did not shut down cleanly.
+				Err(DockerError::Conflict(_)) => {
+					self.client.remove_container(&launch.name, true)?;
+					self.client.create_container(&launch.name, &config)?;
+				},
+				result => {
+					result?;
+				},
+			}
+			self.client.start_container(&launch.name)
+		})?;
+		Ok(InstanceHandle::Container(launch.name.clone()))
+	}
+
+	fn reattach(
+		&self,
+		_app: &DownloadInfo,
+		_launch: &Launch,
+		id: &InstanceId,
+	) -> BackendResult<Option<InstanceHandle>> {
+		let InstanceId::Container(name) = id else {
+			return Err("not a container instance".into());
+		};
+		blocking(|| match self.client.inspect_container(name) {
+			Ok(ContainerState { running: true, .. }) =>
+				Ok(Some(InstanceHandle::Container(name.clone()))),
+			Ok(_) => {
+				self.client.remove_container(name, true)?;
+				Ok(None)
+			},
+			Err(DockerError::NotFound(_)) => Ok(None),
+			Err(err) => Err(err.into()),
+		})
+	}
+
+	fn stop(&self, handle: &mut InstanceHandle) -> BackendResult<()> {
+		let InstanceHandle::Container(name) = handle else {
+			return Err("not a container instance".into());
+		};
+		// The daemon sends SIGTERM and kills the container after the timeout.
+		blocking(|| {
+			self.client.stop_container(name, self.stop_timeout.as_secs())?;
+			self.client.remove_container(name, true)
+		})?;
+		Ok(())
+	}
+
+	fn status(&self, handle: &mut InstanceHandle) -> InstanceState {
+		let InstanceHandle::Container(name) = handle else { return InstanceState::Unknown };
+		match blocking(|| self.client.inspect_container(name)) {
+			Ok(ContainerState { running: true, .. }) => InstanceState::Running,
+			Ok(ContainerState { exit_code, .. }) => InstanceState::Exited(Some(exit_code)),
+			Err(_) => InstanceState::Unknown,
+		}
+	}
+
+	fn output(
+		&self,
+		handle: &InstanceHandle,
+		_launch: &Launch,
+		from_start: bool,
+	) -> BackendResult<Box<dyn Output>> {
+		let InstanceHandle::Container(name) = handle else {
+			return Err("not a container instance".into());
+		};
+		let (sender, receiver) = mpsc::channel();
+		let client = self.client.clone();
+		let name = name.clone();
+		// Ends with the container, or with the next frame once the output is no longer read.
+		thread::spawn(move || {
+			let tail = if from_start { None } else { Some(0) };
+			let result =
+				client.stream_logs(&name, tail, true, |frame| sender.send(frame.to_vec()).is_ok());
+			if let Err(err) = result {
+				log::debug!("follow logs of {} error:{:?}", name, err);
+			}
+		});
+		Ok(Box::new(ChannelOutput::new(receiver, None)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{super::tests::launch_in, *};
+	use std::{os::unix::net::UnixListener, path::Path, thread};
+
+	// Serve one canned response per request and hand back the request lines.
+	fn serve(
+		responses: Vec<String>,
+	) -> (tempfile::TempDir, DockerClient, thread::JoinHandle<Vec<String>>) {
+		let dir = tempfile::tempdir().unwrap();
+		let socket = dir.path().join("docker.sock");
+		let listener = UnixListener::bind(&socket).unwrap();
+		let server = thread::spawn(move || {
+			let mut requests = Vec::new();
+			for response in responses {
+				let (stream, _) = listener.accept().unwrap();
+				let mut reader = BufReader::new(stream);
+				let mut line = String::new();
+				reader.read_line(&mut line).unwrap();
+				requests.push(line.trim_end().to_string());
+				let mut length = 0;
+				loop {
+					let mut header = String::new();
+					reader.read_line(&mut header).unwrap();
+					if header.trim_end().is_empty() {
+						break;
+					}
+					if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:")
+					{
+						length = value.trim().parse().unwrap();
+					}
+				}
+				let mut body = vec![0u8; length];
+				reader.read_exact(&mut body).unwrap();
+				reader.into_inner().write_all(response.as_bytes()).unwrap();
+			}
+			requests
+		});
+		(dir, DockerClient::new(socket), server)
+	}
+
+	fn response(status: &str, body: &str) -> String {
+		format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n{}", status, body.len(), body)
+	}
+
+	fn chunked(status: &str, chunks: &[&[u8]]) -> String {
+		let mut response = format!("HTTP/1.1 {}\r\nTransfer-Encoding: chunked\r\n\r\n", status);
+		for chunk in chunks {
+			response.push_str(&format!(
+				"{:x}\r\n{}\r\n",
+				chunk.len(),
+				String::from_utf8_lossy(chunk)
+			));
+		}
+		response.push_str("0\r\n\r\n");
+		response
+	}
+
+	#[test]
+	fn image_exists_is_exact() {
+		let (_dir, client, server) = serve(vec![
+			response("404 Not Found", r#"{"message":"No such image: app:2"}"#),
+			response("200 OK", r#"{"Id":"sha256:1"}"#),
+		]);
+
+		assert!(!client.image_exists("app:2").unwrap());
+		assert!(client.image_exists("app:1").unwrap());
+		assert_eq!(
+			server.join().unwrap(),
+			vec![
+				"GET /v1.41/images/app%3A2/json HTTP/1.1",
+				"GET /v1.41/images/app%3A1/json HTTP/1.1"
+			]
+		);
+	}
+
+	#[test]
+	fn image_references_are_encoded_in_paths() {
+		let image = "registry:5000/repo@sha256:0123abcd";
+		let (_dir, client, server) = serve(vec![
+			response("200 OK", r#"{"Id":"sha256:0123abcd"}"#),
+			response("200 OK", "[]"),
+		]);
+
+		assert!(client.image_exists(image).unwrap());
+		client.remove_image(image).unwrap();
+		assert_eq!(
+			server.join().unwrap(),
+			vec![
+				"GET /v1.41/images/registry%3A5000%2Frepo%40sha256%3A0123abcd/json HTTP/1.1",
+				"DELETE /v1.41/images/registry%3A5000%2Frepo%40sha256%3A0123abcd HTTP/1.1"
+			]
+		);
+	}
+
+	#[test]
+	fn pull_reports_progress_and_fails_on_error_line() {
+		let (_dir, client, _server) = serve(vec![
+			chunked(
+				"200 OK",
+				&[
+					b"{\"status\":\"Pulling from library/app\",\"id\":\"1\"}\r\n",
+					b"{\"status\":\"Downloading\",\"progress\":\"[==>  ]\",\"id\":\"2\"}\r\n",
+				],
+			),
+			chunked("200 OK", &[b"{\"error\":\"manifest unknown\"}\r\n"]),
+			chunked("200 OK", &[b"{\"status\":\"Pulling from library/app\",\"id\":\"3\"}\r\n"]),
+		]);
+
+		let mut statuses = Vec::new();
+		client
+			.pull_image("library/app:1", |progress| {
+				statuses.push(progress.status.clone().unwrap());
+				ControlFlow::Continue(())
+			})
+			.unwrap();
+		assert_eq!(statuses, vec!["Pulling from library/app", "Downloading"]);
+
+		match client.pull_image("library/app:2", |_| ControlFlow::Continue(())) {
+			Err(DockerError::Pull(error)) => assert_eq!(error, "manifest unknown"),
+			result => panic!("unexpected {:?}", result),
+		}
+
+		match client.pull_image("library/app:3", |_| ControlFlow::Break(())) {
+			Err(DockerError::Cancelled) => {},
+			result => panic!("unexpected {:?}", result),
+		}
+	}
+
+	#[test]
+	fn errors_carry_daemon_message() {
+		let (_dir, client, _server) = serve(vec![
+			response("409 Conflict", r#"{"message":"name in use"}"#),
+			response("500 Internal Server Error", r#"{"message":"boom"}"#),
+		]);
+
+		match client.create_container("app-1", &json!({})) {
+			Err(DockerError::Conflict(message)) => assert_eq!(message, "name in use"),
+			result => panic!("unexpected {:?}", result),
+		}
+		match client.start_container("app-1") {
+			Err(DockerError::Api { status: 500, message }) => assert_eq!(message, "boom"),
+			result => panic!("unexpected {:?}", result),
+		}
+	}
+
+	#[test]
+	fn inspect_reads_state() {
+		let (_dir, client, _server) = serve(vec![response(
+			"200 OK",
+			r#"{"Id":"1","State":{"Status":"exited","Running":false,"ExitCode":3}}"#,
+		)]);
+
+		assert_eq!(
+			client.inspect_container("app-1").unwrap(),
+			ContainerState { running: false, exit_code: 3 }
+		);
+	}
+
+	#[test]
+	fn silent_daemon_times_out() {
+		let dir = tempfile::tempdir().unwrap();
+		let socket = dir.path().join("docker.sock");
+		let listener = UnixListener::bind(&socket).unwrap();
+		// Accepts the request and never answers.
+		let _server = thread::spawn(move || {
+			let (stream, _) = listener.accept().unwrap();
+			thread::sleep(Duration::from_secs(5));
+			drop(stream);
+		});
+		let client =
+			DockerClient { timeout: Duration::from_millis(100), ..DockerClient::new(socket) };
+
+		match client.inspect_container("app-1") {
+			Err(DockerError::Io(err)) =>
+				assert!(matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)),
+			result => panic!("unexpected {:?}", result),
+		}
+	}
+
+	#[test]
+	fn logs_are_demultiplexed() {
+		let mut body = vec![1, 0, 0, 0, 0, 0, 0, 4];
+		body.extend_from_slice(b"out\n");
+		body.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 4]);
+		body.extend_from_slice(b"err\n");
+		let (_dir, client, server) =
+			serve(vec![chunked("200 OK", &[&body]), chunked("200 OK", &[&body])]);
+
+		let mut logs = Vec::new();
+		client
+			.stream_logs("app-1", Some(10), false, |frame| {
+				logs.extend_from_slice(frame);
+				true
+			})
+			.unwrap();
+		assert_eq!(logs, b"out\nerr\n");
+		// Given up after the first frame.
+		let mut frames = 0;
+		client
+			.stream_logs("app-1", None, true, |_| {
+				frames += 1;
+				false
+			})
+			.unwrap();
+		assert_eq!(frames, 1);
+		assert_eq!(
+			server.join().unwrap(),
+			vec![
+				"GET /v1.41/containers/app-1/logs?stdout=true&stderr=true&tail=10&follow=false HTTP/1.1",
+				"GET /v1.41/containers/app-1/logs?stdout=true&stderr=true&tail=all&follow=true HTTP/1.1"
+			]
+		);
+	}
+
+	#[test]
+	fn container_config_maps_options() {
+		let launch = Launch {
+			name: "app-1".into(),
+			args: vec!["--chain".into(), "dev".into()],
+			envs: vec![("RUST_LOG".into(), "info".into())],
+			options: vec![
+				"--network=host".into(),
+				"-p".into(),
+				"9944:9944".into(),
+				"-v".into(),
+				"/data:/data".into(),
+			],
+			..launch_in(Path::new("apps"))
+		};
+
+		let config = container_config("app:1", &launch).unwrap();
+		assert_eq!(config["Cmd"], json!(["--chain", "dev"]));
+		assert_eq!(config["Env"], json!(["RUST_LOG=info"]));
+		assert_eq!(config["HostConfig"]["NetworkMode"], json!("host"));
+		assert_eq!(config["HostConfig"]["Binds"], json!(["/data:/data"]));
+		assert_eq!(
+			config["HostConfig"]["PortBindings"]["9944/tcp"],
+			json!([{ "HostPort": "9944" }])
+		);
+		assert_eq!(config["ExposedPorts"]["9944/tcp"], json!({}));
+
+		let launch = Launch {
+			options: vec![
+				"-e".into(),
+				"CHAIN=dev".into(),
+				"--cap-add=NET_ADMIN".into(),
+				"--ulimit".into(),
+				"nofile=1024:2048".into(),
+				"--ulimit".into(),
+				"core=0".into(),
+				"--memory=2g".into(),
+				"--cpus".into(),
+				"1.5".into(),
+				"-w".into(),
+				"/app".into(),
+			],
+			..launch
+		};
+		let config = container_config("app:1", &launch).unwrap();
+		assert_eq!(config["Env"], json!(["RUST_LOG=info", "CHAIN=dev"]));
+		assert_eq!(config["WorkingDir"], json!("/app"));
+		assert_eq!(config["HostConfig"]["CapAdd"], json!(["NET_ADMIN"]));
+		assert_eq!(
+			config["HostConfig"]["Ulimits"],
+			json!([
+				{ "Name": "nofile", "Soft": 1024, "Hard": 2048 },
+				{ "Name": "core", "Soft": 0, "Hard": 0 }
+			])
+		);
+		assert_eq!(config["HostConfig"]["Memory"], json!(2i64 << 30));
+		assert_eq!(config["HostConfig"]["NanoCpus"], json!(1_500_000_000i64));
+
+		for options in [vec!["--memory", "lots"], vec!["--ulimit", "nofile"], vec!["--pid=host"]] {
+			let launch =
+				Launch { options: options.into_iter().map(Into::into).collect(), ..launch.clone() };
+			assert!(matches!(
+				container_config("app:1", &launch),
+				Err(DockerError::InvalidOption(_))
+			));
+		}
+	}
+
+	#[test]
+	fn check_options_rejects_unmapped_options() {
+		let options =
+			|options: &[&str]| options.iter().map(|option| option.to_string()).collect::<Vec<_>>();
+
+		assert!(check_options(&options(&["-e", "A=1", "--ulimit=nofile=1024", "-d"])).is_ok());
+		assert!(check_options(&options(&["-p", "{{rpc_port}}:9944"])).is_ok());
+		assert!(matches!(
+			check_options(&options(&["--pid", "host"])),
+			Err(DockerError::InvalidOption(option)) if option == "--pid"
+		));
+		assert!(matches!(check_options(&options(&["-e"])), Err(DockerError::InvalidOption(_))));
+	}
+}
@@ -0,0 +1,260 @@
+//! Output of app instances, kept in log files under the data directory of each instance.
+//!
+//! Backends hand the raw output of an instance over as an [`Output`]. On every supervisor tick
+//! its [`LogCollector`] tags the new lines with the instance and the round it serves and appends
+//! them to a [`LogFile`], which is rotated by size and age.
+
+use super::backend::Output;
+use std::{
+	collections::VecDeque,
+	ffi::OsString,
+	fs::{self, File, OpenOptions},
+	io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+
+/// Lines kept in memory for readiness probes.
+const RECENT_LINES: usize = 1000;
+
+/// Output without a line break is written out as a line once this long.
+const MAX_LINE: usize = 64 * 1024;
+
+/// How the log files of instances are rotated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogPolicy {
+	/// Size in bytes after which the file is rotated.
+	pub max_size: u64,
+	/// Age after which the file is rotated.
+	pub max_age: Duration,
+	/// Rotated files kept besides the current one, `<file>.1` being the newest.
+	pub generations: u32,
+}
+
+/// Log file rotated according to its policy, opened on the first write.
+#[derive(Debug)]
+pub struct LogFile {
+	path: PathBuf,
+	policy: LogPolicy,
+	file: Option<File>,
+	size: u64,
+	created: SystemTime,
+}
+
+impl LogFile {
+	pub fn new(path: PathBuf, policy: LogPolicy) -> Self {
+		LogFile { path, policy, file: None, size: 0, created: SystemTime::now() }
+	}
+
+	/// Append `line` and a line break, rotating the file first when it is due.
+	pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+		if self.file.is_none() {
+			self.open()?;
+		}
+		let age = self.created.elapsed().unwrap_or_default();
+		if self.size > 0 && (self.size >= self.policy.max_size || age >= self.policy.max_age) {
+			self.rotate()?;
+			self.open()?;
+		}
+		let Some(file) = &mut self.file else { return Err(ErrorKind::NotFound.into()) };
+		file.write_all(&[line, b"\n"].concat())?;
+		self.size += line.len() as u64 + 1;
+		Ok(())
+	}
+
+	fn open(&mut self) -> io::Result<()> {
+		if let Some(dir) = self.path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+		let metadata = file.metadata()?;
+		self.size = metadata.len();
+		// A file kept from before a restart of the node ages on.
+		self.created = metadata.created().unwrap_or_else(|_| SystemTime::now());
+		self.file = Some(file);
+		Ok(())
+	}
+
+	// Shift every generation up by one, the oldest one falls off.
+	fn rotate(&mut self) -> io::Result<()> {
+		self.file = None;
+		if self.policy.generations == 0 {
+			return fs::remove_file(&self.path);
+		}
+		for generation in (1..self.policy.generations).rev() {
+			match fs::rename(
+				generation_path(&self.path, generation),
+				generation_path(&self.path, generation + 1),
+			) {
+				Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+				_ => {},
+			}
+		}
+		fs::rename(&self.path, generation_path(&self.path, 1))
+	}
+}
+
+fn generation_path(path: &Path, generation: u32) -> PathBuf {
+	let mut path = OsString::from(path);
+	path.push(format!(".{}", generation));
+	path.into()
+}
+
+/// Last `tail` lines of the log file at `path`.
+pub fn tail(path: &Path, tail: usize) -> io::Result<String> {
+	// Only the end of the file is needed, a line is rarely longer than 1k.
+	let mut file = File::open(path)?;
+	let len = file.metadata()?.len();
+	file.seek(SeekFrom::Start(len.saturating_sub(tail as u64 * 1024)))?;
+	let mut content = Vec::new();
+	file.read_to_end(&mut content)?;
+	let content = String::from_utf8_lossy(&content);
+	let lines: Vec<&str> = content.lines().collect();
+	Ok(lines[lines.len().saturating_sub(tail)..].join("\n"))
+}
+
+/// Moves the output of an instance into its log file.
+#[derive(Debug)]
+pub struct LogCollector {
+	output: Box<dyn Output>,
+	file: LogFile,
+	name: String,
+	/// Round the instance serves, `None` while it syncs.
+	pub round: Option<u32>,
+	// Output after the last line break.
+	partial: Vec<u8>,
+	// Untagged lines since the instance was started.
+	recent: VecDeque<String>,
+}
+
+impl LogCollector {
+	/// Collect `output` of the instance `name` into `file`.
+	pub fn new(output: Box<dyn Output>, file: LogFile, name: String) -> Self {
+		LogCollector {
+			output,
+			file,
+			name,
+			round: None,
+			partial: Vec::new(),
+			recent: VecDeque::new(),
+		}
+	}
+
+	/// Go on with the output of the instance started again.
+	pub fn restarted(&mut self, output: Box<dyn Output>) {
+		self.output = output;
+		self.recent.clear();
+	}
+
+	/// Write the lines output since the last call to the log file.
+	pub fn collect(&mut self) -> io::Result<()> {
+		let output = self.output.read().map_err(io::Error::other)?;
+		self.partial.extend_from_slice(&output);
+		while let Some(end) = self.partial.iter().position(|byte| *byte == b'\n') {
+			let line: Vec<u8> = self.partial.drain(..=end).take(end).collect();
+			self.write(&line)?;
+		}
+		if self.partial.len() >= MAX_LINE {
+			let line = std::mem::take(&mut self.partial);
+			self.write(&line)?;
+		}
+		Ok(())
+	}
+
+	fn write(&mut self, line: &[u8]) -> io::Result<()> {
+		let line = line.strip_suffix(b"\r").unwrap_or(line);
+		let tag = match self.round {
+			Some(round) => format!("[{} round {}] ", self.name, round),
+			None => format!("[{} sync] ", self.name),
+		};
+		self.file.write_line(&[tag.as_bytes(), line].concat())?;
+		if self.recent.len() == RECENT_LINES {
+			self.recent.pop_front();
+		}
+		self.recent.push_back(String::from_utf8_lossy(line).into_owned());
+		Ok(())
+	}
+
+	/// Last `tail` lines output since the instance was last started.
+	pub fn recent(&self, tail: usize) -> impl Iterator<Item = &str> {
+		self.recent
+			.iter()
+			.skip(self.recent.len().saturating_sub(tail))
+			.map(|line| line.as_str())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::container_task::backend::BackendResult;
+
+	#[derive(Debug, Default)]
+	struct Chunks(VecDeque<&'static str>);
+
+	impl Output for Chunks {
+		fn read(&mut self) -> BackendResult<Vec<u8>> {
+			Ok(self.0.pop_front().unwrap_or_default().as_bytes().to_vec())
+		}
+	}
+
+	fn policy(max_size: u64, generations: u32) -> LogPolicy {
+		LogPolicy { max_size, max_age: Duration::from_secs(3600), generations }
+	}
+
+	#[test]
+	fn lines_are_tagged_with_instance_and_round() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("logs/app");
+		let output = Chunks(["Idle\r\nImpor", "ted #1\n", "", "Imported #2\n"].into());
+		let mut collector = LogCollector::new(
+			Box::new(output),
+			LogFile::new(path.clone(), policy(1 << 20, 1)),
+			"app-sequencer-1".into(),
+		);
+
+		collector.collect().unwrap();
+		collector.round = Some(3);
+		collector.collect().unwrap();
+		collector.collect().unwrap();
+		collector.collect().unwrap();
+
+		assert_eq!(
+			fs::read_to_string(&path).unwrap(),
+			"[app-sequencer-1 sync] Idle\n[app-sequencer-1 round 3] Imported #1\n\
+			[app-sequencer-1 round 3] Imported #2\n"
+		);
+		assert_eq!(tail(&path, 1).unwrap(), "[app-sequencer-1 round 3] Imported #2");
+		assert_eq!(collector.recent(2).collect::<Vec<_>>(), vec!["Imported #1", "Imported #2"]);
+	}
+
+	#[test]
+	fn rotation_keeps_configured_generations() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("app");
+		let mut file = LogFile::new(path.clone(), policy(6, 2));
+
+		for line in ["first", "second", "third", "fourth"] {
+			file.write_line(line.as_bytes()).unwrap();
+		}
+
+		assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+		assert_eq!(fs::read_to_string(generation_path(&path, 1)).unwrap(), "third\n");
+		assert_eq!(fs::read_to_string(generation_path(&path, 2)).unwrap(), "second\n");
+		assert!(!generation_path(&path, 3).exists());
+	}
+
+	#[test]
+	fn old_file_is_rotated() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("app");
+		fs::write(&path, "old\n").unwrap();
+		let policy = LogPolicy { max_size: 1 << 20, max_age: Duration::ZERO, generations: 1 };
+		let mut file = LogFile::new(path.clone(), policy);
+
+		file.write_line(b"new").unwrap();
+
+		assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+		assert_eq!(fs::read_to_string(generation_path(&path, 1)).unwrap(), "old\n");
+	}
+}
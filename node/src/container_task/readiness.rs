@@ -0,0 +1,197 @@
+//! Tells when a started app instance is ready to take over.
+//!
+//! The container task switches a round over to the synced release only once its sync instance
+//! passes the [`ReadinessProbe`] the app declares, and keeps the instance of the previous round
+//! until the new run instance passes it as well. An instance still not ready after the timeout
+//! counts as ready, so a broken probe delays a switch but never blocks it.
+
+use super::backend::{BackendResult, Instance, InstanceState};
+use primitives_container::{DownloadInfo, ReadinessProbe};
+use regex::Regex;
+use std::{
+	io::{BufRead, BufReader, Read, Write},
+	net::{Ipv4Addr, SocketAddr, TcpStream},
+	ops::RangeInclusive,
+	time::{Duration, Instant},
+};
+
+/// Time one probe may take, probes run on the supervisor tick.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Lines of output a log probe looks at, from the end.
+const LOG_PROBE_LINES: usize = 1000;
+
+/// Readiness bookkeeping of one instance.
+#[derive(Debug)]
+pub struct Readiness {
+	/// Whether it passed its probe or ran out of time to.
+	pub ready: bool,
+	started: Instant,
+}
+
+impl Readiness {
+	/// Readiness of an instance of `app` started just now, apps without a probe are ready at once.
+	pub fn new(app: &DownloadInfo) -> Self {
+		Readiness { ready: app.readiness.is_none(), started: Instant::now() }
+	}
+}
+
+/// Probe the instance unless it is ready already, returns whether it is ready. `app_ports` are the
+/// ports of both instances of its app.
+pub fn check(
+	instance: &mut Instance,
+	app_ports: &RangeInclusive<u16>,
+	timeout: Duration,
+	now: Instant,
+) -> bool {
+	if instance.readiness.ready {
+		return true;
+	}
+	// A probe could hit whatever took over the port of an exited instance.
+	let running = instance.state() == InstanceState::Running;
+	let passed = match &instance.app.readiness {
+		Some(probe) if running =>
+			probe_instance(probe, instance, app_ports).unwrap_or_else(|err| {
+				log::debug!("probe instance {:?} error:{:?}", instance.handle, err);
+				false
+			}),
+		Some(_) => false,
+		None => true,
+	};
+	if passed {
+		log::info!("instance {:?} is ready", instance.handle);
+	} else if now.saturating_duration_since(instance.readiness.started) >= timeout {
+		log::warn!(
+			"instance {:?} not ready after {:?}, taking it as ready",
+			instance.handle,
+			timeout
+		);
+	} else {
+		return false;
+	}
+	instance.readiness.ready = true;
+	true
+}
+
+fn probe_instance(
+	probe: &ReadinessProbe<Vec<u8>>,
+	instance: &Instance,
+	app_ports: &RangeInclusive<u16>,
+) -> BackendResult<bool> {
+	// During a handover both instances of the app listen side by side, an absolute port among
+	// theirs names only one of them, whichever instance is probed.
+	let absolute = |port: u16| {
+		if app_ports.contains(&port) {
+			return Err(format!(
+				"port {} is one of the app's own, probe it by its offset in the instance's range",
+				port
+			));
+		}
+		Ok(port)
+	};
+	let in_range = |offset: u16| {
+		instance
+			.launch
+			.port_base
+			.checked_add(offset)
+			.ok_or_else(|| format!("port offset {} is out of range", offset))
+	};
+	match probe {
+		ReadinessProbe::Http { port, path } =>
+			http_ok(absolute(*port)?, std::str::from_utf8(path)?),
+		ReadinessProbe::Tcp { port } => Ok(connect(absolute(*port)?).is_ok()),
+		ReadinessProbe::HttpInRange { offset, path } =>
+			http_ok(in_range(*offset)?, std::str::from_utf8(path)?),
+		ReadinessProbe::TcpInRange { offset } => Ok(connect(in_range(*offset)?).is_ok()),
+		ReadinessProbe::Log { pattern } => {
+			let pattern = Regex::new(std::str::from_utf8(pattern)?)?;
+			Ok(instance.output(LOG_PROBE_LINES).any(|line| pattern.is_match(line)))
+		},
+	}
+}
+
+fn connect(port: u16) -> std::io::Result<TcpStream> {
+	let stream =
+		TcpStream::connect_timeout(&SocketAddr::from((Ipv4Addr::LOCALHOST, port)), PROBE_TIMEOUT)?;
+	stream.set_read_timeout(Some(PROBE_TIMEOUT))?;
+	stream.set_write_timeout(Some(PROBE_TIMEOUT))?;
+	Ok(stream)
+}
+
+// Whether a GET of `path` on the local port answers with a 2xx status.
+fn http_ok(port: u16, path: &str) -> BackendResult<bool> {
+	let mut stream = connect(port)?;
+	let path = if path.starts_with('/') { path.to_string() } else { format!("/{}", path) };
+	let request =
+		format!("GET {} HTTP/1.0\r\nHost: localhost:{}\r\nConnection: close\r\n\r\n", path, port);
+	stream.write_all(request.as_bytes())?;
+	let mut status_line = String::new();
+	BufReader::new(stream.take(1024)).read_line(&mut status_line)?;
+	let status = status_line
+		.split_whitespace()
+		.nth(1)
+		.and_then(|status| status.parse::<u16>().ok());
+	Ok(status.is_some_and(|status| (200..300).contains(&status)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		super::{
+			backend::{FakeBackend, Launch},
+			tests::{app_info, launch_in, policies},
+		},
+		*,
+	};
+	use std::{net::TcpListener, sync::Arc, thread};
+
+	// Answers one request with `status_line`, returns the port.
+	fn serve(status_line: &'static str) -> u16 {
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+		let port = listener.local_addr().unwrap().port();
+		thread::spawn(move || {
+			let (stream, _) = listener.accept().unwrap();
+			let mut lines = BufReader::new(&stream).lines().map(Result::unwrap);
+			assert_eq!(lines.next().unwrap(), "GET /health HTTP/1.0");
+			// Answered once the whole request is in.
+			lines.find(|line| line.is_empty());
+			write!(&stream, "{}\r\n\r\n", status_line).unwrap();
+		});
+		port
+	}
+
+	#[test]
+	fn http_probe_wants_success_status() {
+		assert!(http_ok(serve("HTTP/1.1 200 OK"), "/health").unwrap());
+		assert!(!http_ok(serve("HTTP/1.1 503 Service Unavailable"), "health").unwrap());
+	}
+
+	#[test]
+	fn tcp_probe_connects() {
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+		let port = listener.local_addr().unwrap().port();
+		assert!(connect(port).is_ok());
+		drop(listener);
+		assert!(connect(port).is_err());
+	}
+
+	#[test]
+	fn probe_ports_are_absolute_or_in_range() {
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let dir = tempfile::tempdir().unwrap();
+		let launch = Launch { port_base: port - 2, ..launch_in(dir.path()) };
+		let (policy, _) = policies();
+		let instance =
+			Instance::start(Arc::new(FakeBackend::default()), app_info(), launch, policy).unwrap();
+
+		let probe = |probe, app_ports| probe_instance(&probe, &instance, &app_ports);
+
+		// An absolute port outside of the ports of the app, e.g. fixed by its args.
+		assert!(probe(ReadinessProbe::Tcp { port }, 0..=0).unwrap());
+		assert!(probe(ReadinessProbe::Tcp { port }, port - 2..=port + 97).is_err());
+		assert!(probe(ReadinessProbe::TcpInRange { offset: 2 }, port - 2..=port + 97).unwrap());
+		assert!(!probe(ReadinessProbe::TcpInRange { offset: 1 }, port - 2..=port + 97).unwrap());
+		assert!(probe(ReadinessProbe::TcpInRange { offset: u16::MAX }, 0..=0).is_err());
+	}
+}
@@ -0,0 +1,361 @@
+//! The running apps persisted under the node's data path, so a restarted node takes over the
+//! instances it left running instead of starting them a second time.
+
+use super::{
+	backend::{Instance, InstanceId, Launch},
+	AppRole, ContainerConfig, InstanceIndex, RunStatus, RunningApp, RunningApps,
+};
+use futures::lock::Mutex;
+use primitives_container::DownloadInfo;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::BTreeMap,
+	fs,
+	io::{self, ErrorKind},
+	path::Path,
+	sync::Arc,
+};
+
+/// Name of the file the state is kept in, under the node's data path.
+pub const STATE_FILE: &str = "container_state.json";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedInstance {
+	slot: u8,
+	id: InstanceId,
+	app: DownloadInfo,
+	launch: Launch,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedApp {
+	// Missing in state written before the node ran several apps, that app was the sequencer's.
+	#[serde(default)]
+	role: AppRole,
+	group_id: u32,
+	app_id: u32,
+	running: RunStatus,
+	app_info: Option<DownloadInfo>,
+	cur_ins: u8,
+	download_url: Option<Vec<u8>>,
+	run_app_info: Option<DownloadInfo>,
+	instances: Vec<PersistedInstance>,
+	#[serde(default)]
+	round: Option<u32>,
+	#[serde(default)]
+	synced_for: Option<u32>,
+	#[serde(default)]
+	handover: bool,
+}
+
+impl RunningApp {
+	fn persisted(&self) -> PersistedApp {
+		let instances = [InstanceIndex::Instance1, InstanceIndex::Instance2]
+			.into_iter()
+			.filter_map(|index| {
+				let instance = self.instance(index).as_ref()?;
+				Some(PersistedInstance {
+					slot: index.number(),
+					id: instance.handle.id(),
+					app: instance.app.clone(),
+					launch: instance.launch.clone(),
+				})
+			})
+			.collect();
+		PersistedApp {
+			role: self.role,
+			group_id: self.group_id,
+			app_id: self.app_id,
+			running: self.running,
+			app_info: self.app_info.clone(),
+			cur_ins: self.cur_ins.number(),
+			download_url: self.download_url.clone(),
+			run_app_info: self.run_app_info.clone(),
+			instances,
+			round: self.round,
+			synced_for: self.synced_for,
+			handover: self.handover,
+		}
+	}
+
+	// Take over the state of an app written by a previous run of the node.
+	fn restored(state: PersistedApp, config: &ContainerConfig) -> Result<Self, String> {
+		let mut app = RunningApp::new(state.role, config)?;
+		app.group_id = state.group_id;
+		app.app_id = state.app_id;
+		app.running = state.running;
+		app.app_info = state.app_info;
+		app.cur_ins = InstanceIndex::from_number(state.cur_ins).unwrap_or(app.cur_ins);
+		app.download_url = state.download_url;
+		app.run_app_info = state.run_app_info;
+		app.round = state.round;
+		app.synced_for = state.synced_for;
+		app.handover = state.handover;
+
+		for PersistedInstance { slot, id, app: app_info, launch } in state.instances {
+			let Some(slot) = InstanceIndex::from_number(slot) else { continue };
+			let backend = config.backends.for_app(&app_info);
+			let instance = match Instance::reattach(
+				backend.clone(),
+				app_info.clone(),
+				launch.clone(),
+				&id,
+				config.log_policy,
+			) {
+				Ok(Some(instance)) => {
+					log::info!("reattached instance {}:{:?}", slot.number(), instance.handle);
+					Some(instance)
+				},
+				result => {
+					log::info!(
+						"instance {} {:?} is gone ({:?}), starting it",
+						slot.number(),
+						id,
+						result
+					);
+					Instance::start(backend, app_info, launch, config.log_policy)
+						.map_err(|err| {
+							log::warn!("start instance {} error:{:?}", slot.number(), err)
+						})
+						.ok()
+				},
+			};
+			*app.instance_mut(slot) = instance;
+		}
+		// Only the active instance and one taking over serve the round, the other one syncs.
+		for slot in [app.cur_ins, app.cur_ins.other()] {
+			let round = if slot == app.cur_ins && !app.handover { None } else { app.round };
+			if let Some(instance) = app.instance_mut(slot) {
+				instance.log.round = round;
+			}
+		}
+
+		if app.running == RunStatus::Downloading {
+			// Forget the group so the next head prepares the release again, the download
+			// resumes from what was already fetched.
+			app.group_id = 0xFFFFFFFF;
+			app.running = RunStatus::Pending;
+		}
+		Ok(app)
+	}
+}
+
+/// Write the state of `apps` to `path` if it changed since it was last written.
+pub(super) async fn save(
+	apps: &RunningApps,
+	path: &Path,
+	saved: &mut Option<String>,
+) -> io::Result<()> {
+	let apps: Vec<_> = apps.lock().await.values().cloned().collect();
+	let mut state = Vec::new();
+	for app in apps {
+		state.push(app.lock().await.persisted());
+	}
+	let state = serde_json::to_string(&state)?;
+	if saved.as_ref() == Some(&state) {
+		return Ok(());
+	}
+	// Written aside and renamed, so a crash never leaves a truncated file.
+	let tmp = path.with_extension("tmp");
+	fs::write(&tmp, &state)?;
+	fs::rename(&tmp, path)?;
+	*saved = Some(state);
+	Ok(())
+}
+
+/// Load the apps written by a previous run of the node and take over their instances.
+/// Instances that are gone are started again, a download that was cut short is redone.
+pub(super) fn restore(
+	path: &Path,
+	config: &ContainerConfig,
+) -> BTreeMap<AppRole, Arc<Mutex<RunningApp>>> {
+	let mut apps = BTreeMap::new();
+	let sequencer =
+		RunningApp::new(AppRole::Sequencer, config).expect("checked before the task starts; qed");
+	apps.insert(AppRole::Sequencer, Arc::new(Mutex::new(sequencer)));
+	let state = match fs::read(path) {
+		Ok(state) => state,
+		Err(err) if err.kind() == ErrorKind::NotFound => return apps,
+		Err(err) => {
+			log::warn!("read container state {:?} error:{:?}", path, err);
+			return apps;
+		},
+	};
+	let state: Vec<PersistedApp> = match serde_json::from_slice(&state) {
+		Ok(state) => state,
+		Err(err) => {
+			log::warn!("invalid container state {:?}:{:?}", path, err);
+			return apps;
+		},
+	};
+	for state in state {
+		let role = state.role;
+		match RunningApp::restored(state, config) {
+			Ok(app) => {
+				apps.insert(role, Arc::new(Mutex::new(app)));
+			},
+			Err(err) => log::error!("not restoring the app of {:?}: {}", role, err),
+		}
+	}
+	apps
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		super::{
+			backend::{Backends, FakeBackend},
+			tests::{app_info, launch_in, policies},
+			HeadFollow,
+		},
+		*,
+	};
+	use futures::executor::block_on;
+	use std::time::Duration;
+
+	fn launch(name: &str) -> Launch {
+		Launch { name: name.into(), ..launch_in(Path::new("apps")) }
+	}
+
+	fn config(dir: &Path, fake: &Arc<FakeBackend>) -> ContainerConfig {
+		ContainerConfig {
+			backends: Backends::new(fake.clone(), fake.clone()),
+			restart_policy: policies().1,
+			state_file: dir.join(STATE_FILE),
+			apps_dir: dir.join("apps"),
+			port_base: 30000,
+			follow: HeadFollow::Finalized,
+			ready_timeout: Duration::from_secs(600),
+			log_policy: policies().0,
+		}
+	}
+
+	fn running_app(config: &ContainerConfig, role: AppRole) -> RunningApp {
+		let mut app = RunningApp::new(role, config).unwrap();
+		app.group_id = 1;
+		app.app_id = 1;
+		app.app_info = Some(app_info());
+		app.run_app_info = Some(app_info());
+		app.cur_ins = InstanceIndex::Instance2;
+		app.round = Some(3);
+		for (slot, name) in
+			[(InstanceIndex::Instance1, "app-1"), (InstanceIndex::Instance2, "app-2")]
+		{
+			let backend = config.backends.for_app(&app_info());
+			*app.instance_mut(slot) = Some(
+				Instance::start(backend, app_info(), launch(name), config.log_policy).unwrap(),
+			);
+		}
+		app
+	}
+
+	fn save_apps(config: &ContainerConfig, running: Vec<RunningApp>) {
+		let apps: RunningApps = Default::default();
+		let mut saved = None;
+		block_on(async {
+			let mut map = apps.lock().await;
+			for app in running {
+				map.insert(app.role, Arc::new(Mutex::new(app)));
+			}
+		});
+		block_on(save(&apps, &config.state_file, &mut saved)).unwrap();
+	}
+
+	fn sequencer(
+		apps: &BTreeMap<AppRole, Arc<Mutex<RunningApp>>>,
+		config: &ContainerConfig,
+	) -> RunningApp {
+		let app = apps.get(&AppRole::Sequencer).unwrap();
+		std::mem::replace(
+			&mut *block_on(app.lock()),
+			RunningApp::new(AppRole::Sequencer, config).unwrap(),
+		)
+	}
+
+	#[test]
+	fn restore_reattaches_live_instances() {
+		let dir = tempfile::tempdir().unwrap();
+		let fake = Arc::new(FakeBackend::default());
+		let config = config(dir.path(), &fake);
+
+		save_apps(&config, vec![running_app(&config, AppRole::Sequencer)]);
+		let mut app = sequencer(&restore(&config.state_file, &config), &config);
+
+		assert_eq!(app.group_id, 1);
+		assert_eq!(app.cur_ins, InstanceIndex::Instance2);
+		assert_eq!(app.round, Some(3));
+		assert_eq!(app.status().instances.len(), 2);
+		// Taken over, not started a second time.
+		assert_eq!(fake.started.lock().unwrap().len(), 2);
+	}
+
+	#[test]
+	fn restore_starts_instances_that_are_gone() {
+		let dir = tempfile::tempdir().unwrap();
+		let fake = Arc::new(FakeBackend::default());
+		let config = config(dir.path(), &fake);
+
+		save_apps(&config, vec![running_app(&config, AppRole::Sequencer)]);
+		*fake.exit_code.lock().unwrap() = Some(0);
+		let app = sequencer(&restore(&config.state_file, &config), &config);
+
+		assert!(app.instance1.is_some());
+		assert!(app.instance2.is_some());
+		let started = fake.started.lock().unwrap();
+		assert_eq!(started.len(), 4);
+		assert_eq!(started[2].name, "app-1");
+		assert_eq!(started[3].name, "app-2");
+	}
+
+	#[test]
+	fn restore_redoes_interrupted_download() {
+		let dir = tempfile::tempdir().unwrap();
+		let fake = Arc::new(FakeBackend::default());
+		let config = config(dir.path(), &fake);
+
+		let mut app = RunningApp::new(AppRole::Sequencer, &config).unwrap();
+		app.group_id = 1;
+		app.running = RunStatus::Downloading;
+		save_apps(&config, vec![app]);
+		let app = sequencer(&restore(&config.state_file, &config), &config);
+
+		assert_eq!(app.group_id, 0xFFFFFFFF);
+		assert_eq!(app.running, RunStatus::Pending);
+	}
+
+	#[test]
+	fn restore_without_state_starts_fresh() {
+		let dir = tempfile::tempdir().unwrap();
+		let fake = Arc::new(FakeBackend::default());
+		let config = config(dir.path(), &fake);
+
+		let apps = restore(&config.state_file, &config);
+		assert_eq!(apps.keys().collect::<Vec<_>>(), [&AppRole::Sequencer]);
+		let app = sequencer(&apps, &config);
+		assert_eq!(app.group_id, 0xFFFFFFFF);
+		assert!(app.instance1.is_none());
+	}
+
+	#[test]
+	fn restore_brings_back_every_app() {
+		let dir = tempfile::tempdir().unwrap();
+		let fake = Arc::new(FakeBackend::default());
+		let config = config(dir.path(), &fake);
+
+		save_apps(
+			&config,
+			vec![
+				running_app(&config, AppRole::Sequencer),
+				running_app(&config, AppRole::Processor(3)),
+			],
+		);
+		let apps = restore(&config.state_file, &config);
+
+		assert_eq!(apps.keys().collect::<Vec<_>>(), [&AppRole::Sequencer, &AppRole::Processor(3)]);
+		let processor = block_on(apps[&AppRole::Processor(3)].lock());
+		assert_eq!(processor.apps_dir, dir.path().join("apps"));
+		assert_eq!(processor.port_base, 30400);
+	}
+}
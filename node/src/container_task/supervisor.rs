@@ -0,0 +1,167 @@
+//! Restarts app instances that exited on their own.
+//!
+//! Each [`Instance`](super::backend::Instance) carries a [`Supervision`] the container task
+//! feeds with the instance state on every check. A crashed instance is restarted after an
+//! exponentially growing backoff, until it used up its failure budget.
+
+use super::backend::InstanceState;
+use std::time::{Duration, Instant};
+
+/// An instance running this long without a crash gets its full failure budget back.
+const HEALTHY_AFTER: Duration = Duration::from_secs(600);
+
+/// How crashed instances are restarted.
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+	/// Delay before the first restart, doubled on each following failure.
+	pub backoff: Duration,
+	/// Cap on the delay between restarts.
+	pub max_backoff: Duration,
+	/// Failures in a row after which the instance is left down.
+	pub failure_budget: u32,
+}
+
+/// What to do with a supervised instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+	/// Nothing, it runs or waits for its restart.
+	Wait,
+	/// It just crashed, the restart follows after the given backoff.
+	Crashed(Duration),
+	/// Its backoff is over, restart it.
+	Restart,
+	/// It crashed once more than the budget allows, leave it down.
+	GiveUp,
+}
+
+/// Crash bookkeeping of one instance.
+#[derive(Debug, Default)]
+pub struct Supervision {
+	/// Crashes since it last ran healthy.
+	pub failures: u32,
+	/// Restarts since it was started.
+	pub restarts: u32,
+	/// Exit code of the last crash, `None` inside when it had none.
+	pub last_exit: Option<Option<i32>>,
+	/// Whether it used up its failure budget.
+	pub given_up: bool,
+	backoff: Duration,
+	restart_at: Option<Instant>,
+	running_since: Option<Instant>,
+}
+
+impl Supervision {
+	/// Feed the current state of the instance.
+	pub fn observe(
+		&mut self,
+		state: InstanceState,
+		now: Instant,
+		policy: &RestartPolicy,
+	) -> Action {
+		if self.given_up {
+			return Action::Wait;
+		}
+		match state {
+			InstanceState::Running => {
+				let since = *self.running_since.get_or_insert(now);
+				if now.duration_since(since) >= HEALTHY_AFTER {
+					self.failures = 0;
+				}
+				Action::Wait
+			},
+			InstanceState::Exited(code) => match self.restart_at {
+				None => self.crashed(code, now, policy),
+				Some(restart_at) if now >= restart_at => Action::Restart,
+				Some(_) => Action::Wait,
+			},
+			InstanceState::Unknown => Action::Wait,
+		}
+	}
+
+	/// Count a crash, or a failed restart, and schedule the next restart.
+	pub fn crashed(&mut self, code: Option<i32>, now: Instant, policy: &RestartPolicy) -> Action {
+		self.failures += 1;
+		self.last_exit = Some(code);
+		self.running_since = None;
+		if self.failures > policy.failure_budget {
+			self.given_up = true;
+			self.restart_at = None;
+			return Action::GiveUp;
+		}
+		self.backoff = if self.failures == 1 {
+			policy.backoff
+		} else {
+			(self.backoff * 2).min(policy.max_backoff)
+		};
+		self.restart_at = Some(now + self.backoff);
+		Action::Crashed(self.backoff)
+	}
+
+	/// The instance was started again.
+	pub fn restarted(&mut self) {
+		self.restarts += 1;
+		self.restart_at = None;
+		self.running_since = None;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const POLICY: RestartPolicy = RestartPolicy {
+		backoff: Duration::from_secs(1),
+		max_backoff: Duration::from_secs(3),
+		failure_budget: 3,
+	};
+
+	fn secs(secs: u64) -> Duration {
+		Duration::from_secs(secs)
+	}
+
+	#[test]
+	fn restarts_with_exponential_backoff_until_budget_is_used() {
+		let start = Instant::now();
+		let mut supervision = Supervision::default();
+		let exited = InstanceState::Exited(Some(101));
+
+		assert_eq!(supervision.observe(InstanceState::Running, start, &POLICY), Action::Wait);
+		assert_eq!(supervision.observe(exited, start, &POLICY), Action::Crashed(secs(1)));
+		assert_eq!(supervision.observe(exited, start, &POLICY), Action::Wait);
+		assert_eq!(supervision.observe(exited, start + secs(1), &POLICY), Action::Restart);
+		supervision.restarted();
+
+		assert_eq!(supervision.observe(exited, start + secs(2), &POLICY), Action::Crashed(secs(2)));
+		assert_eq!(supervision.observe(exited, start + secs(4), &POLICY), Action::Restart);
+		supervision.restarted();
+		// The backoff is capped.
+		assert_eq!(supervision.observe(exited, start + secs(5), &POLICY), Action::Crashed(secs(3)));
+		supervision.restarted();
+
+		assert_eq!(supervision.observe(exited, start + secs(9), &POLICY), Action::GiveUp);
+		assert!(supervision.given_up);
+		assert_eq!(supervision.failures, 4);
+		assert_eq!(supervision.restarts, 3);
+		assert_eq!(supervision.last_exit, Some(Some(101)));
+		assert_eq!(supervision.observe(exited, start + secs(20), &POLICY), Action::Wait);
+	}
+
+	#[test]
+	fn running_healthy_restores_the_budget() {
+		let start = Instant::now();
+		let mut supervision = Supervision::default();
+
+		supervision.observe(InstanceState::Exited(None), start, &POLICY);
+		supervision.restarted();
+		supervision.observe(InstanceState::Running, start + secs(1), &POLICY);
+		assert_eq!(supervision.failures, 1);
+
+		supervision.observe(InstanceState::Running, start + secs(1) + HEALTHY_AFTER, &POLICY);
+		assert_eq!(supervision.failures, 0);
+		// The backoff starts over as well.
+		assert_eq!(
+			supervision.observe(InstanceState::Exited(None), start + secs(700), &POLICY),
+			Action::Crashed(secs(1))
+		);
+	}
+}
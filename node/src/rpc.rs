@@ -5,52 +5,76 @@
 
 #![warn(missing_docs)]
 
+pub mod container;
+pub mod popsicle;
+
 use std::sync::Arc;
 
 use popsicle_runtime::{opaque::Block, AccountId, Balance, Nonce};
 
-pub use sc_rpc::DenyUnsafe;
+use sc_client_api::{Backend, BlockchainEvents};
+pub use sc_rpc::{DenyUnsafe, SubscriptionTaskExecutor};
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_keystore::KeystorePtr;
+
+use crate::container_task::ContainerHandle;
 
 /// A type representing all RPC extensions.
 pub type RpcExtension = jsonrpsee::RpcModule<()>;
 
 /// Full client dependencies
-pub struct FullDeps<C, P> {
+pub struct FullDeps<C, P, B> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// Keystore holding the sequencer key.
+	pub keystore: KeystorePtr,
+	/// Executor for subscription tasks.
+	pub subscription_executor: SubscriptionTaskExecutor,
+	/// Handle of the local container task.
+	pub container: ContainerHandle,
+	/// Backend holding the offchain storage.
+	pub backend: Arc<B>,
 }
 
 /// Instantiate all RPC extensions.
-pub fn create_full<C, P>(
-	deps: FullDeps<C, P>,
+pub fn create_full<C, P, B>(
+	deps: FullDeps<C, P, B>,
 ) -> Result<RpcExtension, Box<dyn std::error::Error + Send + Sync>>
 where
 	C: ProvideRuntimeApi<Block>
 		+ HeaderBackend<Block>
 		+ HeaderMetadata<Block, Error = BlockChainError>
+		+ BlockchainEvents<Block>
 		+ Send
 		+ Sync
 		+ 'static,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
 	C::Api: BlockBuilder<Block>,
+	C::Api: primitives_container::ContainerRuntimeApi<Block, AccountId>,
+	C::Api: primitives_sequencer_staking::SequencerStakingApi<Block, AccountId, Balance>,
 	P: TransactionPool + Sync + Send + 'static,
+	B: Backend<Block> + Send + Sync + 'static,
 {
+	use container::{Container, ContainerApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
+	use popsicle::{Popsicle, PopsicleApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
 	let mut module = RpcExtension::new(());
-	let FullDeps { client, pool, deny_unsafe } = deps;
+	let FullDeps { client, pool, deny_unsafe, keystore, subscription_executor, container, backend } =
+		deps;
 
 	module.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	module.merge(Popsicle::new(client, keystore, subscription_executor).into_rpc())?;
+	module.merge(Container::new(container, backend.offchain_storage(), deny_unsafe).into_rpc())?;
 	Ok(module)
 }
@@ -0,0 +1,212 @@
+//! Unsafe `container_*` RPC methods to inspect and control the apps run by the local node.
+//! Methods acting on one app take its role, the sequencer's app unless given.
+
+use jsonrpsee::{
+	core::{async_trait, RpcResult},
+	proc_macros::rpc,
+	types::{error::ErrorObject, ErrorObjectOwned},
+};
+use sc_rpc::DenyUnsafe;
+use sp_core::offchain::OffchainStorage;
+use sp_offchain::STORAGE_PREFIX;
+
+use crate::container_task::{
+	decode_args, effective_args, encode_args, validate_args, AppRole, ArgsKind, ContainerCommand,
+	ContainerHandle, ContainerStatus, EffectiveArgs,
+};
+
+#[rpc(server)]
+pub trait ContainerApi {
+	/// Status of every app run by the node.
+	#[method(name = "container_apps")]
+	async fn apps(&self) -> RpcResult<Vec<ContainerStatus>>;
+
+	/// Status of the app, its download and both instances.
+	#[method(name = "container_status")]
+	async fn status(&self, app: Option<AppRole>) -> RpcResult<ContainerStatus>;
+
+	/// Restart the active instance.
+	#[method(name = "container_restart")]
+	async fn restart(&self, app: Option<AppRole>) -> RpcResult<()>;
+
+	/// Remove the fetched binary or image and download it again.
+	#[method(name = "container_redownload")]
+	async fn redownload(&self, app: Option<AppRole>) -> RpcResult<()>;
+
+	/// Stop all instances until the next restart or re-download.
+	#[method(name = "container_stop")]
+	async fn stop(&self, app: Option<AppRole>) -> RpcResult<()>;
+
+	/// Last lines of the log of the active instance, tagged with instance and round, 100 unless
+	/// `tail` is given.
+	#[method(name = "container_logs")]
+	async fn logs(&self, tail: Option<u32>, app: Option<AppRole>) -> RpcResult<String>;
+
+	/// Args override of an app, or the global one when `app_id` is `None`.
+	#[method(name = "container_args")]
+	fn args(&self, kind: ArgsKind, app_id: Option<u32>) -> RpcResult<Option<Vec<String>>>;
+
+	/// Set the args override of an app, or the global one when `app_id` is `None`. Each entry
+	/// is passed as one argument, as it is.
+	#[method(name = "container_setArgs")]
+	fn set_args(&self, kind: ArgsKind, app_id: Option<u32>, value: Vec<String>) -> RpcResult<()>;
+
+	/// Clear the args override of an app, or the global one when `app_id` is `None`.
+	#[method(name = "container_clearArgs")]
+	fn clear_args(&self, kind: ArgsKind, app_id: Option<u32>) -> RpcResult<()>;
+
+	/// Args an app is started with, by default the app of the local sequencer.
+	#[method(name = "container_effectiveArgs")]
+	async fn effective_args(&self, app_id: Option<u32>) -> RpcResult<EffectiveArgs>;
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The container task rejected or failed the command.
+	CommandFailed,
+	/// The node has no offchain storage.
+	NoOffchainStorage,
+	/// The args value is malformed.
+	InvalidArgs,
+	/// The node runs no such app, or no app id given and the sequencer runs no app.
+	NoApp,
+	/// The logs of the instance could not be read.
+	LogsUnavailable,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::CommandFailed => 1,
+			Error::NoOffchainStorage => 2,
+			Error::InvalidArgs => 3,
+			Error::NoApp => 4,
+			Error::LogsUnavailable => 5,
+		}
+	}
+}
+
+/// Provides the `container_*` RPC methods.
+pub struct Container<S> {
+	handle: ContainerHandle,
+	storage: Option<S>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<S: OffchainStorage> Container<S> {
+	/// Create new `Container` with the given handle of the container task.
+	pub fn new(handle: ContainerHandle, storage: Option<S>, deny_unsafe: DenyUnsafe) -> Self {
+		Self { handle, storage, deny_unsafe }
+	}
+
+	async fn send(&self, app: Option<AppRole>, command: ContainerCommand) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		self.handle.send(app.unwrap_or_default(), command).await.map_err(
+			|error| -> ErrorObjectOwned {
+				ErrorObject::owned(
+					Error::CommandFailed.into(),
+					"Container command failed.",
+					Some(error),
+				)
+			},
+		)
+	}
+
+	fn storage(&self) -> RpcResult<S> {
+		self.deny_unsafe.check_if_safe()?;
+		self.storage.clone().ok_or_else(|| {
+			ErrorObject::owned(
+				Error::NoOffchainStorage.into(),
+				"Offchain storage is not available.",
+				None::<()>,
+			)
+		})
+	}
+}
+
+#[async_trait]
+impl<S: OffchainStorage + 'static> ContainerApiServer for Container<S> {
+	async fn apps(&self) -> RpcResult<Vec<ContainerStatus>> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.handle.statuses().await)
+	}
+
+	async fn status(&self, app: Option<AppRole>) -> RpcResult<ContainerStatus> {
+		self.deny_unsafe.check_if_safe()?;
+		self.handle.status(app.unwrap_or_default()).await.ok_or_else(|| {
+			ErrorObject::owned(Error::NoApp.into(), "The node runs no such app.", None::<()>)
+		})
+	}
+
+	async fn restart(&self, app: Option<AppRole>) -> RpcResult<()> {
+		self.send(app, ContainerCommand::Restart).await
+	}
+
+	async fn redownload(&self, app: Option<AppRole>) -> RpcResult<()> {
+		self.send(app, ContainerCommand::Redownload).await
+	}
+
+	async fn stop(&self, app: Option<AppRole>) -> RpcResult<()> {
+		self.send(app, ContainerCommand::Stop).await
+	}
+
+	async fn logs(&self, tail: Option<u32>, app: Option<AppRole>) -> RpcResult<String> {
+		self.deny_unsafe.check_if_safe()?;
+		self.handle
+			.logs(app.unwrap_or_default(), tail.unwrap_or(100) as usize)
+			.await
+			.map_err(|error| {
+				ErrorObject::owned(
+					Error::LogsUnavailable.into(),
+					"Logs are not available.",
+					Some(error),
+				)
+			})
+	}
+
+	fn args(&self, kind: ArgsKind, app_id: Option<u32>) -> RpcResult<Option<Vec<String>>> {
+		let storage = self.storage()?;
+		Ok(storage
+			.get(STORAGE_PREFIX, &kind.storage_key(app_id))
+			.map(|value| decode_args(&value)))
+	}
+
+	fn set_args(&self, kind: ArgsKind, app_id: Option<u32>, value: Vec<String>) -> RpcResult<()> {
+		let mut storage = self.storage()?;
+		validate_args(kind, &value).map_err(|error| -> ErrorObjectOwned {
+			ErrorObject::owned(Error::InvalidArgs.into(), "Invalid args.", Some(error))
+		})?;
+		storage.set(STORAGE_PREFIX, &kind.storage_key(app_id), &encode_args(&value));
+		Ok(())
+	}
+
+	fn clear_args(&self, kind: ArgsKind, app_id: Option<u32>) -> RpcResult<()> {
+		let mut storage = self.storage()?;
+		storage.remove(STORAGE_PREFIX, &kind.storage_key(app_id));
+		Ok(())
+	}
+
+	async fn effective_args(&self, app_id: Option<u32>) -> RpcResult<EffectiveArgs> {
+		let storage = self.storage()?;
+		let app_id = match app_id {
+			Some(app_id) => app_id,
+			None => {
+				let status = self.handle.status(AppRole::Sequencer).await;
+				status.and_then(|status| status.app_id).ok_or_else(|| {
+					ErrorObject::owned(
+						Error::NoApp.into(),
+						"The sequencer runs no app.",
+						None::<()>,
+					)
+				})?
+			},
+		};
+		let args = |kind| effective_args(&storage, kind, app_id);
+		Ok(EffectiveArgs {
+			app_id,
+			run: args(ArgsKind::Run),
+			sync: args(ArgsKind::Sync),
+			option: args(ArgsKind::Option),
+		})
+	}
+}
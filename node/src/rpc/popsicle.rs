@@ -0,0 +1,480 @@
+//! `popsicle_*` RPC methods over the container, grouping and staking runtime apis.
+
+use std::sync::Arc;
+
+use frame_support::storage::StoragePrefixedMap;
+use futures::{future, stream, StreamExt};
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::{error::ErrorObject, ErrorObjectOwned},
+	PendingSubscriptionSink,
+};
+use popsicle_runtime::{AccountId, Balance, Runtime};
+use primitives_container::{
+	AppSummary, ContainerApiError, ContainerRuntimeApi, DownloadInfo, ProcessorSummary,
+	RoundSchedule,
+};
+use primitives_sequencer_staking::{
+	CandidateSummary, DelegatorSummary, SequencerStakingApi, StakingRound,
+};
+use sc_client_api::BlockchainEvents;
+use sc_rpc::{
+	utils::{pipe_from_stream, spawn_subscription_task},
+	SubscriptionTaskExecutor,
+};
+use serde::{Deserialize, Serialize};
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_keystore::KeystorePtr;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+/// Staking round and the next sequencer grouping round.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rounds<BlockNumber> {
+	/// Current staking round
+	pub staking: StakingRound<BlockNumber>,
+	/// Round the current grouping applies from
+	pub grouping: RoundSchedule<BlockNumber>,
+}
+
+/// Group and app the local sequencer is assigned to.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Assignment<BlockNumber> {
+	/// Sequencer account of the local node
+	pub sequencer: AccountId,
+	/// `None` while the sequencer is in no group
+	pub group: Option<u32>,
+	/// App allocated to the group
+	pub app: Option<AppSummary<AccountId>>,
+	/// Round the assignment applies from
+	pub round: RoundSchedule<BlockNumber>,
+}
+
+#[rpc(server)]
+pub trait PopsicleApi<BlockHash, BlockNumber> {
+	/// Group of a sequencer.
+	#[method(name = "popsicle_groupOf")]
+	fn group_of(&self, account: AccountId, at: Option<BlockHash>) -> RpcResult<u32>;
+
+	/// Sequencers of a group.
+	#[method(name = "popsicle_groupMembers")]
+	fn group_members(&self, group_id: u32, at: Option<BlockHash>) -> RpcResult<Vec<AccountId>>;
+
+	/// App allocated to a group.
+	#[method(name = "popsicle_groupApp")]
+	fn group_app(
+		&self,
+		group_id: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<AppSummary<AccountId>>>;
+
+	/// Processors hosting a group and their endpoints.
+	#[method(name = "popsicle_groupProcessors")]
+	fn group_processors(
+		&self,
+		group_id: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<ProcessorSummary<AccountId>>>;
+
+	/// All registered apps.
+	#[method(name = "popsicle_apps")]
+	fn apps(&self, at: Option<BlockHash>) -> RpcResult<Vec<AppSummary<AccountId>>>;
+
+	/// What the sequencer downloads and runs for its group.
+	#[method(name = "popsicle_downloadInfo")]
+	fn download_info(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<DownloadInfo>>;
+
+	/// Current staking round and next grouping round.
+	#[method(name = "popsicle_rounds")]
+	fn rounds(&self, at: Option<BlockHash>) -> RpcResult<Rounds<BlockNumber>>;
+
+	/// Staking summary of all candidates.
+	#[method(name = "popsicle_candidates")]
+	fn candidates(
+		&self,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<CandidateSummary<AccountId, Balance>>>;
+
+	/// Staking summary of a candidate.
+	#[method(name = "popsicle_candidate")]
+	fn candidate(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<CandidateSummary<AccountId, Balance>>>;
+
+	/// Staking summary of a delegator.
+	#[method(name = "popsicle_delegator")]
+	fn delegator(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<DelegatorSummary<AccountId, Balance>>>;
+
+	/// Assignment of the local sequencer, sent now and whenever the grouping or the app of its
+	/// group changes.
+	#[subscription(
+		name = "popsicle_subscribeAssignment" => "popsicle_assignment",
+		unsubscribe = "popsicle_unsubscribeAssignment",
+		item = Assignment<BlockNumber>
+	)]
+	fn subscribe_assignment(&self);
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// The runtime api is missing or older than the method needs.
+	UnsupportedRuntime,
+	/// The app does not exist.
+	AppNotExist,
+	/// The group does not exist.
+	GroupNotExist,
+	/// The account is in no group.
+	AccountNotInGroup,
+	/// The keystore holds no sequencer key.
+	NoSequencerKey,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::UnsupportedRuntime => 2,
+			Error::AppNotExist => 3,
+			Error::GroupNotExist => 4,
+			Error::AccountNotInGroup => 5,
+			Error::NoSequencerKey => 6,
+		}
+	}
+}
+
+fn runtime_error(error: impl ToString) -> ErrorObjectOwned {
+	ErrorObject::owned(
+		Error::RuntimeError.into(),
+		"Runtime api call failed.",
+		Some(error.to_string()),
+	)
+}
+
+fn container_error(error: ContainerApiError) -> ErrorObjectOwned {
+	let (code, message) = match error {
+		ContainerApiError::AppNotExist => (Error::AppNotExist, "App does not exist."),
+		ContainerApiError::GroupNotExist => (Error::GroupNotExist, "Group does not exist."),
+		ContainerApiError::AccountNotInGroup =>
+			(Error::AccountNotInGroup, "Account is not in any group."),
+	};
+	ErrorObject::owned(code.into(), message, None::<()>)
+}
+
+/// Provides the `popsicle_*` RPC methods.
+pub struct Popsicle<C, Block> {
+	client: Arc<C>,
+	keystore: KeystorePtr,
+	executor: SubscriptionTaskExecutor,
+	_marker: std::marker::PhantomData<Block>,
+}
+
+impl<C, Block> Popsicle<C, Block> {
+	/// Creates a new instance of the Popsicle Rpc helper.
+	pub fn new(client: Arc<C>, keystore: KeystorePtr, executor: SubscriptionTaskExecutor) -> Self {
+		Self { client, keystore, executor, _marker: Default::default() }
+	}
+}
+
+impl<C, Block> Popsicle<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: ContainerRuntimeApi<Block, AccountId>,
+{
+	fn at_or_best(&self, at: Option<Block::Hash>) -> Block::Hash {
+		at.unwrap_or_else(|| self.client.info().best_hash)
+	}
+
+	// Version of the container api at the block, methods newer than the runtime are missing.
+	fn ensure_container_api(&self, at: Block::Hash, required: u32) -> RpcResult<u32> {
+		let version = self
+			.client
+			.runtime_api()
+			.api_version::<dyn ContainerRuntimeApi<Block, AccountId>>(at)
+			.map_err(runtime_error)?;
+
+		match version {
+			Some(version) if version >= required => Ok(version),
+			_ => Err(unsupported_runtime("ContainerRuntimeApi", required, version)),
+		}
+	}
+
+	fn ensure_staking_api(&self, at: Block::Hash) -> RpcResult<()>
+	where
+		C::Api: SequencerStakingApi<Block, AccountId, Balance>,
+	{
+		let version = self
+			.client
+			.runtime_api()
+			.api_version::<dyn SequencerStakingApi<Block, AccountId, Balance>>(at)
+			.map_err(runtime_error)?;
+
+		match version {
+			Some(_) => Ok(()),
+			None => Err(unsupported_runtime("SequencerStakingApi", 1, version)),
+		}
+	}
+}
+
+fn unsupported_runtime(api: &str, required: u32, version: Option<u32>) -> ErrorObjectOwned {
+	ErrorObject::owned(
+		Error::UnsupportedRuntime.into(),
+		format!("Runtime does not support {} v{}.", api, required),
+		Some(format!("{:?}", version)),
+	)
+}
+
+// Assignment of `sequencer` at the given block, `None` if the runtime can not tell.
+fn assignment<C, Block>(
+	client: &C,
+	at: Block::Hash,
+	sequencer: &AccountId,
+) -> Option<Assignment<NumberFor<Block>>>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block>,
+	C::Api: ContainerRuntimeApi<Block, AccountId>,
+{
+	let api = client.runtime_api();
+
+	let result = (|| -> Result<Assignment<NumberFor<Block>>, sp_api::ApiError> {
+		let group = api.group_id_of(at, sequencer.clone())?.ok();
+		let app = match group {
+			Some(group) => api.app_of_group(at, group)?.ok().flatten(),
+			None => None,
+		};
+		let round = api.round_schedule(at)?;
+
+		Ok(Assignment { sequencer: sequencer.clone(), group, app, round })
+	})();
+
+	result.map_err(|e| log::warn!("popsicle assignment at {:?}: {:?}", at, e)).ok()
+}
+
+// Prefixes of the storage an assignment is read from: the grouping with its round, written with
+// `SequencerGroupUpdated`, and the apps allocated to the groups.
+fn assignment_storage() -> [Vec<u8>; 3] {
+	[
+		pallet_sequencer_grouping::GroupMembers::<Runtime>::hashed_key().to_vec(),
+		pallet_sequencer_grouping::NextRoundStorage::<Runtime>::hashed_key().to_vec(),
+		pallet_container::GroupAPPMap::<Runtime>::final_prefix().to_vec(),
+	]
+}
+
+impl<C, Block> PopsicleApiServer<Block::Hash, NumberFor<Block>> for Popsicle<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block>
+		+ HeaderBackend<Block>
+		+ BlockchainEvents<Block>
+		+ Send
+		+ Sync
+		+ 'static,
+	C::Api: ContainerRuntimeApi<Block, AccountId>,
+	C::Api: SequencerStakingApi<Block, AccountId, Balance>,
+{
+	fn group_of(&self, account: AccountId, at: Option<Block::Hash>) -> RpcResult<u32> {
+		let at = self.at_or_best(at);
+		self.ensure_container_api(at, 3)?;
+
+		self.client
+			.runtime_api()
+			.group_id_of(at, account)
+			.map_err(runtime_error)?
+			.map_err(container_error)
+	}
+
+	fn group_members(&self, group_id: u32, at: Option<Block::Hash>) -> RpcResult<Vec<AccountId>> {
+		let at = self.at_or_best(at);
+		self.ensure_container_api(at, 3)?;
+
+		self.client
+			.runtime_api()
+			.group_members(at, group_id)
+			.map_err(runtime_error)?
+			.map_err(container_error)
+	}
+
+	fn group_app(
+		&self,
+		group_id: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<AppSummary<AccountId>>> {
+		let at = self.at_or_best(at);
+		self.ensure_container_api(at, 3)?;
+
+		self.client
+			.runtime_api()
+			.app_of_group(at, group_id)
+			.map_err(runtime_error)?
+			.map_err(container_error)
+	}
+
+	fn group_processors(
+		&self,
+		group_id: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<ProcessorSummary<AccountId>>> {
+		let at = self.at_or_best(at);
+		self.ensure_container_api(at, 3)?;
+
+		self.client
+			.runtime_api()
+			.group_processors(at, group_id)
+			.map_err(runtime_error)?
+			.map_err(container_error)
+	}
+
+	fn apps(&self, at: Option<Block::Hash>) -> RpcResult<Vec<AppSummary<AccountId>>> {
+		let at = self.at_or_best(at);
+		self.ensure_container_api(at, 3)?;
+
+		self.client.runtime_api().apps(at).map_err(runtime_error)
+	}
+
+	fn download_info(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<DownloadInfo>> {
+		let at = self.at_or_best(at);
+
+		let version = self.ensure_container_api(at, 2)?;
+
+		let api = self.client.runtime_api();
+
+		// Runtimes from before v5 return the download info in an older layout.
+		if version >= 5 {
+			api.shuld_load(at, account).map_err(runtime_error)
+		} else if version >= 3 {
+			#[allow(deprecated)]
+			let v4 = api.shuld_load_before_version_5(at, account).map_err(runtime_error)?;
+			Ok(v4.map(DownloadInfo::from))
+		} else {
+			#[allow(deprecated)]
+			let legacy = api.shuld_load_before_version_3(at, account).map_err(runtime_error)?;
+			Ok(legacy.map(DownloadInfo::from))
+		}
+	}
+
+	fn rounds(&self, at: Option<Block::Hash>) -> RpcResult<Rounds<NumberFor<Block>>> {
+		let at = self.at_or_best(at);
+		self.ensure_container_api(at, 3)?;
+		self.ensure_staking_api(at)?;
+
+		let api = self.client.runtime_api();
+
+		Ok(Rounds {
+			staking: api.round(at).map_err(runtime_error)?,
+			grouping: api.round_schedule(at).map_err(runtime_error)?,
+		})
+	}
+
+	fn candidates(
+		&self,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<CandidateSummary<AccountId, Balance>>> {
+		let at = self.at_or_best(at);
+		self.ensure_staking_api(at)?;
+
+		self.client.runtime_api().candidates(at).map_err(runtime_error)
+	}
+
+	fn candidate(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<CandidateSummary<AccountId, Balance>>> {
+		let at = self.at_or_best(at);
+		self.ensure_staking_api(at)?;
+
+		self.client.runtime_api().candidate(at, account).map_err(runtime_error)
+	}
+
+	fn delegator(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<DelegatorSummary<AccountId, Balance>>> {
+		let at = self.at_or_best(at);
+		self.ensure_staking_api(at)?;
+
+		self.client.runtime_api().delegator(at, account).map_err(runtime_error)
+	}
+
+	fn subscribe_assignment(&self, pending: PendingSubscriptionSink) {
+		let sequencer: Option<AccountId> = self
+			.keystore
+			.sr25519_public_keys(sp_application_crypto::key_types::AURA)
+			.first()
+			.map(|public| (*public).into());
+
+		let sequencer = match sequencer {
+			Some(sequencer) => sequencer,
+			None => {
+				let error = ErrorObject::owned(
+					Error::NoSequencerKey.into(),
+					"No sequencer key in the keystore.",
+					None::<()>,
+				);
+				spawn_subscription_task(&self.executor, pending.reject(error));
+				return;
+			},
+		};
+
+		let client = self.client.clone();
+
+		if let Err(error) = self.ensure_container_api(client.info().best_hash, 3) {
+			spawn_subscription_task(&self.executor, pending.reject(error));
+			return;
+		}
+
+		// Storage changes are notified for new best blocks, the runtime is only asked again
+		// when the grouping or an allocation changed.
+		let storage_changes = match client.storage_changes_notification_stream(None, None) {
+			Ok(storage_changes) => storage_changes,
+			Err(error) => {
+				spawn_subscription_task(&self.executor, pending.reject(runtime_error(error)));
+				return;
+			},
+		};
+
+		let initial = assignment(&*client, client.info().best_hash, &sequencer);
+
+		let storage = assignment_storage();
+		let mut last = initial.clone();
+		let changes = storage_changes
+			.filter(move |notification| {
+				future::ready(notification.changes.iter().any(|(child, key, _)| {
+					child.is_none() && storage.iter().any(|prefix| key.0.starts_with(prefix))
+				}))
+			})
+			.filter_map(move |notification| {
+				let current = assignment(&*client, notification.block, &sequencer);
+				let changed = current.is_some() &&
+					current.as_ref().map(|current| (&current.group, &current.app)) !=
+						last.as_ref().map(|last| (&last.group, &last.app));
+				if changed {
+					last = current.clone();
+				}
+				future::ready(if changed { current } else { None })
+			});
+
+		let stream = stream::iter(initial).chain(changes);
+
+		spawn_subscription_task(&self.executor, pipe_from_stream(pending, stream.boxed()));
+	}
+}
@@ -22,7 +22,10 @@ use cumulus_primitives_core::{relay_chain::CollatorPair, ParaId};
 use cumulus_relay_chain_interface::{OverseerHandle, RelayChainInterface};
 
 // Substrate Imports
-use crate::container_task::spawn_container_task;
+use crate::{
+	cli::ContainerCli,
+	container_task::{spawn_container_task, ContainerHandle},
+};
 use frame_benchmarking_cli::SUBSTRATE_REFERENCE_HARDWARE;
 use primitives_container::ContainerRuntimeApi;
 use sc_client_api::Backend;
@@ -163,6 +166,7 @@ async fn start_node_impl(
 	collator_options: CollatorOptions,
 	para_id: ParaId,
 	hwbench: Option<sc_sysinfo::HwBench>,
+	container_options: ContainerCli,
 ) -> sc_service::error::Result<(TaskManager, Arc<ParachainClient>)> {
 	let parachain_config = prepare_node_config(parachain_config);
 
@@ -227,15 +231,24 @@ async fn start_node_impl(
 		);
 	}
 
+	let (container, container_commands) = ContainerHandle::new();
+
 	let rpc_builder = {
 		let client = client.clone();
 		let transaction_pool = transaction_pool.clone();
+		let keystore = params.keystore_container.keystore();
+		let container = container.clone();
+		let backend = backend.clone();
 
-		Box::new(move |deny_unsafe, _| {
+		Box::new(move |deny_unsafe, subscription_executor| {
 			let deps = crate::rpc::FullDeps {
 				client: client.clone(),
 				pool: transaction_pool.clone(),
 				deny_unsafe,
+				keystore: keystore.clone(),
+				subscription_executor,
+				container: container.clone(),
+				backend: backend.clone(),
 			};
 
 			crate::rpc::create_full(deps).map_err(Into::into)
@@ -333,8 +346,10 @@ async fn start_node_impl(
 			relay_chain_interface.clone(),
 			&task_manager,
 			params.keystore_container.keystore(),
-			data_path,
 			backend,
+			container,
+			container_commands,
+			container_options.container_config(data_path),
 		)?;
 	}
 
@@ -450,6 +465,15 @@ pub async fn start_parachain_node(
 	collator_options: CollatorOptions,
 	para_id: ParaId,
 	hwbench: Option<sc_sysinfo::HwBench>,
+	container_options: ContainerCli,
 ) -> sc_service::error::Result<(TaskManager, Arc<ParachainClient>)> {
-	start_node_impl(parachain_config, polkadot_config, collator_options, para_id, hwbench).await
+	start_node_impl(
+		parachain_config,
+		polkadot_config,
+		collator_options,
+		para_id,
+		hwbench,
+		container_options,
+	)
+	.await
 }
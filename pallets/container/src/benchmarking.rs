@@ -4,10 +4,34 @@ use super::*;
 
 #[allow(unused)]
 use crate::Pallet as Container;
-use frame_benchmarking::{benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_benchmarking::{
+	account, benchmarks, impl_benchmark_test_suite, whitelisted_caller, BenchmarkError,
+};
 use frame_support::{assert_noop, assert_ok};
 use frame_system::RawOrigin;
+use pallet_sequencer_grouping::{CpuArch, GroupMembers, NextRound, NextRoundStorage};
 use sp_core::H256;
+use sp_runtime::traits::Bounded;
+
+// Caller with enough balance to reserve app deposits.
+fn funded_caller<T: Config>() -> T::AccountId {
+	let caller: T::AccountId = whitelisted_caller();
+	T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value() / 2u32.into());
+	caller
+}
+
+fn max_arg<T: Config>() -> BoundedVec<u8, T::MaxArgLength> {
+	BoundedVec::try_from(vec![b'a'; T::MaxArgLength::get() as usize]).unwrap()
+}
+
+fn max_args<T: Config>() -> AppArgs<T> {
+	BoundedVec::try_from(vec![max_arg::<T>(); T::MaxArgCount::get() as usize]).unwrap()
+}
+
+fn max_envs<T: Config>() -> AppEnvs<T> {
+	BoundedVec::try_from(vec![(max_arg::<T>(), max_arg::<T>()); T::MaxArgCount::get() as usize])
+		.unwrap()
+}
 
 benchmarks! {
 	set_default_url {
@@ -23,18 +47,271 @@ benchmarks! {
 		let project_name = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
 		let file_name = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
 		let file_size = 123;
-		let args = Some(BoundedVec::try_from("--chain dev".as_bytes().to_vec()).unwrap());
+		let args = max_args::<T>();
+		let envs = max_envs::<T>();
 		let log = Some(BoundedVec::try_from("aaaa".as_bytes().to_vec()).unwrap());
-		let caller: T::AccountId = whitelisted_caller();
+		let caller: T::AccountId = funded_caller::<T>();
 	}: _(RawOrigin::Signed(caller),             hash,
 	project_name,
 	file_name,
 	file_size,
 	args,
-	log)
+	envs,
+	log,
+	None,
+	None,
+	Resources { cpu_cores: 8, memory_mb: 16384, disk_mb: 512000, arch: Some(CpuArch::X86_64) })
+	verify {
+		let app = APPInfoMap::<T>::get(1).unwrap();
+		assert_eq!(app.releases[0].app_hash, H256::from([1; 32]));
+	}
+
+	update_app {
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		assert_ok!(Container::<T>::register_app(
+			RawOrigin::Signed(caller.clone()).into(),
+			H256::from([1; 32]),
+			name.clone(),
+			name,
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			Resources::default()
+		));
+		let hash = H256::from([2; 32]);
+		let file_name = BoundedVec::try_from("test_v2".as_bytes().to_vec()).unwrap();
+		let args = max_args::<T>();
+		let envs = max_envs::<T>();
+	}: _(RawOrigin::Signed(caller), 1, hash, file_name, 456, args, envs, None, None, None)
+	verify {
+		let app = APPInfoMap::<T>::get(1).unwrap();
+		assert_eq!(app.releases[1].app_hash, H256::from([2; 32]));
+	}
+
+	deregister_app {
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		assert_ok!(Container::<T>::register_app(
+			RawOrigin::Signed(caller.clone()).into(),
+			H256::from([1; 32]),
+			name.clone(),
+			name,
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			Resources::default()
+		));
+		GroupAPPMap::<T>::insert(0, 1);
+	}: _(RawOrigin::Signed(caller), 1)
+	verify {
+		assert!(APPInfoMap::<T>::get(1).is_none());
+		assert_eq!(GroupAPPMap::<T>::get(0), None);
+	}
+
+	schedule_release {
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		assert_ok!(Container::<T>::register_app(
+			RawOrigin::Signed(caller.clone()).into(),
+			H256::from([1; 32]),
+			name.clone(),
+			name,
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			Resources::default()
+		));
+		let hash = H256::from([2; 32]);
+		let file_name = BoundedVec::try_from("test_v2".as_bytes().to_vec()).unwrap();
+		let start_block = frame_system::Pallet::<T>::block_number() + 10u32.into();
+		NextRoundStorage::<T>::put(NextRound { starting_block: start_block, round_index: 1 });
+	}: _(RawOrigin::Signed(caller), 1, hash, file_name, 456, max_args::<T>(), max_envs::<T>(), None, None, start_block)
+	verify {
+		let app = APPInfoMap::<T>::get(1).unwrap();
+		assert_eq!(app.releases.len(), 2);
+	}
+
+	rollback_release {
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		assert_ok!(Container::<T>::register_app(
+			RawOrigin::Signed(caller.clone()).into(),
+			H256::from([1; 32]),
+			name.clone(),
+			name.clone(),
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			Resources::default()
+		));
+		let start_block = frame_system::Pallet::<T>::block_number() + 10u32.into();
+		NextRoundStorage::<T>::put(NextRound { starting_block: start_block, round_index: 1 });
+		assert_ok!(Container::<T>::schedule_release(
+			RawOrigin::Signed(caller.clone()).into(),
+			1,
+			H256::from([2; 32]),
+			name,
+			456,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			start_block
+		));
+	}: _(RawOrigin::Signed(caller), 1)
 	verify {
 		let app = APPInfoMap::<T>::get(1).unwrap();
-		assert_eq!(app.app_hash, H256::from([1; 32]));
+		assert_eq!(app.releases.len(), 1);
+	}
+
+	confirm_upload {
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		assert_ok!(Container::<T>::register_app(
+			RawOrigin::Signed(caller.clone()).into(),
+			H256::from([1; 32]),
+			name.clone(),
+			name,
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			Resources::default()
+		));
+		assert_ok!(Container::<T>::set_uploader(RawOrigin::Root.into(), caller.clone(), true));
+	}: _(RawOrigin::Signed(caller), 1, H256::from([1; 32]), 123)
+	verify {
+		let app = APPInfoMap::<T>::get(1).unwrap();
+		assert!(app.releases[0].uploaded);
+	}
+
+	set_uploader {
+		let uploader: T::AccountId = whitelisted_caller();
+	}: _(RawOrigin::Root, uploader.clone(), true)
+	verify {
+		assert!(Uploaders::<T>::get(uploader));
+	}
+
+	set_mirrors {
+		let m = T::MaxMirrors::get();
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		assert_ok!(Container::<T>::register_app(
+			RawOrigin::Signed(caller.clone()).into(),
+			H256::from([1; 32]),
+			name.clone(),
+			name,
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			Resources::default()
+		));
+		let mirror: BoundedVec<u8, T::MaxUrlLength> = BoundedVec::try_from(vec![b'a'; T::MaxUrlLength::get() as usize]).unwrap();
+		let mirrors: MirrorUrls<T> = BoundedVec::try_from(vec![mirror; m as usize]).unwrap();
+	}: _(RawOrigin::Signed(caller), 1, Some(H256::from([1; 32])), mirrors)
+	verify {
+		let app = APPInfoMap::<T>::get(1).unwrap();
+		assert_eq!(app.releases[0].mirrors.len(), m as usize);
+	}
+
+	set_app_status {
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		assert_ok!(Container::<T>::register_app(
+			RawOrigin::Signed(caller).into(),
+			H256::from([1; 32]),
+			name.clone(),
+			name,
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			Resources::default()
+		));
+		let origin = T::ApproveOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+	}: _<T::RuntimeOrigin>(origin, 1, AppStatus::Approved)
+	verify {
+		let app = APPInfoMap::<T>::get(1).unwrap();
+		assert_eq!(app.status, AppStatus::Approved);
+	}
+
+	set_readiness_probe {
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		assert_ok!(Container::<T>::register_app(
+			RawOrigin::Signed(caller.clone()).into(),
+			H256::from([1; 32]),
+			name.clone(),
+			name,
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			Resources::default()
+		));
+		let path: BoundedVec<u8, T::MaxUrlLength> = BoundedVec::try_from(vec![b'a'; T::MaxUrlLength::get() as usize]).unwrap();
+		let probe: AppReadinessProbe<T> = ReadinessProbe::Http { port: 9933, path };
+	}: _(RawOrigin::Signed(caller), 1, Some(probe.clone()))
+	verify {
+		let app = APPInfoMap::<T>::get(1).unwrap();
+		assert_eq!(app.readiness, Some(probe));
+	}
+
+	reconcile_groups {
+		let g in 1 .. T::MaxGroupNumber::get();
+		let a in 1 .. T::MaxRuningAPP::get();
+		let caller: T::AccountId = funded_caller::<T>();
+		let name: BoundedVec<u8, T::MaxLengthFileName> = BoundedVec::try_from("test".as_bytes().to_vec()).unwrap();
+		let groups: Vec<_> = (0..g).map(|index| BoundedVec::truncate_from(vec![account("member", index, 0)])).collect();
+		GroupMembers::<T>::put(BoundedVec::truncate_from(groups));
+		// Approved apps no processor can host, so every group goes through every app.
+		for app_id in 1..=a {
+			assert_ok!(Container::<T>::register_app(
+				RawOrigin::Signed(caller.clone()).into(),
+				H256::from([1; 32]),
+				name.clone(),
+				name.clone(),
+				123,
+				BoundedVec::new(),
+				BoundedVec::new(),
+				None,
+				Some(true),
+				None,
+				Resources { cpu_cores: 1, ..Default::default() }
+			));
+			APPInfoMap::<T>::mutate(app_id, |app_info| {
+				app_info.as_mut().unwrap().status = AppStatus::Approved;
+			});
+		}
+		let groups = Container::<T>::get_groups();
+	}: {
+		Container::<T>::reconcile_groups(&groups);
+	}
+	verify {
+		assert_eq!(GroupAPPMap::<T>::iter().count(), 0);
+		assert_eq!(InuseMap::<T>::get().len() as u32, a);
 	}
 }
 
@@ -11,37 +11,106 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+pub mod migrations;
 pub mod weights;
 use codec::{Decode, Encode, MaxEncodedLen};
 use cumulus_primitives_core::relay_chain::Hash;
-use frame_support::pallet_prelude::*;
+use frame_support::{
+	pallet_prelude::*,
+	traits::{Currency, ReservableCurrency},
+};
 use frame_system::pallet_prelude::*;
-use pallet_sequencer_grouping::SequencerGroup;
-use primitives_container::DownloadInfo;
+use pallet_sequencer_grouping::{GroupResources, Resources, SequencerGroup};
+pub use primitives_container::AppStatus;
+use primitives_container::{
+	AppSummary, ContainerApiError, DownloadInfo, ProcessorSummary, ReadinessProbe, RoundSchedule,
+};
 use scale_info::{prelude::vec::Vec, TypeInfo};
-use sp_runtime::BoundedVec;
+use sp_runtime::{
+	traits::{Saturating, Zero},
+	BoundedVec,
+};
 use sp_std::vec;
 pub use weights::*;
 
-#[derive(Encode, Decode, Default, Clone, TypeInfo, MaxEncodedLen, Debug)]
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+// Command line arguments, each one is passed to the app as is.
+pub type AppArgs<T> =
+	BoundedVec<BoundedVec<u8, <T as Config>::MaxArgLength>, <T as Config>::MaxArgCount>;
+
+// Environment variables as (name, value) pairs.
+pub type AppEnvs<T> = BoundedVec<
+	(BoundedVec<u8, <T as Config>::MaxArgLength>, BoundedVec<u8, <T as Config>::MaxArgLength>),
+	<T as Config>::MaxArgCount,
+>;
+
+// Ordered mirror base urls, the file is fetched from `<url>/<file_name>`.
+pub type MirrorUrls<T> =
+	BoundedVec<BoundedVec<u8, <T as Config>::MaxUrlLength>, <T as Config>::MaxMirrors>;
+
+// Readiness check of the app instances, the path or pattern is at most a url long.
+pub type AppReadinessProbe<T> = ReadinessProbe<BoundedVec<u8, <T as Config>::MaxUrlLength>>;
+
+#[derive(Encode, Decode, CloneNoBound, TypeInfo, MaxEncodedLen, DebugNoBound)]
 #[scale_info(skip_type_params(T))]
-pub struct APPInfo<T: Config> {
+pub struct AppRelease<T: Config> {
 	app_hash: Hash,
-	creator: T::AccountId,
-	project_name: BoundedVec<u8, T::MaxLengthFileName>,
 	file_name: BoundedVec<u8, T::MaxLengthFileName>,
+	// Set once the uploaded file is confirmed to match app_hash and size.
 	uploaded: bool,
 	size: u32,
-	args: Option<BoundedVec<u8, T::MaxArgLength>>,
-	log: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+	args: AppArgs<T>,
+	envs: AppEnvs<T>,
 	is_docker_image: Option<bool>,
 	docker_image: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+	// Overrides the app mirrors for this release when not empty.
+	mirrors: MirrorUrls<T>,
+	// The release is active from this block on.
+	start_block: BlockNumberFor<T>,
+}
+
+#[derive(Encode, Decode, Default, Clone, TypeInfo, MaxEncodedLen, Debug)]
+#[scale_info(skip_type_params(T))]
+pub struct APPInfo<T: Config> {
+	creator: T::AccountId,
+	project_name: BoundedVec<u8, T::MaxLengthFileName>,
+	log: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+	mirrors: MirrorUrls<T>,
+	status: AppStatus,
+	// Capacity a processor needs to host the app.
+	requirements: Resources,
+	// Checked before an instance of the app takes over from the previous one.
+	readiness: Option<AppReadinessProbe<T>>,
+	// Reserved from the creator for the bytes the app occupies in storage.
+	deposit: BalanceOf<T>,
+	// Ordered by start_block, the last one is the newest release.
+	releases: BoundedVec<AppRelease<T>, T::MaxReleases>,
+}
+
+impl<T: Config> APPInfo<T> {
+	// Index of the release which is active at the given block.
+	fn release_index_at(&self, block_number: BlockNumberFor<T>) -> Option<usize> {
+		self.releases.iter().rposition(|release| release.start_block <= block_number)
+	}
+
+	// Index of the newest release active at the given block whose upload is confirmed.
+	fn loadable_release_index(&self, block_number: BlockNumberFor<T>) -> Option<usize> {
+		self.releases
+			.iter()
+			.rposition(|release| release.start_block <= block_number && release.uploaded)
+	}
 }
 #[frame_support::pallet]
 pub mod pallet {
 	use super::*;
 
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
@@ -51,6 +120,20 @@ pub mod pallet {
 		/// Type representing the weight of this pallet
 		type WeightInfo: WeightInfo;
 
+		/// Origin allowed to approve, reject and suspend apps
+		type ApproveOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Currency to reserve app deposits in
+		type Currency: ReservableCurrency<Self::AccountId>;
+
+		/// Base deposit for registering an app
+		#[pallet::constant]
+		type AppDepositBase: Get<BalanceOf<Self>>;
+
+		/// Deposit per byte of app info kept in storage
+		#[pallet::constant]
+		type AppDepositPerByte: Get<BalanceOf<Self>>;
+
 		#[pallet::constant]
 		type MaxLengthFileName: Get<u32>;
 
@@ -65,6 +148,17 @@ pub mod pallet {
 
 		#[pallet::constant]
 		type MaxArgLength: Get<u32>;
+
+		/// Maximum number of releases kept for an app
+		#[pallet::constant]
+		type MaxReleases: Get<u32>;
+
+		/// Maximum number of download mirrors of an app or a release
+		#[pallet::constant]
+		type MaxMirrors: Get<u32>;
+
+		/// Length of a round in blocks, releases start with a round
+		type RoundLength: Get<u32>;
 	}
 
 	#[pallet::type_value]
@@ -83,16 +177,22 @@ pub mod pallet {
 	#[pallet::getter(fn appinfo_map)]
 	pub type APPInfoMap<T: Config> = StorageMap<_, Twox64Concat, u32, APPInfo<T>, OptionQuery>;
 
-	// app_id,inuse
+	// app_id,inuse of every registered app, in registration order.
 	#[pallet::storage]
 	#[pallet::getter(fn inuse_map)]
-	pub type InuseMap<T: Config> = StorageValue<_, BoundedVec<bool, T::MaxRuningAPP>, ValueQuery>;
+	pub type InuseMap<T: Config> =
+		StorageValue<_, BoundedVec<(u32, bool), T::MaxRuningAPP>, ValueQuery>;
 
 	// groupid,app_id
 	#[pallet::storage]
 	#[pallet::getter(fn group_app_map)]
 	pub type GroupAPPMap<T: Config> = StorageMap<_, Twox64Concat, u32, u32, OptionQuery>;
 
+	// Accounts trusted to confirm uploads, app creators confirm nothing about their own files.
+	#[pallet::storage]
+	#[pallet::getter(fn uploaders)]
+	pub type Uploaders<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, bool, ValueQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -106,54 +206,81 @@ pub mod pallet {
 		SetDownloadURL {
 			url: BoundedVec<u8, T::MaxUrlLength>,
 		},
+		UpdateApp {
+			appid: u32,
+			file_name: BoundedVec<u8, T::MaxLengthFileName>,
+			hash: Hash,
+			size: u32,
+		},
+		DeregisterApp {
+			appid: u32,
+		},
+		ConfirmUpload {
+			appid: u32,
+			hash: Hash,
+			uploader: T::AccountId,
+		},
+		SetUploader {
+			account: T::AccountId,
+			authorised: bool,
+		},
+		SetMirrors {
+			appid: u32,
+			hash: Option<Hash>,
+			mirrors: MirrorUrls<T>,
+		},
+		AssignApp {
+			group: u32,
+			appid: u32,
+		},
+		UnassignApp {
+			group: u32,
+			appid: u32,
+		},
+		ScheduleRelease {
+			appid: u32,
+			file_name: BoundedVec<u8, T::MaxLengthFileName>,
+			hash: Hash,
+			size: u32,
+			start_block: BlockNumberFor<T>,
+		},
+		RollbackRelease {
+			appid: u32,
+			hash: Hash,
+		},
+		SetAppStatus {
+			appid: u32,
+			status: AppStatus,
+		},
+		SetReadinessProbe {
+			appid: u32,
+			probe: Option<AppReadinessProbe<T>>,
+		},
 	}
 
 	#[pallet::error]
 	pub enum Error<T> {
 		AppNotExist,
+		NotAppCreator,
+		InvalidStartBlock,
+		TooManyReleases,
+		NoPreviousRelease,
+		NotUploader,
+		ReleaseNotExist,
+		SizeMismatch,
+		TooManyApps,
+		InvalidEnv,
 	}
 
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
-		fn on_finalize(_: BlockNumberFor<T>) {
+		fn on_initialize(_: BlockNumberFor<T>) -> Weight {
 			let groups = Self::get_groups();
-			log::info!("groups:{:?}", groups);
-
-			let mut inuse_apps = InuseMap::<T>::get();
-			log::info!("inuse_apps:{:?}", inuse_apps);
-
-			for group in groups.iter() {
-				let app = GroupAPPMap::<T>::get(group);
-				match app {
-					Some(_app_id) => {
-						// TODO:alloced app to group,do nothing??
-						// GroupAPPMap::<T>::mutate(group, |id| *id=Some((index + 1) as u64));
-					},
-					None => {
-						// alloc app to group
-						let alloc_apps = inuse_apps.len();
-
-						let mut index = 0;
+			let apps = InuseMap::<T>::decode_len().unwrap_or_default() as u32;
 
-						while index < alloc_apps {
-							if !inuse_apps[index] {
-								inuse_apps[index] = true;
+			Self::reconcile_groups(&groups);
 
-								InuseMap::<T>::mutate(|inuses| inuses[index] = true);
-
-								GroupAPPMap::<T>::insert(group, (index + 1) as u32);
-
-								break;
-							}
-							index += 1;
-						}
-						if index == alloc_apps {
-							// all is inuse, can not alloc,do nothing,just wait
-						}
-					},
-				}
-			}
-			log::info!("inuse_apps:{:?}", inuse_apps);
+			<T as pallet::Config>::WeightInfo::reconcile_groups(groups.len() as u32, apps)
 		}
 	}
 
@@ -167,37 +294,63 @@ pub mod pallet {
 			project_name: BoundedVec<u8, T::MaxLengthFileName>,
 			file_name: BoundedVec<u8, T::MaxLengthFileName>,
 			size: u32,
-			args: Option<BoundedVec<u8, T::MaxArgLength>>,
+			args: AppArgs<T>,
+			envs: AppEnvs<T>,
 			log: Option<BoundedVec<u8, T::MaxLengthFileName>>,
 			is_docker_image: Option<bool>,
 			docker_image: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+			requirements: Resources,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
 
+			Self::check_envs(&envs)?;
+
+			// App ids are never reused, only the slots of deregistered apps are.
 			let old_application_id = NextApplicationID::<T>::get();
 
-			APPInfoMap::<T>::insert(
-				old_application_id,
-				APPInfo {
-					app_hash,
-					creator: who,
-					project_name: project_name.clone(),
-					file_name: file_name.clone(),
-					uploaded: false,
-					size,
-					args,
-					log,
-					is_docker_image,
-					docker_image,
-				},
-			);
+			let mut inuse_apps = InuseMap::<T>::get();
+			inuse_apps
+				.try_push((old_application_id, false))
+				.map_err(|_| Error::<T>::TooManyApps)?;
+
+			InuseMap::<T>::put(inuse_apps);
 
 			NextApplicationID::<T>::set(old_application_id + 1);
 
-			let mut inuse_apps = InuseMap::<T>::get();
-			inuse_apps.try_push(false).map_err(|_| Error::<T>::AppNotExist)?;
+			// Docker images are pulled from their registry, there is nothing to upload.
+			let uploaded = is_docker_image == Some(true);
 
-			InuseMap::<T>::put(inuse_apps);
+			let release = AppRelease {
+				app_hash,
+				file_name: file_name.clone(),
+				uploaded,
+				size,
+				args,
+				envs,
+				is_docker_image,
+				docker_image,
+				mirrors: BoundedVec::new(),
+				start_block: <frame_system::Pallet<T>>::block_number(),
+			};
+
+			let mut releases = BoundedVec::new();
+			releases.try_push(release).map_err(|_| Error::<T>::TooManyReleases)?;
+
+			let mut app_info = APPInfo {
+				creator: who,
+				project_name: project_name.clone(),
+				log,
+				mirrors: BoundedVec::new(),
+				status: AppStatus::Pending,
+				requirements,
+				readiness: None,
+				deposit: Default::default(),
+				releases,
+			};
+
+			Self::update_deposit(&mut app_info)?;
+
+			APPInfoMap::<T>::insert(old_application_id, app_info);
 
 			Pallet::<T>::deposit_event(Event::<T>::ReisterApp {
 				appid: old_application_id,
@@ -223,10 +376,487 @@ pub mod pallet {
 			Pallet::<T>::deposit_event(Event::<T>::SetDownloadURL { url });
 			Ok(())
 		}
+
+		#[pallet::call_index(2)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::update_app())]
+		pub fn update_app(
+			origin: OriginFor<T>,
+			app_id: u32,
+			app_hash: Hash,
+			file_name: BoundedVec<u8, T::MaxLengthFileName>,
+			size: u32,
+			args: AppArgs<T>,
+			envs: AppEnvs<T>,
+			log: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+			is_docker_image: Option<bool>,
+			docker_image: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			Self::check_envs(&envs)?;
+
+			APPInfoMap::<T>::try_mutate(app_id, |app_info| -> DispatchResult {
+				let app_info = app_info.as_mut().ok_or(Error::<T>::AppNotExist)?;
+
+				ensure!(app_info.creator == who, Error::<T>::NotAppCreator);
+
+				let release = AppRelease {
+					app_hash,
+					file_name: file_name.clone(),
+					uploaded: is_docker_image == Some(true),
+					size,
+					args,
+					envs,
+					is_docker_image,
+					docker_image,
+					mirrors: BoundedVec::new(),
+					start_block: Default::default(),
+				};
+
+				let release_block = Self::release_block();
+
+				let newest = app_info.releases.last_mut().ok_or(Error::<T>::AppNotExist)?;
+
+				if newest.start_block > release_block {
+					// No sequencer loads the newest release yet, replace it keeping its start
+					// block.
+					*newest = AppRelease { start_block: newest.start_block, ..release };
+				} else {
+					// Sequencers already load the newest release, switch all of them at the next
+					// round instead.
+					let start_block = Self::next_round_start(release_block)
+						.ok_or(Error::<T>::InvalidStartBlock)?;
+
+					Self::push_release(app_info, AppRelease { start_block, ..release })?;
+				}
+
+				app_info.log = log;
+
+				Self::update_deposit(app_info)
+			})?;
+
+			Pallet::<T>::deposit_event(Event::<T>::UpdateApp {
+				appid: app_id,
+				file_name,
+				hash: app_hash,
+				size,
+			});
+
+			Ok(())
+		}
+
+		#[pallet::call_index(3)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::deregister_app())]
+		pub fn deregister_app(origin: OriginFor<T>, app_id: u32) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let app_info = APPInfoMap::<T>::get(app_id).ok_or(Error::<T>::AppNotExist)?;
+
+			ensure!(app_info.creator == who, Error::<T>::NotAppCreator);
+
+			APPInfoMap::<T>::remove(app_id);
+
+			T::Currency::unreserve(&app_info.creator, app_info.deposit);
+
+			// Release the groups running this app so they can be allocated again.
+			let groups: Vec<u32> = GroupAPPMap::<T>::iter()
+				.filter(|(_, group_app_id)| *group_app_id == app_id)
+				.map(|(group, _)| group)
+				.collect();
+
+			for group in groups {
+				Self::unassign_group(group, app_id);
+			}
+
+			InuseMap::<T>::mutate(|inuses| inuses.retain(|(id, _)| *id != app_id));
+
+			Pallet::<T>::deposit_event(Event::<T>::DeregisterApp { appid: app_id });
+
+			Ok(())
+		}
+
+		#[pallet::call_index(4)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::schedule_release())]
+		pub fn schedule_release(
+			origin: OriginFor<T>,
+			app_id: u32,
+			app_hash: Hash,
+			file_name: BoundedVec<u8, T::MaxLengthFileName>,
+			size: u32,
+			args: AppArgs<T>,
+			envs: AppEnvs<T>,
+			is_docker_image: Option<bool>,
+			docker_image: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+			start_block: BlockNumberFor<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			Self::check_envs(&envs)?;
+
+			let block_number = <frame_system::Pallet<T>>::block_number();
+
+			ensure!(
+				start_block > block_number && Self::is_round_start(start_block),
+				Error::<T>::InvalidStartBlock
+			);
+
+			APPInfoMap::<T>::try_mutate(app_id, |app_info| -> DispatchResult {
+				let app_info = app_info.as_mut().ok_or(Error::<T>::AppNotExist)?;
+
+				ensure!(app_info.creator == who, Error::<T>::NotAppCreator);
+
+				if let Some(last) = app_info.releases.last() {
+					ensure!(start_block > last.start_block, Error::<T>::InvalidStartBlock);
+				}
+
+				Self::push_release(
+					app_info,
+					AppRelease {
+						app_hash,
+						file_name: file_name.clone(),
+						uploaded: is_docker_image == Some(true),
+						size,
+						args,
+						envs,
+						is_docker_image,
+						docker_image,
+						mirrors: BoundedVec::new(),
+						start_block,
+					},
+				)?;
+
+				Self::update_deposit(app_info)
+			})?;
+
+			Pallet::<T>::deposit_event(Event::<T>::ScheduleRelease {
+				appid: app_id,
+				file_name,
+				hash: app_hash,
+				size,
+				start_block,
+			});
+
+			Ok(())
+		}
+
+		#[pallet::call_index(5)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::rollback_release())]
+		pub fn rollback_release(origin: OriginFor<T>, app_id: u32) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let hash =
+				APPInfoMap::<T>::try_mutate(app_id, |app_info| -> Result<Hash, DispatchError> {
+					let app_info = app_info.as_mut().ok_or(Error::<T>::AppNotExist)?;
+
+					ensure!(app_info.creator == who, Error::<T>::NotAppCreator);
+
+					ensure!(app_info.releases.len() > 1, Error::<T>::NoPreviousRelease);
+
+					// Whether the newest release is still scheduled or already active,
+					// the previous one takes over from the next round.
+					app_info.releases.pop();
+
+					let hash =
+						app_info.releases.last().ok_or(Error::<T>::NoPreviousRelease)?.app_hash;
+
+					Self::update_deposit(app_info)?;
+
+					Ok(hash)
+				})?;
+
+			Pallet::<T>::deposit_event(Event::<T>::RollbackRelease { appid: app_id, hash });
+
+			Ok(())
+		}
+
+		/// Attest that the release file at the download urls has the hash and size the release
+		/// declares. The chain can not check the file, so only uploaders trusted by root do, after
+		/// fetching and verifying it.
+		#[pallet::call_index(6)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::confirm_upload())]
+		pub fn confirm_upload(
+			origin: OriginFor<T>,
+			app_id: u32,
+			app_hash: Hash,
+			size: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			APPInfoMap::<T>::try_mutate(app_id, |app_info| -> DispatchResult {
+				let app_info = app_info.as_mut().ok_or(Error::<T>::AppNotExist)?;
+
+				ensure!(Uploaders::<T>::get(&who), Error::<T>::NotUploader);
+
+				let release = app_info
+					.releases
+					.iter_mut()
+					.rev()
+					.find(|release| release.app_hash == app_hash)
+					.ok_or(Error::<T>::ReleaseNotExist)?;
+
+				ensure!(release.size == size, Error::<T>::SizeMismatch);
+
+				release.uploaded = true;
+
+				Ok(())
+			})?;
+
+			Pallet::<T>::deposit_event(Event::<T>::ConfirmUpload {
+				appid: app_id,
+				hash: app_hash,
+				uploader: who,
+			});
+
+			Ok(())
+		}
+
+		#[pallet::call_index(7)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::set_uploader())]
+		pub fn set_uploader(
+			origin: OriginFor<T>,
+			account: T::AccountId,
+			authorised: bool,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+
+			if authorised {
+				Uploaders::<T>::insert(&account, true);
+			} else {
+				Uploaders::<T>::remove(&account);
+			}
+
+			Pallet::<T>::deposit_event(Event::<T>::SetUploader { account, authorised });
+
+			Ok(())
+		}
+
+		// Set the mirrors of the app, or of its release with `app_hash` if given.
+		#[pallet::call_index(8)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::set_mirrors())]
+		pub fn set_mirrors(
+			origin: OriginFor<T>,
+			app_id: u32,
+			app_hash: Option<Hash>,
+			mirrors: MirrorUrls<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			APPInfoMap::<T>::try_mutate(app_id, |app_info| -> DispatchResult {
+				let app_info = app_info.as_mut().ok_or(Error::<T>::AppNotExist)?;
+
+				ensure!(app_info.creator == who, Error::<T>::NotAppCreator);
+
+				match app_hash {
+					Some(app_hash) => {
+						let release = app_info
+							.releases
+							.iter_mut()
+							.rev()
+							.find(|release| release.app_hash == app_hash)
+							.ok_or(Error::<T>::ReleaseNotExist)?;
+
+						release.mirrors = mirrors.clone();
+					},
+					None => app_info.mirrors = mirrors.clone(),
+				}
+
+				Self::update_deposit(app_info)
+			})?;
+
+			Pallet::<T>::deposit_event(Event::<T>::SetMirrors {
+				appid: app_id,
+				hash: app_hash,
+				mirrors,
+			});
+
+			Ok(())
+		}
+
+		// Move the app to another review state, groups running an app that is no longer
+		// approved are released in on_initialize.
+		#[pallet::call_index(9)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::set_app_status())]
+		pub fn set_app_status(
+			origin: OriginFor<T>,
+			app_id: u32,
+			status: AppStatus,
+		) -> DispatchResult {
+			T::ApproveOrigin::ensure_origin(origin)?;
+
+			APPInfoMap::<T>::try_mutate(app_id, |app_info| -> DispatchResult {
+				let app_info = app_info.as_mut().ok_or(Error::<T>::AppNotExist)?;
+
+				app_info.status = status;
+
+				Ok(())
+			})?;
+
+			Pallet::<T>::deposit_event(Event::<T>::SetAppStatus { appid: app_id, status });
+
+			Ok(())
+		}
+
+		// Set how nodes check that an instance of the app is ready, or clear it. Ports the node
+		// reserves for the app take the `*InRange` probes, see `ReadinessProbe`.
+		#[pallet::call_index(10)]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::set_readiness_probe())]
+		pub fn set_readiness_probe(
+			origin: OriginFor<T>,
+			app_id: u32,
+			probe: Option<AppReadinessProbe<T>>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			APPInfoMap::<T>::try_mutate(app_id, |app_info| -> DispatchResult {
+				let app_info = app_info.as_mut().ok_or(Error::<T>::AppNotExist)?;
+
+				ensure!(app_info.creator == who, Error::<T>::NotAppCreator);
+
+				app_info.readiness = probe.clone();
+
+				Self::update_deposit(app_info)
+			})?;
+
+			Pallet::<T>::deposit_event(Event::<T>::SetReadinessProbe { appid: app_id, probe });
+
+			Ok(())
+		}
 	}
 }
 
 impl<T: Config> Pallet<T> {
+	// Deposit for the bytes the app occupies in storage.
+	fn app_deposit(app_info: &APPInfo<T>) -> BalanceOf<T> {
+		let bytes = app_info.encoded_size() as u32;
+
+		T::AppDepositBase::get()
+			.saturating_add(T::AppDepositPerByte::get().saturating_mul(bytes.into()))
+	}
+
+	// Reserve the missing deposit or refund the excess after the app info changed.
+	// Append a release, dropping the ones before the active release and its predecessor.
+	fn push_release(app_info: &mut APPInfo<T>, release: AppRelease<T>) -> DispatchResult {
+		let block_number = <frame_system::Pallet<T>>::block_number();
+
+		// Drop outdated releases, keep the active one and its predecessor for rollback.
+		if let Some(active) = app_info.release_index_at(block_number) {
+			for _ in 0..active.saturating_sub(1) {
+				app_info.releases.remove(0);
+			}
+		}
+
+		app_info.releases.try_push(release).map_err(|_| Error::<T>::TooManyReleases)?;
+
+		Ok(())
+	}
+
+	fn update_deposit(app_info: &mut APPInfo<T>) -> DispatchResult {
+		let deposit = Self::app_deposit(app_info);
+
+		if deposit > app_info.deposit {
+			T::Currency::reserve(&app_info.creator, deposit.saturating_sub(app_info.deposit))?;
+		} else {
+			T::Currency::unreserve(&app_info.creator, app_info.deposit.saturating_sub(deposit));
+		}
+
+		app_info.deposit = deposit;
+
+		Ok(())
+	}
+
+	// Environment variable names must be non empty and can not contain '=' or NUL.
+	fn check_envs(envs: &AppEnvs<T>) -> DispatchResult {
+		for (name, value) in envs.iter() {
+			ensure!(
+				!name.is_empty() && !name.iter().any(|c| *c == b'=' || *c == 0),
+				Error::<T>::InvalidEnv
+			);
+			ensure!(!value.contains(&0), Error::<T>::InvalidEnv);
+		}
+
+		Ok(())
+	}
+
+	// Release stale assignments and give every idle group the first free app it can host.
+	pub(crate) fn reconcile_groups(groups: &[u32]) {
+		// Release the assignments of groups that no longer exist and of apps which are
+		// removed, no longer approved or do not fit the group's processors anymore.
+		let stale: Vec<(u32, u32)> = GroupAPPMap::<T>::iter()
+			.filter(|(group, app_id)| {
+				!groups.contains(group) ||
+					!APPInfoMap::<T>::get(app_id).map_or(false, |app_info| {
+						app_info.status == AppStatus::Approved &&
+							Self::group_can_host(*group, &app_info.requirements)
+					})
+			})
+			.collect();
+
+		for (group, app_id) in stale {
+			Self::unassign_group(group, app_id);
+		}
+
+		// Rebuild the inuse flags from the remaining assignments.
+		let assigned: Vec<u32> = GroupAPPMap::<T>::iter_values().collect();
+
+		let mut inuse_apps = InuseMap::<T>::get();
+		for (app_id, inuse) in inuse_apps.iter_mut() {
+			*inuse = assigned.contains(app_id);
+		}
+
+		let release_block = Self::release_block();
+
+		// Requirements of the approved and uploaded apps, read once for all groups.
+		let ready: Vec<Option<Resources>> = inuse_apps
+			.iter()
+			.map(|(app_id, _)| {
+				APPInfoMap::<T>::get(app_id)
+					.filter(|app_info| {
+						app_info.status == AppStatus::Approved &&
+							app_info.loadable_release_index(release_block).is_some()
+					})
+					.map(|app_info| app_info.requirements)
+			})
+			.collect();
+
+		for group in groups {
+			if GroupAPPMap::<T>::contains_key(group) {
+				continue;
+			}
+
+			// Groups no free app fits wait for one.
+			let free = (0..inuse_apps.len()).find(|index| {
+				!inuse_apps[*index].1 &&
+					ready[*index]
+						.as_ref()
+						.is_some_and(|requirements| Self::group_can_host(*group, requirements))
+			});
+
+			if let Some(index) = free {
+				let app_id = inuse_apps[index].0;
+				inuse_apps[index].1 = true;
+
+				GroupAPPMap::<T>::insert(group, app_id);
+
+				Pallet::<T>::deposit_event(Event::<T>::AssignApp { group: *group, appid: app_id });
+			}
+		}
+
+		InuseMap::<T>::put(inuse_apps);
+	}
+
+	// Remove the group's assignment and free the app for other groups.
+	fn unassign_group(group: u32, app_id: u32) {
+		GroupAPPMap::<T>::remove(group);
+
+		InuseMap::<T>::mutate(|inuses| {
+			if let Some((_, inuse)) = inuses.iter_mut().find(|(id, _)| *id == app_id) {
+				*inuse = false;
+			}
+		});
+
+		Pallet::<T>::deposit_event(Event::<T>::UnassignApp { group, appid: app_id });
+	}
+
 	// Obtain application information corresponding to the group.
 	// If no group has been assigned or there are no available apps in the group, return None
 	pub fn shuld_load(author: T::AccountId) -> Option<DownloadInfo> {
@@ -234,37 +864,142 @@ impl<T: Config> Pallet<T> {
 		//Get the group ID of the sequencer, error when got 0xFFFFFFFF
 		let group_id = Self::get_group_id(author);
 
+		Self::download_info(group_id)
+	}
+
+	// Apps of all groups the processor is assigned to, one per group with a loadable app.
+	pub fn processor_apps(processor: T::AccountId) -> Vec<DownloadInfo> {
+		<pallet_sequencer_grouping::Pallet<T>>::get_group_ids(processor)
+			.into_iter()
+			.filter_map(Self::download_info)
+			.collect()
+	}
+
+	// Release of the group's app to load, None if the group has no loadable app.
+	fn download_info(group_id: u32) -> Option<DownloadInfo> {
 		let app_id = GroupAPPMap::<T>::get(group_id)?;
 
 		let app_info = APPInfoMap::<T>::get(app_id).ok_or(Error::<T>::AppNotExist).ok()?;
 
-		let url = DefaultUrl::<T>::get()?;
+		if app_info.status != AppStatus::Approved {
+			return None;
+		}
 
-		let args = app_info.args.and_then(|log| Some(log.as_slice().to_vec()));
+		let index = app_info.loadable_release_index(Self::release_block())?;
 
 		let log = app_info.log.and_then(|log| Some(log.as_slice().to_vec()));
 
+		let readiness = app_info.readiness.map(|probe| match probe {
+			ReadinessProbe::Http { port, path } =>
+				ReadinessProbe::Http { port, path: path.into_inner() },
+			ReadinessProbe::Tcp { port } => ReadinessProbe::Tcp { port },
+			ReadinessProbe::Log { pattern } =>
+				ReadinessProbe::Log { pattern: pattern.into_inner() },
+			ReadinessProbe::HttpInRange { offset, path } =>
+				ReadinessProbe::HttpInRange { offset, path: path.into_inner() },
+			ReadinessProbe::TcpInRange { offset } => ReadinessProbe::TcpInRange { offset },
+		});
+
+		let release = app_info.releases.into_inner().swap_remove(index);
+
+		// Release mirrors first, then the app mirrors, DefaultUrl is the last resort.
+		let mut mirrors: Vec<Vec<u8>> =
+			if release.mirrors.is_empty() { app_info.mirrors } else { release.mirrors }
+				.into_iter()
+				.map(|mirror| mirror.into_inner())
+				.collect();
+
+		if let Some(default_url) = DefaultUrl::<T>::get() {
+			mirrors.push(default_url.into_inner());
+		}
+
+		let args = release.args.into_iter().map(|arg| arg.into_inner()).collect();
+
+		let envs = release
+			.envs
+			.into_iter()
+			.map(|(name, value)| (name.into_inner(), value.into_inner()))
+			.collect();
+
 		let is_docker_image =
-			if let Some(is_docker) = app_info.is_docker_image { is_docker } else { false };
+			if let Some(is_docker) = release.is_docker_image { is_docker } else { false };
 
-		let docker_image = app_info
+		let docker_image = release
 			.docker_image
 			.and_then(|docker_image| Some(docker_image.as_slice().to_vec()));
 
+		// A binary without any source can not be loaded, docker images come from their registry.
+		let url = match mirrors.first() {
+			Some(url) => url.clone(),
+			None if is_docker_image => Vec::new(),
+			None => return None,
+		};
+
 		Some(DownloadInfo {
 			app_id,
-			app_hash: app_info.app_hash,
-			file_name: app_info.file_name.into(),
-			size: app_info.size,
+			app_hash: release.app_hash,
+			file_name: release.file_name.into(),
+			size: release.size,
 			group: group_id,
-			url: url.into(),
+			url,
 			args,
+			envs,
 			log,
 			is_docker_image,
 			docker_image,
+			mirrors,
+			readiness,
 		})
 	}
 
+	// The block whose release the sequencers should load: the starting block of the next round,
+	// so every sequencer switches to a new release at the same block.
+	pub fn release_block() -> BlockNumberFor<T> {
+		let next_round = <pallet_sequencer_grouping::Pallet<T>>::next_round();
+
+		let block_number = <frame_system::Pallet<T>>::block_number();
+
+		next_round.starting_block.max(block_number)
+	}
+
+	// First round start after the block, None if rounds have no length.
+	fn next_round_start(block_number: BlockNumberFor<T>) -> Option<BlockNumberFor<T>> {
+		let next_round = <pallet_sequencer_grouping::Pallet<T>>::next_round();
+
+		if block_number < next_round.starting_block {
+			return Some(next_round.starting_block);
+		}
+
+		let round_length: BlockNumberFor<T> = T::RoundLength::get().into();
+
+		if round_length.is_zero() {
+			return None;
+		}
+
+		let offset = block_number - next_round.starting_block;
+
+		Some(block_number + round_length - offset % round_length)
+	}
+
+	// Whether a round starts at the block: the next round or one of the rounds after it.
+	fn is_round_start(block_number: BlockNumberFor<T>) -> bool {
+		let next_round = <pallet_sequencer_grouping::Pallet<T>>::next_round();
+
+		if block_number < next_round.starting_block {
+			return false;
+		}
+
+		let offset = block_number - next_round.starting_block;
+
+		let round_length: BlockNumberFor<T> = T::RoundLength::get().into();
+
+		if round_length.is_zero() {
+			offset.is_zero()
+		} else {
+			(offset % round_length).is_zero()
+		}
+	}
+
 	pub fn should_run() -> bool {
 		let next_round = <pallet_sequencer_grouping::Pallet<T>>::next_round();
 
@@ -290,4 +1025,103 @@ impl<T: Config> Pallet<T> {
 	pub fn get_groups() -> Vec<u32> {
 		<pallet_sequencer_grouping::Pallet<T>>::all_group_ids()
 	}
+
+	pub fn group_id_of(author: T::AccountId) -> Result<u32, ContainerApiError> {
+		<pallet_sequencer_grouping::Pallet<T>>::account_in_group(author)
+			.map_err(|_| ContainerApiError::AccountNotInGroup)
+	}
+
+	// All registered apps ordered by app id.
+	pub fn apps() -> Vec<AppSummary<T::AccountId>> {
+		let mut apps: Vec<AppSummary<T::AccountId>> = APPInfoMap::<T>::iter()
+			.map(|(app_id, app_info)| Self::app_summary(app_id, app_info))
+			.collect();
+
+		apps.sort_by_key(|app| app.app_id);
+
+		apps
+	}
+
+	pub fn app_of_group(
+		group_id: u32,
+	) -> Result<Option<AppSummary<T::AccountId>>, ContainerApiError> {
+		Self::ensure_group(group_id)?;
+
+		match GroupAPPMap::<T>::get(group_id) {
+			Some(app_id) => {
+				let app_info =
+					APPInfoMap::<T>::get(app_id).ok_or(ContainerApiError::AppNotExist)?;
+
+				Ok(Some(Self::app_summary(app_id, app_info)))
+			},
+			None => Ok(None),
+		}
+	}
+
+	pub fn group_processors(
+		group_id: u32,
+	) -> Result<Vec<ProcessorSummary<T::AccountId>>, ContainerApiError> {
+		Self::ensure_group(group_id)?;
+
+		Ok(<pallet_sequencer_grouping::Pallet<T>>::processors_of_group(group_id)
+			.into_iter()
+			.map(|(account, ip_address, groups)| ProcessorSummary { account, ip_address, groups })
+			.collect())
+	}
+
+	pub fn group_members(group_id: u32) -> Result<Vec<T::AccountId>, ContainerApiError> {
+		<pallet_sequencer_grouping::Pallet<T>>::group_members_of(group_id)
+			.ok_or(ContainerApiError::GroupNotExist)
+	}
+
+	pub fn round_schedule() -> RoundSchedule<BlockNumberFor<T>> {
+		let next_round = <pallet_sequencer_grouping::Pallet<T>>::next_round();
+
+		RoundSchedule {
+			round_index: next_round.round_index,
+			starting_block: next_round.starting_block,
+			release_block: Self::release_block(),
+		}
+	}
+
+	fn ensure_group(group_id: u32) -> Result<(), ContainerApiError> {
+		if Self::get_groups().contains(&group_id) {
+			Ok(())
+		} else {
+			Err(ContainerApiError::GroupNotExist)
+		}
+	}
+
+	fn app_summary(app_id: u32, app_info: APPInfo<T>) -> AppSummary<T::AccountId> {
+		let release = app_info
+			.loadable_release_index(Self::release_block())
+			.and_then(|index| app_info.releases.get(index));
+
+		AppSummary {
+			app_id,
+			creator: app_info.creator.clone(),
+			project_name: app_info.project_name.to_vec(),
+			status: app_info.status,
+			app_hash: release.map(|release| release.app_hash),
+			file_name: release.map(|release| release.file_name.to_vec()),
+			is_docker_image: release.map_or(false, |release| release.is_docker_image == Some(true)),
+			groups: GroupAPPMap::<T>::iter()
+				.filter(|(_, group_app_id)| *group_app_id == app_id)
+				.map(|(group, _)| group)
+				.collect(),
+		}
+	}
+
+	fn group_can_host(group: u32, requirements: &Resources) -> bool {
+		<pallet_sequencer_grouping::Pallet<T>>::group_can_host(group, requirements)
+	}
+}
+
+impl<T: Config> GroupResources for Pallet<T> {
+	fn group_requirement(group_id: u32) -> Resources {
+		GroupAPPMap::<T>::get(group_id)
+			.and_then(APPInfoMap::<T>::get)
+			.map(|app_info| app_info.requirements)
+			.unwrap_or_default()
+	}
 }
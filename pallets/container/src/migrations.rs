@@ -0,0 +1,119 @@
+//! Storage migrations of the container pallet.
+
+use super::*;
+use frame_support::{migrations::VersionedMigration, traits::OnRuntimeUpgrade};
+
+/// Moves apps registered before releases, approval and deposits existed to the current layout.
+pub type MigrateToV1<T> = VersionedMigration<
+	0,
+	1,
+	v1::VersionUncheckedMigrateToV1<T>,
+	Pallet<T>,
+	<T as frame_system::Config>::DbWeight,
+>;
+
+pub mod v1 {
+	use super::*;
+
+	/// Layout of an app at storage version 0.
+	#[derive(Encode, Decode)]
+	pub struct OldAPPInfo<T: Config> {
+		pub app_hash: Hash,
+		pub creator: T::AccountId,
+		pub project_name: BoundedVec<u8, T::MaxLengthFileName>,
+		pub file_name: BoundedVec<u8, T::MaxLengthFileName>,
+		pub uploaded: bool,
+		pub size: u32,
+		pub args: Option<BoundedVec<u8, T::MaxArgLength>>,
+		pub log: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+		pub is_docker_image: Option<bool>,
+		pub docker_image: Option<BoundedVec<u8, T::MaxLengthFileName>>,
+	}
+
+	impl<T: Config> From<OldAPPInfo<T>> for APPInfo<T> {
+		fn from(old: OldAPPInfo<T>) -> Self {
+			// The arguments used to be a single string split at spaces by the node.
+			let args = old
+				.args
+				.map(|args| {
+					args.split(|byte| *byte == b' ')
+						.filter(|arg| !arg.is_empty())
+						.map(|arg| BoundedVec::truncate_from(arg.to_vec()))
+						.collect::<Vec<_>>()
+				})
+				.unwrap_or_default();
+
+			let release = AppRelease {
+				app_hash: old.app_hash,
+				file_name: old.file_name,
+				uploaded: old.uploaded,
+				size: old.size,
+				args: BoundedVec::truncate_from(args),
+				envs: BoundedVec::new(),
+				is_docker_image: old.is_docker_image,
+				docker_image: old.docker_image,
+				mirrors: BoundedVec::new(),
+				start_block: Zero::zero(),
+			};
+
+			// Apps kept running without approval so far, and nothing was reserved for them.
+			APPInfo {
+				creator: old.creator,
+				project_name: old.project_name,
+				log: old.log,
+				mirrors: BoundedVec::new(),
+				status: AppStatus::Approved,
+				requirements: Resources::default(),
+				readiness: None,
+				deposit: Zero::zero(),
+				releases: BoundedVec::truncate_from(vec![release]),
+			}
+		}
+	}
+
+	pub struct VersionUncheckedMigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for VersionUncheckedMigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let mut apps = 0u64;
+
+			APPInfoMap::<T>::translate::<OldAPPInfo<T>, _>(|_, old| {
+				apps += 1;
+				Some(old.into())
+			});
+
+			// The inuse flags were indexed by app id - 1.
+			let _ = InuseMap::<T>::translate::<BoundedVec<bool, T::MaxRuningAPP>, _>(|old| {
+				let inuses = old
+					.unwrap_or_default()
+					.into_iter()
+					.enumerate()
+					.map(|(index, inuse)| ((index + 1) as u32, inuse))
+					.filter(|(app_id, _)| APPInfoMap::<T>::contains_key(app_id))
+					.collect::<Vec<_>>();
+
+				Some(BoundedVec::truncate_from(inuses))
+			});
+
+			T::DbWeight::get().reads_writes(apps * 2 + 1, apps + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::TryRuntimeError> {
+			Ok((APPInfoMap::<T>::iter_keys().count() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::TryRuntimeError> {
+			let apps = u32::decode(&mut &state[..]).map_err(|_| "invalid pre upgrade state")?;
+
+			ensure!(
+				APPInfoMap::<T>::iter_values().count() as u32 == apps,
+				"apps were lost in the migration"
+			);
+			ensure!(InuseMap::<T>::get().len() as u32 == apps, "inuse slots do not match apps");
+
+			Ok(())
+		}
+	}
+}
@@ -13,6 +13,7 @@ frame_support::construct_runtime!(
 	pub enum Test
 	{
 		System: frame_system::{Pallet, Call, Config<T>, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
 		SequencerGroupingModule: pallet_sequencer_grouping::{Pallet, Call, Storage, Event<T>},
 		ContainerModule: crate::{Pallet, Call, Storage, Event<T>},
 	}
@@ -40,7 +41,7 @@ impl frame_system::Config for Test {
 	type DbWeight = ();
 	type Version = ();
 	type PalletInfo = PalletInfo;
-	type AccountData = ();
+	type AccountData = pallet_balances::AccountData<u64>;
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
 	type SystemWeightInfo = ();
@@ -49,6 +50,26 @@ impl frame_system::Config for Test {
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type MaxLocks = ();
+	type Balance = u64;
+	type RuntimeEvent = RuntimeEvent;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type RuntimeHoldReason = ();
+	type FreezeIdentifier = ();
+	type MaxFreezes = ();
+	type RuntimeFreezeReason = ();
+}
+
 impl pallet_sequencer_grouping::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
@@ -57,6 +78,7 @@ impl pallet_sequencer_grouping::Config for Test {
 	type Randomness = SimpleRandomness<Self>;
 	type MaxLengthIP = ConstU32<15>;
 	type MaxRunningAPP = ConstU32<20>;
+	type GroupResources = ContainerModule;
 }
 
 parameter_types! {
@@ -65,19 +87,35 @@ parameter_types! {
 	pub const MaxUrlLength: u32 = 300;
 	pub const MaxArgCount: u32 = 10;
 	pub const MaxArgLength: u32 = 100;
+	pub const MaxReleases: u32 = 3;
+	pub const MaxMirrors: u32 = 3;
+	pub const RoundLength: u32 = 10;
+	pub const AppDepositBase: u64 = 100;
+	pub const AppDepositPerByte: u64 = 1;
 }
 impl crate::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
+	type ApproveOrigin = frame_system::EnsureRoot<u64>;
+	type Currency = Balances;
+	type AppDepositBase = AppDepositBase;
+	type AppDepositPerByte = AppDepositPerByte;
 	// type AuthorityId = AuraId;
 	type MaxLengthFileName = MaxLengthFileName;
 	type MaxRuningAPP = MaxRuningAPP;
 	type MaxUrlLength = MaxUrlLength;
 	type MaxArgCount = MaxArgCount;
 	type MaxArgLength = MaxArgLength;
+	type MaxReleases = MaxReleases;
+	type MaxMirrors = MaxMirrors;
+	type RoundLength = RoundLength;
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::<Test>::default().build_storage().unwrap().into()
+	let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap();
+	pallet_balances::GenesisConfig::<Test> { balances: vec![(1, 1_000_000), (2, 1_000_000)] }
+		.assimilate_storage(&mut t)
+		.unwrap();
+	t.into()
 }
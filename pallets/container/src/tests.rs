@@ -1,7 +1,18 @@
-use crate::mock::*;
-use frame_support::assert_ok;
+use crate::{
+	migrations::{v1::OldAPPInfo, MigrateToV1},
+	mock::*,
+	APPInfoMap, AppArgs, AppStatus, Error, GroupAPPMap, InuseMap,
+};
+use frame_support::{
+	assert_noop, assert_ok,
+	pallet_prelude::DispatchResult,
+	storage::unhashed,
+	traits::{GetStorageVersion, Hooks, OnRuntimeUpgrade},
+};
+use pallet_sequencer_grouping::{CpuArch, GroupMembers, NextRound, NextRoundStorage, Resources};
+use primitives_container::{ContainerApiError, ReadinessProbe};
 use sp_core::H256;
-use sp_runtime::BoundedVec;
+use sp_runtime::{BoundedVec, DispatchError};
 
 #[test]
 fn it_works_for_default_value() {
@@ -28,12 +39,910 @@ fn register_app() {
 			BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
 			BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
 			123,
-			Some(BoundedVec::try_from("--chain dev".as_bytes().to_vec()).unwrap()),
+			test_args(&["--chain", "dev"]),
+			BoundedVec::new(),
 			None,
 			None,
 			None,
+			Resources::default(),
 		));
 		let app = ContainerModule::appinfo_map(1).unwrap();
-		assert_eq!(app.app_hash, H256::from([1; 32]));
+		assert_eq!(app.releases[0].app_hash, H256::from([1; 32]));
+	});
+}
+
+fn test_args(args: &[&str]) -> AppArgs<Test> {
+	BoundedVec::try_from(
+		args.iter()
+			.map(|arg| BoundedVec::try_from(arg.as_bytes().to_vec()).unwrap())
+			.collect::<Vec<_>>(),
+	)
+	.unwrap()
+}
+
+fn register_test_app(creator: u64, name: &str) {
+	assert_ok!(ContainerModule::register_app(
+		RuntimeOrigin::signed(creator),
+		H256::from([1; 32]),
+		BoundedVec::try_from(name.as_bytes().to_vec()).unwrap(),
+		BoundedVec::try_from(name.as_bytes().to_vec()).unwrap(),
+		123,
+		BoundedVec::new(),
+		BoundedVec::new(),
+		None,
+		None,
+		None,
+		Resources::default(),
+	));
+}
+
+#[test]
+fn update_app_works() {
+	new_test_ext().execute_with(|| {
+		register_test_app(1, "test");
+		assert_ok!(ContainerModule::update_app(
+			RuntimeOrigin::signed(1),
+			1,
+			H256::from([2; 32]),
+			BoundedVec::try_from("test_v2".as_bytes().to_vec()).unwrap(),
+			456,
+			test_args(&["--chain", "dev"]),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+		));
+		// The registered release is already loaded, the update starts with the next round.
+		let app = ContainerModule::appinfo_map(1).unwrap();
+		assert_eq!(app.releases.len(), 2);
+		assert_eq!(app.releases[0].app_hash, H256::from([1; 32]));
+		assert_eq!(app.releases[1].app_hash, H256::from([2; 32]));
+		assert_eq!(app.releases[1].file_name.to_vec(), "test_v2".as_bytes().to_vec());
+		assert_eq!(app.releases[1].size, 456);
+		assert_eq!(app.releases[1].start_block, 10);
+		assert_eq!(ContainerModule::next_application_id(), 2);
+
+		// A release that is not loaded yet is replaced in place.
+		assert_ok!(ContainerModule::update_app(
+			RuntimeOrigin::signed(1),
+			1,
+			H256::from([3; 32]),
+			BoundedVec::try_from("test_v3".as_bytes().to_vec()).unwrap(),
+			789,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+		));
+		let app = ContainerModule::appinfo_map(1).unwrap();
+		assert_eq!(app.releases.len(), 2);
+		assert_eq!(app.releases[1].app_hash, H256::from([3; 32]));
+		assert_eq!(app.releases[1].start_block, 10);
+	});
+}
+
+#[test]
+fn update_app_switches_at_next_round() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+		confirm_test_upload(1, 1);
+		approve_test_app(1);
+		assign_test_group(5);
+
+		// Sequencers already load the release starting at block 1 for the round at block 5.
+		assert_ok!(ContainerModule::update_app(
+			RuntimeOrigin::signed(1),
+			1,
+			H256::from([2; 32]),
+			BoundedVec::try_from("test_v2".as_bytes().to_vec()).unwrap(),
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+		));
+		confirm_test_upload(1, 2);
+		assert_eq!(ContainerModule::appinfo_map(1).unwrap().releases[1].start_block, 15);
+		assert_eq!(ContainerModule::shuld_load(1).unwrap().app_hash, H256::from([1; 32]));
+
+		System::set_block_number(5);
+		assert_eq!(ContainerModule::shuld_load(1).unwrap().app_hash, H256::from([1; 32]));
+
+		NextRoundStorage::<Test>::put(NextRound { starting_block: 15, round_index: 2 });
+		assert_eq!(ContainerModule::shuld_load(1).unwrap().app_hash, H256::from([2; 32]));
+	});
+}
+
+#[test]
+fn update_app_fails_for_non_creator() {
+	new_test_ext().execute_with(|| {
+		register_test_app(1, "test");
+		assert_noop!(
+			ContainerModule::update_app(
+				RuntimeOrigin::signed(2),
+				1,
+				H256::from([2; 32]),
+				BoundedVec::try_from("test_v2".as_bytes().to_vec()).unwrap(),
+				456,
+				BoundedVec::new(),
+				BoundedVec::new(),
+				None,
+				None,
+				None,
+			),
+			Error::<Test>::NotAppCreator
+		);
+		assert_noop!(
+			ContainerModule::update_app(
+				RuntimeOrigin::signed(1),
+				2,
+				H256::from([2; 32]),
+				BoundedVec::try_from("test_v2".as_bytes().to_vec()).unwrap(),
+				456,
+				BoundedVec::new(),
+				BoundedVec::new(),
+				None,
+				None,
+				None,
+			),
+			Error::<Test>::AppNotExist
+		);
+	});
+}
+
+#[test]
+fn deregister_app_releases_group_and_slot() {
+	new_test_ext().execute_with(|| {
+		register_test_app(1, "test1");
+		register_test_app(1, "test2");
+		GroupAPPMap::<Test>::insert(0, 1);
+		InuseMap::<Test>::mutate(|inuses| inuses[0].1 = true);
+
+		assert_noop!(
+			ContainerModule::deregister_app(RuntimeOrigin::signed(2), 1),
+			Error::<Test>::NotAppCreator
+		);
+		assert_ok!(ContainerModule::deregister_app(RuntimeOrigin::signed(1), 1));
+
+		assert!(ContainerModule::appinfo_map(1).is_none());
+		assert_eq!(ContainerModule::group_app_map(0), None);
+		assert_eq!(ContainerModule::inuse_map().to_vec(), vec![(2, false)]);
+
+		// The freed slot is reused by the next registration, its app id is not.
+		register_test_app(2, "test3");
+		assert!(ContainerModule::appinfo_map(1).is_none());
+		assert_eq!(ContainerModule::appinfo_map(3).unwrap().creator, 2);
+		assert_eq!(ContainerModule::inuse_map().to_vec(), vec![(2, false), (3, false)]);
+		assert_eq!(ContainerModule::next_application_id(), 4);
+	});
+}
+
+fn schedule_test_release(creator: u64, app_id: u32, hash: u8, start_block: u64) -> DispatchResult {
+	ContainerModule::schedule_release(
+		RuntimeOrigin::signed(creator),
+		app_id,
+		H256::from([hash; 32]),
+		BoundedVec::try_from(format!("test_v{}", hash).as_bytes().to_vec()).unwrap(),
+		123,
+		BoundedVec::new(),
+		BoundedVec::new(),
+		None,
+		None,
+		start_block,
+	)
+}
+
+fn confirm_test_upload(app_id: u32, hash: u8) {
+	assert_ok!(ContainerModule::set_uploader(RuntimeOrigin::root(), 3, true));
+	assert_ok!(ContainerModule::confirm_upload(
+		RuntimeOrigin::signed(3),
+		app_id,
+		H256::from([hash; 32]),
+		123
+	));
+}
+
+fn approve_test_app(app_id: u32) {
+	assert_ok!(ContainerModule::set_app_status(RuntimeOrigin::root(), app_id, AppStatus::Approved));
+}
+
+// Put sequencer 1 into group 0, which runs app 1, and start the next round at `starting_block`.
+fn assign_test_group(starting_block: u64) {
+	GroupMembers::<Test>::put(
+		BoundedVec::try_from(vec![BoundedVec::try_from(vec![1]).unwrap()]).unwrap(),
+	);
+	NextRoundStorage::<Test>::put(NextRound { starting_block, round_index: 1 });
+	GroupAPPMap::<Test>::insert(0, 1);
+	assert_ok!(ContainerModule::set_default_url(
+		RuntimeOrigin::root(),
+		BoundedVec::try_from("http://127.0.0.1:8000/static".as_bytes().to_vec()).unwrap()
+	));
+}
+
+#[test]
+fn schedule_release_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+
+		assert_noop!(schedule_test_release(2, 1, 2, 10), Error::<Test>::NotAppCreator);
+		assert_noop!(schedule_test_release(1, 1, 2, 1), Error::<Test>::InvalidStartBlock);
+		assert_ok!(schedule_test_release(1, 1, 2, 10));
+		assert_noop!(schedule_test_release(1, 1, 3, 10), Error::<Test>::InvalidStartBlock);
+		assert_ok!(schedule_test_release(1, 1, 3, 20));
+		assert_noop!(schedule_test_release(1, 1, 4, 30), Error::<Test>::TooManyReleases);
+
+		// Once the second release is active, the first one is no longer kept.
+		System::set_block_number(25);
+		assert_ok!(schedule_test_release(1, 1, 4, 30));
+		let app = ContainerModule::appinfo_map(1).unwrap();
+		let hashes: Vec<H256> = app.releases.iter().map(|release| release.app_hash).collect();
+		assert_eq!(hashes, vec![H256::from([2; 32]), H256::from([3; 32]), H256::from([4; 32])]);
+	});
+}
+
+#[test]
+fn schedule_release_starts_with_a_round() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+		NextRoundStorage::<Test>::put(NextRound { starting_block: 5, round_index: 1 });
+
+		// Rounds last 10 blocks, starting with the next round at block 5.
+		assert_noop!(schedule_test_release(1, 1, 2, 3), Error::<Test>::InvalidStartBlock);
+		assert_noop!(schedule_test_release(1, 1, 2, 10), Error::<Test>::InvalidStartBlock);
+		assert_ok!(schedule_test_release(1, 1, 2, 5));
+		assert_noop!(schedule_test_release(1, 1, 3, 21), Error::<Test>::InvalidStartBlock);
+		assert_ok!(schedule_test_release(1, 1, 3, 25));
+	});
+}
+
+#[test]
+fn shuld_load_switches_release_at_round_start() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+		confirm_test_upload(1, 1);
+		approve_test_app(1);
+		assign_test_group(5);
+		assert_ok!(schedule_test_release(1, 1, 2, 15));
+		confirm_test_upload(1, 2);
+
+		let info = ContainerModule::shuld_load(1).unwrap();
+		assert_eq!(info.app_hash, H256::from([1; 32]));
+
+		// The next round starts at the release block, so sequencers load it ahead of time.
+		NextRoundStorage::<Test>::put(NextRound { starting_block: 15, round_index: 2 });
+		let info = ContainerModule::shuld_load(1).unwrap();
+		assert_eq!(info.app_hash, H256::from([2; 32]));
+		assert_eq!(info.file_name, "test_v2".as_bytes().to_vec());
+	});
+}
+
+#[test]
+fn rollback_release_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+		confirm_test_upload(1, 1);
+		approve_test_app(1);
+		assign_test_group(10);
+
+		assert_noop!(
+			ContainerModule::rollback_release(RuntimeOrigin::signed(1), 1),
+			Error::<Test>::NoPreviousRelease
+		);
+		assert_ok!(schedule_test_release(1, 1, 2, 10));
+		confirm_test_upload(1, 2);
+		assert_eq!(ContainerModule::shuld_load(1).unwrap().app_hash, H256::from([2; 32]));
+
+		assert_noop!(
+			ContainerModule::rollback_release(RuntimeOrigin::signed(2), 1),
+			Error::<Test>::NotAppCreator
+		);
+		assert_ok!(ContainerModule::rollback_release(RuntimeOrigin::signed(1), 1));
+		System::assert_last_event(RuntimeEvent::ContainerModule(crate::Event::RollbackRelease {
+			appid: 1,
+			hash: H256::from([1; 32]),
+		}));
+		assert_eq!(ContainerModule::shuld_load(1).unwrap().app_hash, H256::from([1; 32]));
+	});
+}
+
+#[test]
+fn confirm_upload_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+
+		// Neither the creator nor anyone else without the uploader role attests the file.
+		assert_noop!(
+			ContainerModule::confirm_upload(RuntimeOrigin::signed(1), 1, H256::from([1; 32]), 123),
+			Error::<Test>::NotUploader
+		);
+		assert_noop!(
+			ContainerModule::confirm_upload(RuntimeOrigin::signed(2), 1, H256::from([1; 32]), 123),
+			Error::<Test>::NotUploader
+		);
+
+		assert_ok!(ContainerModule::set_uploader(RuntimeOrigin::root(), 2, true));
+		assert_noop!(
+			ContainerModule::confirm_upload(RuntimeOrigin::signed(2), 1, H256::from([2; 32]), 123),
+			Error::<Test>::ReleaseNotExist
+		);
+		assert_noop!(
+			ContainerModule::confirm_upload(RuntimeOrigin::signed(2), 1, H256::from([1; 32]), 124),
+			Error::<Test>::SizeMismatch
+		);
+		assert_ok!(ContainerModule::confirm_upload(
+			RuntimeOrigin::signed(2),
+			1,
+			H256::from([1; 32]),
+			123
+		));
+		System::assert_last_event(RuntimeEvent::ContainerModule(crate::Event::ConfirmUpload {
+			appid: 1,
+			hash: H256::from([1; 32]),
+			uploader: 2,
+		}));
+		assert!(ContainerModule::appinfo_map(1).unwrap().releases[0].uploaded);
+	});
+}
+
+#[test]
+fn on_initialize_assigns_only_uploaded_apps() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test1");
+		register_test_app(1, "test2");
+		approve_test_app(1);
+		approve_test_app(2);
+		assign_test_group(5);
+		GroupAPPMap::<Test>::remove(0);
+
+		ContainerModule::on_initialize(1);
+		assert_eq!(ContainerModule::group_app_map(0), None);
+		assert!(ContainerModule::shuld_load(1).is_none());
+
+		confirm_test_upload(2, 1);
+		ContainerModule::on_initialize(1);
+		assert_eq!(ContainerModule::group_app_map(0), Some(2));
+		assert_eq!(ContainerModule::inuse_map().to_vec(), vec![(1, false), (2, true)]);
+	});
+}
+
+#[test]
+fn shuld_load_orders_mirrors() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+		confirm_test_upload(1, 1);
+		approve_test_app(1);
+		assign_test_group(5);
+
+		let app_mirror: BoundedVec<u8, MaxUrlLength> =
+			BoundedVec::try_from("http://app.mirror".as_bytes().to_vec()).unwrap();
+		let release_mirror: BoundedVec<u8, MaxUrlLength> =
+			BoundedVec::try_from("http://release.mirror".as_bytes().to_vec()).unwrap();
+
+		assert_noop!(
+			ContainerModule::set_mirrors(
+				RuntimeOrigin::signed(2),
+				1,
+				None,
+				BoundedVec::try_from(vec![app_mirror.clone()]).unwrap()
+			),
+			Error::<Test>::NotAppCreator
+		);
+		assert_ok!(ContainerModule::set_mirrors(
+			RuntimeOrigin::signed(1),
+			1,
+			None,
+			BoundedVec::try_from(vec![app_mirror.clone()]).unwrap()
+		));
+		let info = ContainerModule::shuld_load(1).unwrap();
+		assert_eq!(info.url, app_mirror.to_vec());
+		assert_eq!(
+			info.mirrors,
+			vec![app_mirror.to_vec(), "http://127.0.0.1:8000/static".as_bytes().to_vec()]
+		);
+
+		assert_ok!(ContainerModule::set_mirrors(
+			RuntimeOrigin::signed(1),
+			1,
+			Some(H256::from([1; 32])),
+			BoundedVec::try_from(vec![release_mirror.clone()]).unwrap()
+		));
+		let info = ContainerModule::shuld_load(1).unwrap();
+		assert_eq!(info.url, release_mirror.to_vec());
+		assert_eq!(
+			info.mirrors,
+			vec![release_mirror.to_vec(), "http://127.0.0.1:8000/static".as_bytes().to_vec()]
+		);
+	});
+}
+
+#[test]
+fn shuld_load_returns_readiness_probe() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+		confirm_test_upload(1, 1);
+		approve_test_app(1);
+		assign_test_group(5);
+
+		assert_eq!(ContainerModule::shuld_load(1).unwrap().readiness, None);
+
+		let probe = ReadinessProbe::Http {
+			port: 9933,
+			path: BoundedVec::try_from("/health".as_bytes().to_vec()).unwrap(),
+		};
+		assert_noop!(
+			ContainerModule::set_readiness_probe(RuntimeOrigin::signed(2), 1, Some(probe.clone())),
+			Error::<Test>::NotAppCreator
+		);
+		assert_ok!(ContainerModule::set_readiness_probe(RuntimeOrigin::signed(1), 1, Some(probe)));
+		assert_eq!(
+			ContainerModule::shuld_load(1).unwrap().readiness,
+			Some(ReadinessProbe::Http { port: 9933, path: "/health".as_bytes().to_vec() })
+		);
+
+		let probe = ReadinessProbe::TcpInRange { offset: 1 };
+		assert_ok!(ContainerModule::set_readiness_probe(RuntimeOrigin::signed(1), 1, Some(probe)));
+		assert_eq!(
+			ContainerModule::shuld_load(1).unwrap().readiness,
+			Some(ReadinessProbe::TcpInRange { offset: 1 })
+		);
+
+		assert_ok!(ContainerModule::set_readiness_probe(RuntimeOrigin::signed(1), 1, None));
+		assert_eq!(ContainerModule::shuld_load(1).unwrap().readiness, None);
+	});
+}
+
+#[test]
+fn on_initialize_releases_removed_groups() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test1");
+		register_test_app(1, "test2");
+		confirm_test_upload(1, 1);
+		confirm_test_upload(2, 1);
+		approve_test_app(1);
+		approve_test_app(2);
+		assign_test_group(5);
+		GroupAPPMap::<Test>::remove(0);
+		GroupMembers::<Test>::put(
+			BoundedVec::try_from(vec![
+				BoundedVec::try_from(vec![1]).unwrap(),
+				BoundedVec::try_from(vec![2]).unwrap(),
+			])
+			.unwrap(),
+		);
+
+		ContainerModule::on_initialize(1);
+		assert_eq!(ContainerModule::group_app_map(0), Some(1));
+		assert_eq!(ContainerModule::group_app_map(1), Some(2));
+		System::assert_has_event(RuntimeEvent::ContainerModule(crate::Event::AssignApp {
+			group: 1,
+			appid: 2,
+		}));
+
+		// The grouping shrinks to a single group, group 1 is gone.
+		GroupMembers::<Test>::put(
+			BoundedVec::try_from(vec![BoundedVec::try_from(vec![1]).unwrap()]).unwrap(),
+		);
+		ContainerModule::on_initialize(2);
+		assert_eq!(ContainerModule::group_app_map(0), Some(1));
+		assert_eq!(ContainerModule::group_app_map(1), None);
+		assert_eq!(ContainerModule::inuse_map().to_vec(), vec![(1, true), (2, false)]);
+		System::assert_has_event(RuntimeEvent::ContainerModule(crate::Event::UnassignApp {
+			group: 1,
+			appid: 2,
+		}));
+	});
+}
+
+#[test]
+fn on_initialize_reassigns_removed_apps() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test1");
+		register_test_app(1, "test2");
+		confirm_test_upload(1, 1);
+		confirm_test_upload(2, 1);
+		approve_test_app(1);
+		approve_test_app(2);
+		assign_test_group(5);
+
+		// A stale assignment to an app that no longer exists.
+		GroupAPPMap::<Test>::insert(0, 5);
+		ContainerModule::on_initialize(1);
+		assert_eq!(ContainerModule::group_app_map(0), Some(1));
+
+		assert_ok!(ContainerModule::deregister_app(RuntimeOrigin::signed(1), 1));
+		System::assert_has_event(RuntimeEvent::ContainerModule(crate::Event::UnassignApp {
+			group: 0,
+			appid: 1,
+		}));
+		ContainerModule::on_initialize(2);
+		assert_eq!(ContainerModule::group_app_map(0), Some(2));
+		assert_eq!(ContainerModule::inuse_map().to_vec(), vec![(2, true)]);
+	});
+}
+
+#[test]
+fn register_app_reserves_deposit() {
+	new_test_ext().execute_with(|| {
+		register_test_app(1, "test");
+		let app = ContainerModule::appinfo_map(1).unwrap();
+		assert!(app.deposit > AppDepositBase::get());
+		assert_eq!(Balances::reserved_balance(1), app.deposit);
+
+		// An update of the loaded release adds a release.
+		assert_ok!(ContainerModule::update_app(
+			RuntimeOrigin::signed(1),
+			1,
+			H256::from([2; 32]),
+			BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+			456,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+		));
+		let app = ContainerModule::appinfo_map(1).unwrap();
+		assert_eq!(Balances::reserved_balance(1), app.deposit);
+
+		// A longer file name takes more storage.
+		assert_ok!(ContainerModule::update_app(
+			RuntimeOrigin::signed(1),
+			1,
+			H256::from([2; 32]),
+			BoundedVec::try_from("test_with_a_longer_name".as_bytes().to_vec()).unwrap(),
+			456,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+		));
+		let updated = ContainerModule::appinfo_map(1).unwrap();
+		assert_eq!(updated.deposit, app.deposit + 19);
+		assert_eq!(Balances::reserved_balance(1), updated.deposit);
+
+		assert_ok!(ContainerModule::deregister_app(RuntimeOrigin::signed(1), 1));
+		assert_eq!(Balances::reserved_balance(1), 0);
+		assert_eq!(Balances::free_balance(1), 1_000_000);
+	});
+}
+
+#[test]
+fn register_app_fails_without_deposit() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			ContainerModule::register_app(
+				RuntimeOrigin::signed(4),
+				H256::from([1; 32]),
+				BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+				BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+				123,
+				BoundedVec::new(),
+				BoundedVec::new(),
+				None,
+				None,
+				None,
+				Resources::default(),
+			),
+			pallet_balances::Error::<Test>::InsufficientBalance
+		);
+	});
+}
+
+#[test]
+fn register_app_fails_with_too_many_apps() {
+	new_test_ext().execute_with(|| {
+		for _ in 0..MaxRuningAPP::get() {
+			register_test_app(1, "test");
+		}
+		assert_noop!(
+			ContainerModule::register_app(
+				RuntimeOrigin::signed(1),
+				H256::from([1; 32]),
+				BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+				BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+				123,
+				BoundedVec::new(),
+				BoundedVec::new(),
+				None,
+				None,
+				None,
+				Resources::default(),
+			),
+			Error::<Test>::TooManyApps
+		);
+	});
+}
+
+fn test_env(
+	name: &str,
+	value: &str,
+) -> (BoundedVec<u8, MaxArgLength>, BoundedVec<u8, MaxArgLength>) {
+	(
+		BoundedVec::try_from(name.as_bytes().to_vec()).unwrap(),
+		BoundedVec::try_from(value.as_bytes().to_vec()).unwrap(),
+	)
+}
+
+#[test]
+fn register_app_rejects_invalid_envs() {
+	new_test_ext().execute_with(|| {
+		for env in [test_env("", "1"), test_env("A=B", "1"), test_env("A", "1\0")] {
+			assert_noop!(
+				ContainerModule::register_app(
+					RuntimeOrigin::signed(1),
+					H256::from([1; 32]),
+					BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+					BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+					123,
+					BoundedVec::new(),
+					BoundedVec::try_from(vec![env]).unwrap(),
+					None,
+					None,
+					None,
+					Resources::default(),
+				),
+				Error::<Test>::InvalidEnv
+			);
+		}
+	});
+}
+
+#[test]
+fn shuld_load_returns_args_and_envs() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(ContainerModule::register_app(
+			RuntimeOrigin::signed(1),
+			H256::from([1; 32]),
+			BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+			BoundedVec::try_from("test".as_bytes().to_vec()).unwrap(),
+			123,
+			test_args(&["--name", "my node"]),
+			BoundedVec::try_from(vec![test_env("RUST_LOG", "info,sync=debug")]).unwrap(),
+			None,
+			None,
+			None,
+			Resources::default(),
+		));
+		confirm_test_upload(1, 1);
+		approve_test_app(1);
+		assign_test_group(5);
+
+		let info = ContainerModule::shuld_load(1).unwrap();
+		assert_eq!(info.args, vec![b"--name".to_vec(), b"my node".to_vec()]);
+		assert_eq!(info.envs, vec![(b"RUST_LOG".to_vec(), b"info,sync=debug".to_vec())]);
+	});
+}
+
+#[test]
+fn on_initialize_assigns_only_approved_apps() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test");
+		confirm_test_upload(1, 1);
+		assign_test_group(5);
+		GroupAPPMap::<Test>::remove(0);
+
+		assert_noop!(
+			ContainerModule::set_app_status(RuntimeOrigin::signed(1), 1, AppStatus::Approved),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			ContainerModule::set_app_status(RuntimeOrigin::root(), 2, AppStatus::Approved),
+			Error::<Test>::AppNotExist
+		);
+
+		ContainerModule::on_initialize(1);
+		assert_eq!(ContainerModule::group_app_map(0), None);
+
+		approve_test_app(1);
+		System::assert_last_event(RuntimeEvent::ContainerModule(crate::Event::SetAppStatus {
+			appid: 1,
+			status: AppStatus::Approved,
+		}));
+		ContainerModule::on_initialize(1);
+		assert_eq!(ContainerModule::group_app_map(0), Some(1));
+		assert!(ContainerModule::shuld_load(1).is_some());
+
+		// A suspended app stops loading at once and its group is released.
+		assert_ok!(ContainerModule::set_app_status(RuntimeOrigin::root(), 1, AppStatus::Suspended));
+		assert!(ContainerModule::shuld_load(1).is_none());
+		ContainerModule::on_initialize(2);
+		assert_eq!(ContainerModule::group_app_map(0), None);
+		assert_eq!(ContainerModule::inuse_map().to_vec(), vec![(1, false)]);
+	});
+}
+
+#[test]
+fn on_initialize_matches_app_requirements() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let heavy = Resources {
+			cpu_cores: 8,
+			memory_mb: 32768,
+			disk_mb: 100000,
+			arch: Some(CpuArch::X86_64),
+		};
+		assert_ok!(ContainerModule::register_app(
+			RuntimeOrigin::signed(1),
+			H256::from([1; 32]),
+			BoundedVec::try_from("heavy".as_bytes().to_vec()).unwrap(),
+			BoundedVec::try_from("heavy".as_bytes().to_vec()).unwrap(),
+			123,
+			BoundedVec::new(),
+			BoundedVec::new(),
+			None,
+			None,
+			None,
+			heavy.clone(),
+		));
+		register_test_app(1, "light");
+		for app_id in 1..=2 {
+			confirm_test_upload(app_id, 1);
+			approve_test_app(app_id);
+		}
+
+		// Processor 1 is too small for the heavy app, processor 2 can host it.
+		let ip_address = BoundedVec::try_from(vec![127, 0, 0, 1]).unwrap();
+		assert_ok!(SequencerGroupingModule::register_processor(
+			RuntimeOrigin::signed(1),
+			ip_address.clone(),
+			Resources {
+				cpu_cores: 2,
+				memory_mb: 4096,
+				disk_mb: 100000,
+				arch: Some(CpuArch::X86_64)
+			},
+		));
+		assert_ok!(SequencerGroupingModule::register_processor(
+			RuntimeOrigin::signed(2),
+			ip_address,
+			heavy,
+		));
+		GroupMembers::<Test>::put(
+			BoundedVec::try_from(vec![
+				BoundedVec::try_from(vec![1]).unwrap(),
+				BoundedVec::try_from(vec![2]).unwrap(),
+			])
+			.unwrap(),
+		);
+		assert_ok!(SequencerGroupingModule::assign_processors_to_groups(2));
+
+		ContainerModule::on_initialize(1);
+		assert_eq!(ContainerModule::group_app_map(0), Some(2));
+		assert_eq!(ContainerModule::group_app_map(1), Some(1));
+
+		// The next grouping keeps the heavy app on the processor able to host it.
+		assert_ok!(SequencerGroupingModule::assign_processors_to_groups(2));
+		assert_eq!(SequencerGroupingModule::get_group_ids(1), vec![0]);
+		assert_eq!(SequencerGroupingModule::get_group_ids(2), vec![1]);
+	});
+}
+
+#[test]
+fn processor_apps_has_one_app_per_group() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test1");
+		register_test_app(1, "test2");
+		for app_id in 1..=2 {
+			confirm_test_upload(app_id, 1);
+			approve_test_app(app_id);
+		}
+
+		// A single processor hosts both groups.
+		assert_ok!(SequencerGroupingModule::register_processor(
+			RuntimeOrigin::signed(1),
+			BoundedVec::try_from(vec![127, 0, 0, 1]).unwrap(),
+			Resources {
+				cpu_cores: 8,
+				memory_mb: 32768,
+				disk_mb: 100000,
+				arch: Some(CpuArch::X86_64)
+			},
+		));
+		GroupMembers::<Test>::put(
+			BoundedVec::try_from(vec![
+				BoundedVec::try_from(vec![1]).unwrap(),
+				BoundedVec::try_from(vec![2]).unwrap(),
+			])
+			.unwrap(),
+		);
+		assert_ok!(SequencerGroupingModule::assign_processors_to_groups(2));
+		assert_ok!(ContainerModule::set_default_url(
+			RuntimeOrigin::root(),
+			BoundedVec::try_from("http://127.0.0.1:8000/static".as_bytes().to_vec()).unwrap()
+		));
+		assert!(ContainerModule::processor_apps(1).is_empty());
+
+		ContainerModule::on_initialize(1);
+		let apps = ContainerModule::processor_apps(1);
+		assert_eq!(
+			apps.iter().map(|app| (app.group, app.app_id)).collect::<Vec<_>>(),
+			vec![(0, 1), (1, 2)]
+		);
+		assert!(ContainerModule::processor_apps(2).is_empty());
+	});
+}
+
+#[test]
+fn runtime_queries_work() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		register_test_app(1, "test1");
+		register_test_app(2, "test2");
+		confirm_test_upload(1, 1);
+		approve_test_app(1);
+		assign_test_group(5);
+
+		let apps = ContainerModule::apps();
+		assert_eq!(apps.len(), 2);
+		assert_eq!(apps[0].app_id, 1);
+		assert_eq!(apps[0].status, AppStatus::Approved);
+		assert_eq!(apps[0].app_hash, Some(H256::from([1; 32])));
+		assert_eq!(apps[0].groups, vec![0]);
+		assert_eq!(apps[1].creator, 2);
+		assert_eq!(apps[1].status, AppStatus::Pending);
+		assert_eq!(apps[1].app_hash, None);
+
+		assert_eq!(ContainerModule::app_of_group(0).unwrap().unwrap().app_id, 1);
+		assert_eq!(ContainerModule::app_of_group(1), Err(ContainerApiError::GroupNotExist));
+		assert_eq!(ContainerModule::group_members(0), Ok(vec![1]));
+		assert_eq!(ContainerModule::group_members(1), Err(ContainerApiError::GroupNotExist));
+		assert_eq!(ContainerModule::group_processors(0), Ok(vec![]));
+		assert_eq!(ContainerModule::group_id_of(1), Ok(0));
+		assert_eq!(ContainerModule::group_id_of(3), Err(ContainerApiError::AccountNotInGroup));
+
+		let schedule = ContainerModule::round_schedule();
+		assert_eq!(
+			(schedule.round_index, schedule.starting_block, schedule.release_block),
+			(1, 5, 5)
+		);
+	});
+}
+
+#[test]
+fn migration_to_v1_keeps_apps() {
+	new_test_ext().execute_with(|| {
+		let old = OldAPPInfo::<Test> {
+			app_hash: H256::from([1; 32]),
+			creator: 1,
+			project_name: BoundedVec::try_from(b"test".to_vec()).unwrap(),
+			file_name: BoundedVec::try_from(b"test-node".to_vec()).unwrap(),
+			uploaded: true,
+			size: 123,
+			args: Some(BoundedVec::try_from(b"--chain  dev".to_vec()).unwrap()),
+			log: None,
+			is_docker_image: None,
+			docker_image: None,
+		};
+		unhashed::put(&APPInfoMap::<Test>::hashed_key_for(1), &old);
+		unhashed::put(&InuseMap::<Test>::hashed_key(), &vec![true]);
+
+		MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(ContainerModule::on_chain_storage_version(), 1);
+		let app = ContainerModule::appinfo_map(1).unwrap();
+		assert_eq!(app.creator, 1);
+		assert_eq!(app.status, AppStatus::Approved);
+		assert_eq!(app.releases.len(), 1);
+		assert_eq!(app.releases[0].file_name.to_vec(), b"test-node".to_vec());
+		assert_eq!(app.releases[0].args, test_args(&["--chain", "dev"]));
+		assert!(app.releases[0].uploaded);
+		assert_eq!(ContainerModule::inuse_map().to_vec(), vec![(1, true)]);
 	});
 }
@@ -36,6 +36,16 @@ use core::marker::PhantomData;
 pub trait WeightInfo {
 	fn set_default_url() -> Weight;
 	fn register_app() -> Weight;
+	fn update_app() -> Weight;
+	fn deregister_app() -> Weight;
+	fn schedule_release() -> Weight;
+	fn rollback_release() -> Weight;
+	fn confirm_upload() -> Weight;
+	fn set_uploader() -> Weight;
+	fn set_mirrors() -> Weight;
+	fn set_app_status() -> Weight;
+	fn set_readiness_probe() -> Weight;
+	fn reconcile_groups(g: u32, a: u32, ) -> Weight;
 }
 
 /// Weights for pallet using the Substrate node and recommended hardware.
@@ -58,6 +68,8 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	/// Proof: `ContainerPallet::InuseMap` (`max_values`: Some(1), `max_size`: Some(102), added: 597, mode: `MaxEncodedLen`)
 	/// Storage: `ContainerPallet::APPInfoMap` (r:0 w:1)
 	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(700), added: 3175, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
 	fn register_app() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `42`
@@ -65,8 +77,165 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 		// Minimum execution time: 14_487_000 picoseconds.
 		Weight::from_parts(14_969_000, 0)
 			.saturating_add(Weight::from_parts(0, 1587))
+			.saturating_add(T::DbWeight::get().reads(3))
+			.saturating_add(T::DbWeight::get().writes(4))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(700), added: 3175, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn update_app() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `150`
+		//  Estimated: `4165`
+		// Minimum execution time: 13_112_000 picoseconds.
+		Weight::from_parts(13_596_000, 0)
+			.saturating_add(Weight::from_parts(0, 4165))
 			.saturating_add(T::DbWeight::get().reads(2))
-			.saturating_add(T::DbWeight::get().writes(3))
+			.saturating_add(T::DbWeight::get().writes(2))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(700), added: 3175, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::GroupAPPMap` (r:101 w:1)
+	/// Proof: `ContainerPallet::GroupAPPMap` (`max_values`: None, `max_size`: Some(16), added: 2491, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::InuseMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::InuseMap` (`max_values`: Some(1), `max_size`: Some(102), added: 597, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn deregister_app() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1980`
+		//  Estimated: `252591`
+		// Minimum execution time: 96_331_000 picoseconds.
+		Weight::from_parts(98_204_000, 0)
+			.saturating_add(Weight::from_parts(0, 252591))
+			.saturating_add(T::DbWeight::get().reads(104))
+			.saturating_add(T::DbWeight::get().writes(4))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(3302), added: 5777, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn schedule_release() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1812`
+		//  Estimated: `6767`
+		// Minimum execution time: 18_530_000 picoseconds.
+		Weight::from_parts(19_271_000, 0)
+			.saturating_add(Weight::from_parts(0, 6767))
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(2))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(3302), added: 5777, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn rollback_release() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1812`
+		//  Estimated: `6767`
+		// Minimum execution time: 14_802_000 picoseconds.
+		Weight::from_parts(15_307_000, 0)
+			.saturating_add(Weight::from_parts(0, 6767))
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(2))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(3307), added: 5782, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::Uploaders` (r:1 w:0)
+	/// Proof: `ContainerPallet::Uploaders` (`max_values`: None, `max_size`: Some(49), added: 2524, mode: `MaxEncodedLen`)
+	fn confirm_upload() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1812`
+		//  Estimated: `6772`
+		// Minimum execution time: 17_264_000 picoseconds.
+		Weight::from_parts(17_851_000, 0)
+			.saturating_add(Weight::from_parts(0, 6772))
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	/// Storage: `ContainerPallet::Uploaders` (r:0 w:1)
+	/// Proof: `ContainerPallet::Uploaders` (`max_values`: None, `max_size`: Some(49), added: 2524, mode: `MaxEncodedLen`)
+	fn set_uploader() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		// Minimum execution time: 5_102_000 picoseconds.
+		Weight::from_parts(5_344_000, 0)
+			.saturating_add(Weight::from_parts(0, 0))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(12688), added: 15163, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn set_mirrors() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `2115`
+		//  Estimated: `16138`
+		// Minimum execution time: 19_006_000 picoseconds.
+		Weight::from_parts(19_618_000, 0)
+			.saturating_add(Weight::from_parts(0, 16138))
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(2))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(12688), added: 15163, mode: `MaxEncodedLen`)
+	fn set_app_status() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1812`
+		//  Estimated: `16139`
+		// Minimum execution time: 12_481_000 picoseconds.
+		Weight::from_parts(12_906_000, 0)
+			.saturating_add(Weight::from_parts(0, 16139))
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(12688), added: 15163, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn set_readiness_probe() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1845`
+		//  Estimated: `16138`
+		// Minimum execution time: 17_312_000 picoseconds.
+		Weight::from_parts(17_880_000, 0)
+			.saturating_add(Weight::from_parts(0, 16138))
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(2))
+	}
+	/// Storage: `SequencerGroupingPallet::GroupMembers` (r:1 w:0)
+	/// Proof: `SequencerGroupingPallet::GroupMembers` (`max_values`: Some(1), `max_size`: Some(320002), added: 320497, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::InuseMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::InuseMap` (`max_values`: Some(1), `max_size`: Some(502), added: 997, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::GroupAPPMap` (r:101 w:0)
+	/// Proof: `ContainerPallet::GroupAPPMap` (`max_values`: None, `max_size`: Some(16), added: 2491, mode: `MaxEncodedLen`)
+	/// Storage: `SequencerGroupingPallet::NextRoundStorage` (r:1 w:0)
+	/// Proof: `SequencerGroupingPallet::NextRoundStorage` (`max_values`: Some(1), `max_size`: Some(8), added: 503, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::APPInfoMap` (r:100 w:0)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(12688), added: 15163, mode: `MaxEncodedLen`)
+	/// Storage: `SequencerGroupingPallet::ProcessorInfo` (r:1 w:0)
+	/// Proof: `SequencerGroupingPallet::ProcessorInfo` (`max_values`: Some(1), `max_size`: Some(1101), added: 1596, mode: `MaxEncodedLen`)
+	/// The range of component `g` is `[1, 100]`.
+	/// The range of component `a` is `[1, 100]`.
+	fn reconcile_groups(g: u32, a: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `306 + a * (1801 ±0) + g * (33 ±0)`
+		//  Estimated: `321487 + a * (15163 ±0) + g * (2491 ±0)`
+		// Minimum execution time: 21_904_000 picoseconds.
+		Weight::from_parts(8_316_000, 0)
+			.saturating_add(Weight::from_parts(0, 321487))
+			// Standard Error: 9_612
+			.saturating_add(Weight::from_parts(3_187_000, 0).saturating_mul(g.into()))
+			// Standard Error: 9_612
+			.saturating_add(Weight::from_parts(11_406_000, 0).saturating_mul(a.into()))
+			.saturating_add(T::DbWeight::get().reads(4))
+			.saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(g.into())))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(a.into())))
+			.saturating_add(T::DbWeight::get().writes(1))
+			.saturating_add(Weight::from_parts(0, 15163).saturating_mul(a.into()))
+			.saturating_add(Weight::from_parts(0, 2491).saturating_mul(g.into()))
 	}
 }
 
@@ -89,6 +258,8 @@ impl WeightInfo for () {
 	/// Proof: `ContainerPallet::InuseMap` (`max_values`: Some(1), `max_size`: Some(102), added: 597, mode: `MaxEncodedLen`)
 	/// Storage: `ContainerPallet::APPInfoMap` (r:0 w:1)
 	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(700), added: 3175, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
 	fn register_app() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `42`
@@ -96,7 +267,164 @@ impl WeightInfo for () {
 		// Minimum execution time: 14_487_000 picoseconds.
 		Weight::from_parts(14_969_000, 0)
 			.saturating_add(Weight::from_parts(0, 1587))
+			.saturating_add(RocksDbWeight::get().reads(3))
+			.saturating_add(RocksDbWeight::get().writes(4))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(700), added: 3175, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn update_app() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `150`
+		//  Estimated: `4165`
+		// Minimum execution time: 13_112_000 picoseconds.
+		Weight::from_parts(13_596_000, 0)
+			.saturating_add(Weight::from_parts(0, 4165))
 			.saturating_add(RocksDbWeight::get().reads(2))
-			.saturating_add(RocksDbWeight::get().writes(3))
+			.saturating_add(RocksDbWeight::get().writes(2))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(700), added: 3175, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::GroupAPPMap` (r:101 w:1)
+	/// Proof: `ContainerPallet::GroupAPPMap` (`max_values`: None, `max_size`: Some(16), added: 2491, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::InuseMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::InuseMap` (`max_values`: Some(1), `max_size`: Some(102), added: 597, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn deregister_app() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1980`
+		//  Estimated: `252591`
+		// Minimum execution time: 96_331_000 picoseconds.
+		Weight::from_parts(98_204_000, 0)
+			.saturating_add(Weight::from_parts(0, 252591))
+			.saturating_add(RocksDbWeight::get().reads(104))
+			.saturating_add(RocksDbWeight::get().writes(4))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(3302), added: 5777, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn schedule_release() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1812`
+		//  Estimated: `6767`
+		// Minimum execution time: 18_530_000 picoseconds.
+		Weight::from_parts(19_271_000, 0)
+			.saturating_add(Weight::from_parts(0, 6767))
+			.saturating_add(RocksDbWeight::get().reads(2))
+			.saturating_add(RocksDbWeight::get().writes(2))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(3302), added: 5777, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn rollback_release() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1812`
+		//  Estimated: `6767`
+		// Minimum execution time: 14_802_000 picoseconds.
+		Weight::from_parts(15_307_000, 0)
+			.saturating_add(Weight::from_parts(0, 6767))
+			.saturating_add(RocksDbWeight::get().reads(2))
+			.saturating_add(RocksDbWeight::get().writes(2))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(3307), added: 5782, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::Uploaders` (r:1 w:0)
+	/// Proof: `ContainerPallet::Uploaders` (`max_values`: None, `max_size`: Some(49), added: 2524, mode: `MaxEncodedLen`)
+	fn confirm_upload() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1812`
+		//  Estimated: `6772`
+		// Minimum execution time: 17_264_000 picoseconds.
+		Weight::from_parts(17_851_000, 0)
+			.saturating_add(Weight::from_parts(0, 6772))
+			.saturating_add(RocksDbWeight::get().reads(2))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	/// Storage: `ContainerPallet::Uploaders` (r:0 w:1)
+	/// Proof: `ContainerPallet::Uploaders` (`max_values`: None, `max_size`: Some(49), added: 2524, mode: `MaxEncodedLen`)
+	fn set_uploader() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `0`
+		//  Estimated: `0`
+		// Minimum execution time: 5_102_000 picoseconds.
+		Weight::from_parts(5_344_000, 0)
+			.saturating_add(Weight::from_parts(0, 0))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(12688), added: 15163, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn set_mirrors() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `2115`
+		//  Estimated: `16138`
+		// Minimum execution time: 19_006_000 picoseconds.
+		Weight::from_parts(19_618_000, 0)
+			.saturating_add(Weight::from_parts(0, 16138))
+			.saturating_add(RocksDbWeight::get().reads(2))
+			.saturating_add(RocksDbWeight::get().writes(2))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(12688), added: 15163, mode: `MaxEncodedLen`)
+	fn set_app_status() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1812`
+		//  Estimated: `16139`
+		// Minimum execution time: 12_481_000 picoseconds.
+		Weight::from_parts(12_906_000, 0)
+			.saturating_add(Weight::from_parts(0, 16139))
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	/// Storage: `ContainerPallet::APPInfoMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(12688), added: 15163, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn set_readiness_probe() -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `1845`
+		//  Estimated: `16138`
+		// Minimum execution time: 17_312_000 picoseconds.
+		Weight::from_parts(17_880_000, 0)
+			.saturating_add(Weight::from_parts(0, 16138))
+			.saturating_add(RocksDbWeight::get().reads(2))
+			.saturating_add(RocksDbWeight::get().writes(2))
+	}
+	/// Storage: `SequencerGroupingPallet::GroupMembers` (r:1 w:0)
+	/// Proof: `SequencerGroupingPallet::GroupMembers` (`max_values`: Some(1), `max_size`: Some(320002), added: 320497, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::InuseMap` (r:1 w:1)
+	/// Proof: `ContainerPallet::InuseMap` (`max_values`: Some(1), `max_size`: Some(502), added: 997, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::GroupAPPMap` (r:101 w:0)
+	/// Proof: `ContainerPallet::GroupAPPMap` (`max_values`: None, `max_size`: Some(16), added: 2491, mode: `MaxEncodedLen`)
+	/// Storage: `SequencerGroupingPallet::NextRoundStorage` (r:1 w:0)
+	/// Proof: `SequencerGroupingPallet::NextRoundStorage` (`max_values`: Some(1), `max_size`: Some(8), added: 503, mode: `MaxEncodedLen`)
+	/// Storage: `ContainerPallet::APPInfoMap` (r:100 w:0)
+	/// Proof: `ContainerPallet::APPInfoMap` (`max_values`: None, `max_size`: Some(12688), added: 15163, mode: `MaxEncodedLen`)
+	/// Storage: `SequencerGroupingPallet::ProcessorInfo` (r:1 w:0)
+	/// Proof: `SequencerGroupingPallet::ProcessorInfo` (`max_values`: Some(1), `max_size`: Some(1101), added: 1596, mode: `MaxEncodedLen`)
+	/// The range of component `g` is `[1, 100]`.
+	/// The range of component `a` is `[1, 100]`.
+	fn reconcile_groups(g: u32, a: u32, ) -> Weight {
+		// Proof Size summary in bytes:
+		//  Measured:  `306 + a * (1801 ±0) + g * (33 ±0)`
+		//  Estimated: `321487 + a * (15163 ±0) + g * (2491 ±0)`
+		// Minimum execution time: 21_904_000 picoseconds.
+		Weight::from_parts(8_316_000, 0)
+			.saturating_add(Weight::from_parts(0, 321487))
+			// Standard Error: 9_612
+			.saturating_add(Weight::from_parts(3_187_000, 0).saturating_mul(g.into()))
+			// Standard Error: 9_612
+			.saturating_add(Weight::from_parts(11_406_000, 0).saturating_mul(a.into()))
+			.saturating_add(RocksDbWeight::get().reads(4))
+			.saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(g.into())))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(a.into())))
+			.saturating_add(RocksDbWeight::get().writes(1))
+			.saturating_add(Weight::from_parts(0, 15163).saturating_mul(a.into()))
+			.saturating_add(Weight::from_parts(0, 2491).saturating_mul(g.into()))
 	}
 }
@@ -38,7 +38,8 @@ benchmarks! {
 
 	register_processor {
 		let ip_address: BoundedVec<u8, T::MaxLengthIP> = BoundedVec::from(BoundedVec::try_from(vec![1u8; 15]).unwrap());
-	}: _(RawOrigin::Signed(account("processor", 0, 0)), ip_address)
+		let capacity = Resources { cpu_cores: 8, memory_mb: 16384, disk_mb: 512000, arch: Some(CpuArch::X86_64) };
+	}: _(RawOrigin::Signed(account("processor", 0, 0)), ip_address, capacity)
 }
 
 impl_benchmark_test_suite!(SequencerGrouping, crate::mock::new_test_ext(), crate::mock::Test,);
@@ -52,6 +52,19 @@ pub mod pallet {
 		/// Maximum number of running app
 		#[pallet::constant]
 		type MaxRunningAPP: Get<u32>;
+
+		/// Resources required by the app running on a group
+		type GroupResources: GroupResources;
+	}
+
+	pub trait GroupResources {
+		fn group_requirement(group_id: u32) -> Resources;
+	}
+
+	impl GroupResources for () {
+		fn group_requirement(_group_id: u32) -> Resources {
+			Resources::default()
+		}
 	}
 
 	pub trait SequencerGroup<AccountId, BlockNumber> {
@@ -64,6 +77,44 @@ pub mod pallet {
 		fn account_in_group(account: AccountId) -> Result<u32, DispatchError>;
 		fn all_group_ids() -> Vec<u32>;
 		fn next_round() -> NextRound<BlockNumber>;
+		fn group_can_host(group_id: u32, required: &Resources) -> bool;
+	}
+
+	#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug, Encode, Decode, TypeInfo, MaxEncodedLen)]
+	pub enum CpuArch {
+		X86_64,
+		Aarch64,
+	}
+
+	/// Hardware resources, either the capacity of a processor or the requirement of an app.
+	#[derive(
+		Clone, Eq, PartialEq, RuntimeDebug, Encode, Decode, TypeInfo, MaxEncodedLen, Default,
+	)]
+	pub struct Resources {
+		pub cpu_cores: u32,
+		pub memory_mb: u32,
+		pub disk_mb: u32,
+		/// `None` runs on any architecture, for a processor it means the architecture is unknown.
+		pub arch: Option<CpuArch>,
+	}
+
+	impl Resources {
+		pub fn can_host(&self, required: &Resources) -> bool {
+			self.cpu_cores >= required.cpu_cores &&
+				self.memory_mb >= required.memory_mb &&
+				self.disk_mb >= required.disk_mb &&
+				(required.arch.is_none() || required.arch == self.arch)
+		}
+
+		// Capacity left after hosting `used`, the architecture is kept.
+		pub fn saturating_sub(&self, used: &Resources) -> Resources {
+			Resources {
+				cpu_cores: self.cpu_cores.saturating_sub(used.cpu_cores),
+				memory_mb: self.memory_mb.saturating_sub(used.memory_mb),
+				disk_mb: self.disk_mb.saturating_sub(used.disk_mb),
+				arch: self.arch,
+			}
+		}
 	}
 
 	#[derive(
@@ -100,6 +151,12 @@ pub mod pallet {
 	pub(crate) type ProcessorInfo<T: Config> = StorageValue<_, BoundedVec<
 		(T::AccountId, BoundedVec<u8, T::MaxLengthIP>, BoundedVec<u32, T::MaxRunningAPP>), T::MaxRunningAPP>, ValueQuery>;
 
+	// Capacity advertised by each processor.
+	#[pallet::storage]
+	#[pallet::getter(fn processor_resources)]
+	pub type ProcessorResources<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, Resources, ValueQuery>;
+
 	#[pallet::genesis_config]
 	pub struct GenesisConfig<T: Config> {
 		pub group_size: u32,
@@ -136,6 +193,8 @@ pub mod pallet {
 	pub enum Event<T: Config> {
 		/// Updated the sequencer group.
 		SequencerGroupUpdated { starting_block: BlockNumberFor<T>, round_index: u32 },
+		/// No processor has capacity left for the app of the group, it runs on no processor.
+		GroupUnhostable { group_id: u32 },
 	}
 
 	#[pallet::call]
@@ -183,10 +242,15 @@ pub mod pallet {
 		pub fn register_processor(
 			origin: OriginFor<T>,
 			ip_address: BoundedVec<u8, T::MaxLengthIP>,
+			capacity: Resources,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
 			let mut processor_info = crate::pallet::ProcessorInfo::<T>::get();
 
+			if ProcessorResources::<T>::get(&who) != capacity {
+				ProcessorResources::<T>::insert(&who, capacity);
+			}
+
 			if let Some(existing) = processor_info.iter_mut().find(|(account, _, _)| account == &who) {
 				if existing.1 == ip_address {
 					return Ok(());
@@ -246,12 +310,29 @@ pub mod pallet {
 				groups.clear();
 			}
 
-			for group_id in 0..=group_number - 1 {
-				let processor_index = group_id % processor_count;
-				let processor = &mut processor_info[processor_index as usize];
-
-				if !processor.2.contains(&group_id) {
-					processor.2.try_push(group_id).map_err(|_| Error::<T>::TooManyProcessors)?;
+			let mut remaining: Vec<Resources> = processor_info
+				.iter()
+				.map(|(account, _, _)| ProcessorResources::<T>::get(account))
+				.collect();
+
+			// Place each group on the least loaded processor that can still host its app.
+			for group_id in 0..group_number {
+				let required = T::GroupResources::group_requirement(group_id);
+
+				let processor_index = (0..processor_info.len())
+					.filter(|index| remaining[*index].can_host(&required))
+					.min_by_key(|index| processor_info[*index].2.len());
+
+				match processor_index {
+					Some(index) => {
+						remaining[index] = remaining[index].saturating_sub(&required);
+
+						processor_info[index]
+							.2
+							.try_push(group_id)
+							.map_err(|_| Error::<T>::TooManyProcessors)?;
+					},
+					None => Self::deposit_event(Event::GroupUnhostable { group_id }),
 				}
 			}
 
@@ -259,6 +340,23 @@ pub mod pallet {
 			Ok(())
 		}
 
+		pub fn group_members_of(group_id: u32) -> Option<Vec<T::AccountId>> {
+			GroupMembers::<T>::get()
+				.get(group_id as usize)
+				.map(|members| members.clone().into_inner())
+		}
+
+		// Processors hosting the group, with their ip and all their group ids.
+		pub fn processors_of_group(group_id: u32) -> Vec<(T::AccountId, Vec<u8>, Vec<u32>)> {
+			ProcessorInfo::<T>::get()
+				.into_iter()
+				.filter(|(_, _, group_ids)| group_ids.contains(&group_id))
+				.map(|(account, ip_address, group_ids)| {
+					(account, ip_address.into_inner(), group_ids.into_inner())
+				})
+				.collect()
+		}
+
 		pub fn get_group_ids(account: T::AccountId) -> Vec<u32> {
 			let processor_info = ProcessorInfo::<T>::get();
 			if let Some((_, _, group_ids)) = processor_info.iter().find(|(acc, _, _)| *acc == account) {
@@ -328,5 +426,24 @@ pub mod pallet {
 		fn next_round() -> NextRound<BlockNumberFor<T>> {
 			NextRoundStorage::<T>::get()
 		}
+
+		// Whether a processor of the group has capacity left for `required`, besides the apps
+		// of its other groups.
+		fn group_can_host(group_id: u32, required: &Resources) -> bool {
+			if *required == Resources::default() {
+				return true;
+			}
+
+			ProcessorInfo::<T>::get().iter().any(|(account, _, groups)| {
+				groups.contains(&group_id) &&
+					groups
+						.iter()
+						.filter(|group| **group != group_id)
+						.fold(ProcessorResources::<T>::get(account), |remaining, group| {
+							remaining.saturating_sub(&T::GroupResources::group_requirement(*group))
+						})
+						.can_host(required)
+			})
+		}
 	}
 }
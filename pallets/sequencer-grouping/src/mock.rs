@@ -1,4 +1,5 @@
 use crate as pallet_sequencer_grouping;
+use crate::{GroupResources, Resources};
 use frame_support::{derive_impl, parameter_types, traits::Everything};
 use sp_core::{ConstU32, H256};
 use sp_runtime::{
@@ -59,6 +60,24 @@ impl pallet_sequencer_grouping::Config for Test {
 	type MaxGroupNumber = ConstU32<10u32>;
 	type MaxLengthIP = ConstU32<15u32>;
 	type MaxRunningAPP = ConstU32<20u32>;
+	type GroupResources = MockGroupResources;
+}
+
+parameter_types! {
+	pub static GroupRequirements: Vec<(u32, Resources)> = Vec::new();
+}
+
+// Requirements of the apps on each group, set by the tests.
+pub struct MockGroupResources;
+
+impl GroupResources for MockGroupResources {
+	fn group_requirement(group_id: u32) -> Resources {
+		GroupRequirements::get()
+			.into_iter()
+			.find(|(group, _)| *group == group_id)
+			.map(|(_, required)| required)
+			.unwrap_or_default()
+	}
 }
 
 // Build genesis storage according to the mock runtime.
@@ -1,7 +1,7 @@
 use crate::{
 	mock::*,
 	pallet::{GroupNumber, GroupSize},
-	Error, Event, GroupMembers, NextRound, SequencerGroup,
+	CpuArch, Error, Event, GroupMembers, NextRound, ProcessorResources, Resources, SequencerGroup,
 };
 use frame_support::{assert_noop, assert_ok, pallet_prelude::Get};
 use sp_core::bounded::BoundedVec;
@@ -79,7 +79,8 @@ fn trigger_group_works() {
 		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(1),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 
 		assert_ok!(SequencerGrouping::set_group_metric(RuntimeOrigin::root(), 2, 3));
@@ -112,7 +113,8 @@ fn account_in_group_works() {
 		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(1),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 		assert_ok!(SequencerGrouping::set_group_metric(RuntimeOrigin::root(), 2, 3));
 		assert_ok!(SequencerGrouping::trigger_group(vec![1, 2, 3, 4, 5, 6], 1, 1));
@@ -137,7 +139,8 @@ fn account_in_group_fails() {
 		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(1),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 
 		assert_ok!(SequencerGrouping::set_group_metric(RuntimeOrigin::root(), 2, 3));
@@ -158,7 +161,8 @@ fn all_group_ids_works() {
 		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(1),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 
 		assert_ok!(SequencerGrouping::set_group_metric(RuntimeOrigin::root(), 2, 3));
@@ -177,7 +181,8 @@ fn get_next_round_works() {
 		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(1),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 		assert_ok!(SequencerGrouping::trigger_group(vec![1, 2, 3, 4, 5, 6], 16, 3));
 		println!("Group Members: {:?}", GroupMembers::<Test>::get());
@@ -196,7 +201,8 @@ fn register_new_processor_works() {
 		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(account_id),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 
 		let processor_info = SequencerGrouping::processor_info();
@@ -215,11 +221,13 @@ fn update_existing_processor_ip_works() {
 
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(account_id),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(account_id),
-                new_ip_address.clone()
+                new_ip_address.clone(),
+                Resources::default()
             ));
 
 		let processor_info = SequencerGrouping::processor_info();
@@ -237,11 +245,13 @@ fn do_nothing_if_ip_unchanged() {
 
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(account_id),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(account_id),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 
 		let processor_info = SequencerGrouping::processor_info();
@@ -260,12 +270,14 @@ fn too_many_processors_fails() {
 		for i in 1..= max_running_app {
 			assert_ok!(SequencerGrouping::register_processor(
                     RuntimeOrigin::signed(i as u64),
-                    ip_address.clone()
+                    ip_address.clone(),
+                Resources::default()
                 ));
 		}
 
 		assert_noop!(
-                SequencerGrouping::register_processor(RuntimeOrigin::signed(max_running_app as u64 + 1), ip_address.clone()),
+                SequencerGrouping::register_processor(RuntimeOrigin::signed(max_running_app as u64 + 1), ip_address.clone(),
+                Resources::default()),
                 Error::<Test>::TooManyProcessors
             );
 
@@ -281,11 +293,13 @@ fn account_with_group_ids_works() {
 
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(1),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 		assert_ok!(SequencerGrouping::register_processor(
                 RuntimeOrigin::signed(2),
-                ip_address.clone()
+                ip_address.clone(),
+                Resources::default()
             ));
 
 		System::set_block_number(10);
@@ -307,3 +321,83 @@ fn account_with_group_ids_works() {
 	});
 }
 
+
+#[test]
+fn register_processor_stores_capacity() {
+	new_test_ext().execute_with(|| {
+		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
+		let capacity =
+			Resources { cpu_cores: 8, memory_mb: 16384, disk_mb: 512000, arch: Some(CpuArch::X86_64) };
+
+		assert_ok!(SequencerGrouping::register_processor(
+			RuntimeOrigin::signed(1),
+			ip_address.clone(),
+			capacity.clone()
+		));
+		assert_eq!(ProcessorResources::<Test>::get(1), capacity);
+
+		// Re-registering with the same ip still updates the capacity.
+		let capacity = Resources { cpu_cores: 16, ..capacity };
+		assert_ok!(SequencerGrouping::register_processor(
+			RuntimeOrigin::signed(1),
+			ip_address,
+			capacity.clone()
+		));
+		assert_eq!(ProcessorResources::<Test>::get(1), capacity);
+	});
+}
+
+#[test]
+fn assign_processors_respects_requirements() {
+	new_test_ext().execute_with(|| {
+		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
+		let small = Resources { cpu_cores: 2, memory_mb: 4096, disk_mb: 10000, arch: Some(CpuArch::X86_64) };
+		let large = Resources { cpu_cores: 16, memory_mb: 65536, disk_mb: 10000, arch: Some(CpuArch::X86_64) };
+
+		assert_ok!(SequencerGrouping::register_processor(
+			RuntimeOrigin::signed(1),
+			ip_address.clone(),
+			small
+		));
+		assert_ok!(SequencerGrouping::register_processor(
+			RuntimeOrigin::signed(2),
+			ip_address,
+			large
+		));
+
+		// Group 0 runs a heavy app, group 2 an app which needs arm.
+		let heavy = Resources { cpu_cores: 8, memory_mb: 32768, disk_mb: 0, arch: None };
+		let arm = Resources { arch: Some(CpuArch::Aarch64), ..Default::default() };
+		GroupRequirements::set(vec![(0, heavy.clone()), (2, arm.clone())]);
+
+		assert_ok!(SequencerGrouping::assign_processors_to_groups(3));
+		assert_eq!(SequencerGrouping::get_group_ids(1), vec![1]);
+		assert_eq!(SequencerGrouping::get_group_ids(2), vec![0]);
+
+		assert!(SequencerGrouping::group_can_host(1, &Resources::default()));
+		assert!(SequencerGrouping::group_can_host(1, &Resources { cpu_cores: 2, ..Default::default() }));
+		assert!(!SequencerGrouping::group_can_host(1, &heavy));
+		assert!(SequencerGrouping::group_can_host(0, &heavy));
+		assert!(!SequencerGrouping::group_can_host(2, &arm));
+	});
+}
+
+#[test]
+fn assign_processors_reports_unhostable_groups() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let ip_address: BoundedVec<u8, <Test as crate::Config>::MaxLengthIP> = bounded_vec![127, 0, 0, 1];
+		let capacity = Resources { cpu_cores: 4, memory_mb: 8192, disk_mb: 10000, arch: None };
+		assert_ok!(SequencerGrouping::register_processor(RuntimeOrigin::signed(1), ip_address, capacity));
+
+		// Both groups need 3 cores, only the first one fits on the processor.
+		let required = Resources { cpu_cores: 3, ..Default::default() };
+		GroupRequirements::set(vec![(0, required.clone()), (1, required)]);
+
+		assert_ok!(SequencerGrouping::assign_processors_to_groups(2));
+		assert_eq!(SequencerGrouping::get_group_ids(1), vec![0]);
+		System::assert_last_event(RuntimeEvent::SequencerGrouping(Event::GroupUnhostable {
+			group_id: 1,
+		}));
+	});
+}
@@ -86,15 +86,17 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 
 	/// Storage: `SequencerGroupingPallet::ProcessorInfo` (r:1 w:1)
 	/// Proof: `SequencerGroupingPallet::ProcessorInfo` (`max_values`: Some(1), `max_size`: Some(891), added: 1386, mode: `MaxEncodedLen`)
+	/// Storage: `SequencerGroupingPallet::ProcessorResources` (r:1 w:1)
+	/// Proof: `SequencerGroupingPallet::ProcessorResources` (`max_values`: None, `max_size`: Some(62), added: 2537, mode: `MaxEncodedLen`)
 	fn register_processor() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `55`
-		//  Estimated: `2376`
-		// Minimum execution time: 5_000_000 picoseconds.
-		Weight::from_parts(5_000_000, 0)
-			.saturating_add(Weight::from_parts(0, 2376))
-			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(1))
+		//  Estimated: `3527`
+		// Minimum execution time: 7_000_000 picoseconds.
+		Weight::from_parts(7_000_000, 0)
+			.saturating_add(Weight::from_parts(0, 3527))
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(2))
 	}
 }
 
@@ -142,14 +144,16 @@ impl WeightInfo for () {
 
 	/// Storage: `SequencerGroupingPallet::ProcessorInfo` (r:1 w:1)
 	/// Proof: `SequencerGroupingPallet::ProcessorInfo` (`max_values`: Some(1), `max_size`: Some(891), added: 1386, mode: `MaxEncodedLen`)
+	/// Storage: `SequencerGroupingPallet::ProcessorResources` (r:1 w:1)
+	/// Proof: `SequencerGroupingPallet::ProcessorResources` (`max_values`: None, `max_size`: Some(62), added: 2537, mode: `MaxEncodedLen`)
 	fn register_processor() -> Weight {
 		// Proof Size summary in bytes:
 		//  Measured:  `55`
-		//  Estimated: `2376`
-		// Minimum execution time: 5_000_000 picoseconds.
-		Weight::from_parts(5_000_000, 0)
-			.saturating_add(Weight::from_parts(0, 2376))
-			.saturating_add(RocksDbWeight::get().reads(1))
-			.saturating_add(RocksDbWeight::get().writes(1))
+		//  Estimated: `3527`
+		// Minimum execution time: 7_000_000 picoseconds.
+		Weight::from_parts(7_000_000, 0)
+			.saturating_add(Weight::from_parts(0, 3527))
+			.saturating_add(RocksDbWeight::get().reads(2))
+			.saturating_add(RocksDbWeight::get().writes(2))
 	}
 }
@@ -44,6 +44,7 @@ pub mod pallet {
 	};
 	use frame_system::pallet_prelude::*;
 	use pallet_sequencer_grouping::SequencerGroup;
+	use primitives_sequencer_staking::{CandidateStatus, CandidateSummary, DelegatorSummary};
 	use sp_runtime::{
 		traits::{AccountIdConversion, Saturating, Zero},
 		DispatchErrorWithPostInfo, Perbill,
@@ -1139,6 +1140,64 @@ pub mod pallet {
 			T::PalletAccount::get().into_account_truncating()
 		}
 
+		/// Staking summary of every candidate, for the runtime api
+		pub fn candidate_summaries() -> Vec<CandidateSummary<AccountIdOf<T>, AssetBalanceOf<T>>> {
+			let selected = SelectedCandidates::<T>::get();
+			CandidateInfo::<T>::iter()
+				.map(|(account, info)| {
+					let is_selected = selected.contains(&account);
+					Self::to_candidate_summary(account, info, is_selected)
+				})
+				.collect()
+		}
+
+		/// Staking summary of a candidate, for the runtime api
+		pub fn candidate_summary(
+			account: AccountIdOf<T>,
+		) -> Option<CandidateSummary<AccountIdOf<T>, AssetBalanceOf<T>>> {
+			let info = CandidateInfo::<T>::get(&account)?;
+			let is_selected = SelectedCandidates::<T>::get().contains(&account);
+			Some(Self::to_candidate_summary(account, info, is_selected))
+		}
+
+		/// Staking summary of a delegator, for the runtime api
+		pub fn delegator_summary(
+			account: AccountIdOf<T>,
+		) -> Option<DelegatorSummary<AccountIdOf<T>, AssetBalanceOf<T>>> {
+			let state = DelegatorState::<T>::get(&account)?;
+			Some(DelegatorSummary {
+				account,
+				total: state.total,
+				less_total: state.less_total,
+				delegations: state
+					.delegations
+					.0
+					.into_iter()
+					.map(|bond| (bond.owner, bond.amount))
+					.collect(),
+			})
+		}
+
+		fn to_candidate_summary(
+			account: AccountIdOf<T>,
+			info: CandidateMetadata<AssetBalanceOf<T>>,
+			selected: bool,
+		) -> CandidateSummary<AccountIdOf<T>, AssetBalanceOf<T>> {
+			let status = match info.status {
+				SequencerStatus::Active => CandidateStatus::Active,
+				SequencerStatus::Idle => CandidateStatus::Idle,
+				SequencerStatus::Leaving(round) => CandidateStatus::Leaving(round),
+			};
+			CandidateSummary {
+				account,
+				status,
+				bond: info.bond,
+				total_counted: info.total_counted,
+				delegation_count: info.delegation_count,
+				selected,
+			}
+		}
+
 		pub(crate) fn delegate_inner(
 			candidate: AccountIdOf<T>,
 			delegator: AccountIdOf<T>,
@@ -134,6 +134,7 @@ impl pallet_sequencer_grouping::Config for Test {
 	type MaxGroupNumber = ConstU32<10u32>;
 	type MaxLengthIP = ConstU32<15u32>;
 	type MaxRunningAPP = ConstU32<20u32>;
+	type GroupResources = ();
 }
 
 parameter_types! {
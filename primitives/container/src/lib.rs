@@ -1,9 +1,12 @@
 #![cfg_attr(not(feature = "std"), no_std)]
-use codec::{Codec, Decode, Encode};
+use codec::{Codec, Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use sp_core::H256;
-use sp_std::vec::Vec;
+use sp_std::{vec, vec::Vec};
 #[derive(Debug, Clone, TypeInfo, Encode, Decode, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct DownloadInfo {
 	pub app_id: u32,
 	pub app_hash: H256,
@@ -11,20 +14,197 @@ pub struct DownloadInfo {
 	pub size: u32,
 	pub group: u32,
 	pub url: Vec<u8>,
+	pub args: Vec<Vec<u8>>,
+	pub envs: Vec<(Vec<u8>, Vec<u8>)>,
+	pub log: Option<Vec<u8>>,
+	pub is_docker_image: bool,
+	pub docker_image: Option<Vec<u8>>,
+	// All download base urls in the order to try, `url` is the first of them.
+	pub mirrors: Vec<Vec<u8>>,
+	// Tells when a started instance is ready to take over, ready at once when None.
+	pub readiness: Option<ReadinessProbe<Vec<u8>>>,
+}
+
+// `DownloadInfo` as `shuld_load` returns it before v3 of the runtime api.
+#[derive(Debug, Clone, TypeInfo, Encode, Decode, Default)]
+pub struct LegacyDownloadInfo {
+	pub app_id: u32,
+	pub app_hash: H256,
+	pub file_name: Vec<u8>,
+	pub size: u32,
+	pub group: u32,
+	pub url: Vec<u8>,
+	// All arguments in one string, separated by spaces.
 	pub args: Option<Vec<u8>>,
 	pub log: Option<Vec<u8>>,
 	pub is_docker_image: bool,
 	pub docker_image: Option<Vec<u8>>,
 }
 
+impl From<LegacyDownloadInfo> for DownloadInfo {
+	fn from(info: LegacyDownloadInfo) -> Self {
+		let args = info
+			.args
+			.map(|args| {
+				args.split(|byte| *byte == b' ')
+					.filter(|arg| !arg.is_empty())
+					.map(|arg| arg.to_vec())
+					.collect()
+			})
+			.unwrap_or_default();
+
+		DownloadInfo {
+			app_id: info.app_id,
+			app_hash: info.app_hash,
+			file_name: info.file_name,
+			size: info.size,
+			group: info.group,
+			mirrors: vec![info.url.clone()],
+			url: info.url,
+			args,
+			envs: Vec::new(),
+			log: info.log,
+			is_docker_image: info.is_docker_image,
+			docker_image: info.docker_image,
+			readiness: None,
+		}
+	}
+}
+
+// `DownloadInfo` as `shuld_load` and `processor_apps` return it in v3 and v4 of the runtime api,
+// before readiness probes.
+#[derive(Debug, Clone, TypeInfo, Encode, Decode, Default)]
+pub struct DownloadInfoV4 {
+	pub app_id: u32,
+	pub app_hash: H256,
+	pub file_name: Vec<u8>,
+	pub size: u32,
+	pub group: u32,
+	pub url: Vec<u8>,
+	pub args: Vec<Vec<u8>>,
+	pub envs: Vec<(Vec<u8>, Vec<u8>)>,
+	pub log: Option<Vec<u8>>,
+	pub is_docker_image: bool,
+	pub docker_image: Option<Vec<u8>>,
+	pub mirrors: Vec<Vec<u8>>,
+}
+
+impl From<DownloadInfoV4> for DownloadInfo {
+	fn from(info: DownloadInfoV4) -> Self {
+		DownloadInfo {
+			app_id: info.app_id,
+			app_hash: info.app_hash,
+			file_name: info.file_name,
+			size: info.size,
+			group: info.group,
+			url: info.url,
+			args: info.args,
+			envs: info.envs,
+			log: info.log,
+			is_docker_image: info.is_docker_image,
+			docker_image: info.docker_image,
+			mirrors: info.mirrors,
+			readiness: None,
+		}
+	}
+}
+
+// How the node checks that a started instance of an app is ready. Both instances of an app run
+// side by side during a handover, each on its own half of the ports the node reserves for the app,
+// so those ports are probed with the `*InRange` variants. Nodes reject absolute ports among them,
+// absolute ports are for ports the app fixes outside of its range.
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum ReadinessProbe<Bytes> {
+	// A GET of `path` on the local port answers with a 2xx status.
+	Http { port: u16, path: Bytes },
+	// The local port accepts connections.
+	Tcp { port: u16 },
+	// A line of the instance output matches the regular expression.
+	Log { pattern: Bytes },
+	// `Http` on the port `offset` into the port range the node gives the instance,
+	// `{{port_base}}` in the app args.
+	HttpInRange { offset: u16, path: Bytes },
+	// `Tcp` on the port `offset` into the port range of the instance.
+	TcpInRange { offset: u16 },
+}
+
+// Review state of an app, only approved apps are allocated to groups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TypeInfo, Encode, Decode, MaxEncodedLen, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum AppStatus {
+	#[default]
+	Pending,
+	Approved,
+	Rejected,
+	Suspended,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum ContainerApiError {
+	AppNotExist,
+	GroupNotExist,
+	AccountNotInGroup,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct AppSummary<AccountId> {
+	pub app_id: u32,
+	pub creator: AccountId,
+	pub project_name: Vec<u8>,
+	pub status: AppStatus,
+	// Release loaded from the next round on, None until one is uploaded.
+	pub app_hash: Option<H256>,
+	pub file_name: Option<Vec<u8>>,
+	pub is_docker_image: bool,
+	// Groups the app is assigned to.
+	pub groups: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ProcessorSummary<AccountId> {
+	pub account: AccountId,
+	pub ip_address: Vec<u8>,
+	pub groups: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct RoundSchedule<BlockNumber> {
+	pub round_index: u32,
+	pub starting_block: BlockNumber,
+	// Block from which `shuld_load` picks the releases, the later of starting_block and now.
+	pub release_block: BlockNumber,
+}
+
 sp_api::decl_runtime_apis! {
-	#[api_version(2)]
+	#[api_version(5)]
 	pub trait ContainerRuntimeApi<AuthorityId> where
 	AuthorityId:Codec
 	{
+		#[changed_in(3)]
+		fn shuld_load(author:AuthorityId)->Option<LegacyDownloadInfo>;
+		#[changed_in(5)]
+		fn shuld_load(author:AuthorityId)->Option<DownloadInfoV4>;
 		fn shuld_load(author:AuthorityId)->Option<DownloadInfo>;
 		fn should_run()-> bool;
 		fn get_group_id(author:AuthorityId) ->u32;
 		fn get_groups()->Vec<u32>;
+
+		// Since v3.
+		fn group_id_of(author:AuthorityId) -> Result<u32, ContainerApiError>;
+		fn apps() -> Vec<AppSummary<AuthorityId>>;
+		fn app_of_group(group_id:u32) -> Result<Option<AppSummary<AuthorityId>>, ContainerApiError>;
+		fn group_processors(group_id:u32) -> Result<Vec<ProcessorSummary<AuthorityId>>, ContainerApiError>;
+		fn group_members(group_id:u32) -> Result<Vec<AuthorityId>, ContainerApiError>;
+		fn round_schedule() -> RoundSchedule<sp_runtime::traits::NumberFor<Block>>;
+
+		// Since v4.
+		#[changed_in(5)]
+		fn processor_apps(processor:AuthorityId) -> Vec<DownloadInfoV4>;
+		fn processor_apps(processor:AuthorityId) -> Vec<DownloadInfo>;
 	}
 }
@@ -0,0 +1,59 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+use codec::{Codec, Decode, Encode};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_std::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum CandidateStatus {
+	Active,
+	Idle,
+	// Bonded until the inner round
+	Leaving(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct CandidateSummary<AccountId, Balance> {
+	pub account: AccountId,
+	pub status: CandidateStatus,
+	pub bond: Balance,
+	// Self bond plus the top delegations
+	pub total_counted: Balance,
+	pub delegation_count: u32,
+	// Selected to produce blocks in the current round
+	pub selected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct DelegatorSummary<AccountId, Balance> {
+	pub account: AccountId,
+	pub total: Balance,
+	// Pending revocations and bond decreases
+	pub less_total: Balance,
+	// (candidate, amount) of each delegation
+	pub delegations: Vec<(AccountId, Balance)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct StakingRound<BlockNumber> {
+	pub current: u32,
+	pub first: BlockNumber,
+	pub length: u32,
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait SequencerStakingApi<AccountId, Balance> where
+	AccountId:Codec,
+	Balance:Codec
+	{
+		fn candidates() -> Vec<CandidateSummary<AccountId, Balance>>;
+		fn candidate(account:AccountId) -> Option<CandidateSummary<AccountId, Balance>>;
+		fn delegator(account:AccountId) -> Option<DelegatorSummary<AccountId, Balance>>;
+		fn round() -> StakingRound<sp_runtime::traits::NumberFor<Block>>;
+	}
+}
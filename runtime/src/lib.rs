@@ -37,7 +37,7 @@ use frame_support::{
 	genesis_builder_helper::{build_config, create_default_config},
 	parameter_types,
 	sp_runtime::AccountId32,
-	traits::{ConstBool, ConstU32, ConstU64, ConstU8, EitherOfDiverse, TransformOrigin},
+	traits::{ConstBool, ConstU32, ConstU64, ConstU8, EitherOfDiverse, Get, TransformOrigin},
 	weights::{
 		ConstantMultiplier, Weight, WeightToFeeCoefficient, WeightToFeeCoefficients,
 		WeightToFeePolynomial,
@@ -53,7 +53,10 @@ use pallet_sequencer_grouping::SimpleRandomness;
 use pallet_sequencer_staking::WeightInfo;
 use pallet_xcm::{EnsureXcm, IsVoiceOfBody};
 use parachains_common::message_queue::{NarrowOriginToSibling, ParaIdToSibling};
-use primitives_container::DownloadInfo;
+use primitives_container::{
+	AppSummary, ContainerApiError, DownloadInfo, ProcessorSummary, RoundSchedule,
+};
+use primitives_sequencer_staking::{CandidateSummary, DelegatorSummary, StakingRound};
 pub use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::ConstU128;
 use sp_runtime::DispatchErrorWithPostInfo;
@@ -138,8 +141,12 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
+/// Migrations to apply on runtime upgrade.
+pub type Migrations = (pallet_container::migrations::MigrateToV1<Runtime>,);
+
 /// Handles converting a weight scalar to a fee value, based on the scale and granularity of the
 /// node's balance type.
 ///
@@ -200,7 +207,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("popsicle"),
 	impl_name: create_runtime_str!("popsicle"),
 	authoring_version: 1,
-	spec_version: 1000,
+	spec_version: 1001,
 	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -579,6 +586,7 @@ impl pallet_sequencer_grouping::Config for Runtime {
 	type Randomness = SimpleRandomness<Self>;
 	type MaxLengthIP = ConstU32<15>;
 	type MaxRunningAPP = ConstU32<10>;
+	type GroupResources = ContainerPallet;
 }
 
 parameter_types! {
@@ -587,16 +595,35 @@ parameter_types! {
 	pub const MaxUrlLength: u32 = 300;
 	pub const MaxArgCount: u32 = 10;
 	pub const MaxArgLength: u32 = 100;
+	pub const MaxReleases: u32 = 5;
+	pub const MaxMirrors: u32 = 5;
+	pub const AppDepositBase: Balance = deposit(1, 0);
+	pub const AppDepositPerByte: Balance = deposit(0, 1);
 }
 
 impl pallet_container::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = pallet_container::weights::SubstrateWeight<Runtime>;
+	type ApproveOrigin = EnsureRoot<AccountId>;
+	type Currency = Balances;
+	type AppDepositBase = AppDepositBase;
+	type AppDepositPerByte = AppDepositPerByte;
 	type MaxLengthFileName = MaxLengthFileName;
 	type MaxRuningAPP = MaxRuningAPP;
 	type MaxUrlLength = MaxUrlLength;
 	type MaxArgCount = MaxArgCount;
 	type MaxArgLength = MaxArgLength;
+	type MaxReleases = MaxReleases;
+	type MaxMirrors = MaxMirrors;
+	type RoundLength = RoundLength;
+}
+
+/// Length of the current sequencer round.
+pub struct RoundLength;
+impl Get<u32> for RoundLength {
+	fn get() -> u32 {
+		pallet_sequencer_staking::Round::<Runtime>::get().length
+	}
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -795,6 +822,7 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[api_version(5)]
 	impl primitives_container::ContainerRuntimeApi<Block, AccountId32> for Runtime {
 
 		fn shuld_load(author:AccountId32)->Option<DownloadInfo> {
@@ -811,6 +839,53 @@ impl_runtime_apis! {
 		fn should_run()-> bool {
 			ContainerPallet::should_run()
 		}
+
+		fn group_id_of(author:AccountId32) -> Result<u32, ContainerApiError> {
+			ContainerPallet::group_id_of(author)
+		}
+
+		fn apps() -> Vec<AppSummary<AccountId32>> {
+			ContainerPallet::apps()
+		}
+
+		fn app_of_group(group_id:u32) -> Result<Option<AppSummary<AccountId32>>, ContainerApiError> {
+			ContainerPallet::app_of_group(group_id)
+		}
+
+		fn group_processors(group_id:u32) -> Result<Vec<ProcessorSummary<AccountId32>>, ContainerApiError> {
+			ContainerPallet::group_processors(group_id)
+		}
+
+		fn group_members(group_id:u32) -> Result<Vec<AccountId32>, ContainerApiError> {
+			ContainerPallet::group_members(group_id)
+		}
+
+		fn round_schedule() -> RoundSchedule<BlockNumber> {
+			ContainerPallet::round_schedule()
+		}
+
+		fn processor_apps(processor:AccountId32) -> Vec<DownloadInfo> {
+			ContainerPallet::processor_apps(processor)
+		}
+	}
+
+	impl primitives_sequencer_staking::SequencerStakingApi<Block, AccountId, Balance> for Runtime {
+		fn candidates() -> Vec<CandidateSummary<AccountId, Balance>> {
+			SequencerStaking::candidate_summaries()
+		}
+
+		fn candidate(account: AccountId) -> Option<CandidateSummary<AccountId, Balance>> {
+			SequencerStaking::candidate_summary(account)
+		}
+
+		fn delegator(account: AccountId) -> Option<DelegatorSummary<AccountId, Balance>> {
+			SequencerStaking::delegator_summary(account)
+		}
+
+		fn round() -> StakingRound<BlockNumber> {
+			let round = pallet_sequencer_staking::Round::<Runtime>::get();
+			StakingRound { current: round.current, first: round.first, length: round.length }
+		}
 	}
 
 	impl cumulus_primitives_core::CollectCollationInfo<Block> for Runtime {